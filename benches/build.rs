@@ -3,7 +3,7 @@ use std::path::Path;
 use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
 use tempfile::{tempdir, TempDir};
 
-use librojo::cli::BuildCommand;
+use librojo::cli::{BuildCommand, Compression, HashAlgorithm, XmlFormat};
 
 pub fn benchmark_small_place(c: &mut Criterion) {
     bench_build_place(c, "Small Place", "test-projects/benchmark_small_place")
@@ -37,6 +37,14 @@ fn place_setup<P: AsRef<Path>>(input_path: P) -> (TempDir, BuildCommand) {
         project: input,
         watch: false,
         plugin: None,
+        unpacked: None,
+        kind: None,
+        hash: HashAlgorithm::None,
+        compression: Compression::Fast,
+        xml_format: XmlFormat::Pretty,
+        select: None,
+        exclude_class: Vec::new(),
+        strict: false,
         output,
     };
 
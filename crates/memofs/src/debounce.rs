@@ -0,0 +1,239 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{Receiver, RecvTimeoutError};
+
+use crate::VfsEvent;
+
+/// Spawns a thread that reads events from `source` and re-emits them on the
+/// returned receiver, coalescing events for the same path that arrive within
+/// `window` of the first event in a batch.
+pub(crate) fn debounce(source: Receiver<VfsEvent>, window: Duration) -> Receiver<VfsEvent> {
+    batched(source, window, |events| {
+        let mut batch = Batch::default();
+
+        for event in events {
+            batch.push(event);
+        }
+
+        batch.events
+    })
+}
+
+/// Spawns a thread that reads events from `source` and re-emits them on the
+/// returned receiver, dropping removals of paths whose ancestors were removed
+/// within the same `window`. Removing a directory then only results in a
+/// single event, rather than one for every one of its descendants.
+pub(crate) fn coalesce_dir_removes(
+    source: Receiver<VfsEvent>,
+    window: Duration,
+) -> Receiver<VfsEvent> {
+    batched(source, window, |events| {
+        let removed: HashSet<PathBuf> = events
+            .iter()
+            .filter_map(|event| match event {
+                VfsEvent::Remove(path) => Some(path.clone()),
+                _ => None,
+            })
+            .collect();
+
+        events
+            .into_iter()
+            .filter(|event| match event {
+                VfsEvent::Remove(path) => !path
+                    .ancestors()
+                    .skip(1)
+                    .any(|ancestor| removed.contains(ancestor)),
+                _ => true,
+            })
+            .collect()
+    })
+}
+
+/// Spawns a thread that collects events from `source` into batches, starting
+/// with an event and ending `window` after it, and re-emits each batch on the
+/// returned receiver after passing it through `process`.
+fn batched<F>(source: Receiver<VfsEvent>, window: Duration, process: F) -> Receiver<VfsEvent>
+where
+    F: Fn(Vec<VfsEvent>) -> Vec<VfsEvent> + Send + 'static,
+{
+    let (tx, rx) = crossbeam_channel::unbounded();
+
+    thread::spawn(move || {
+        while let Ok(first) = source.recv() {
+            let mut batch = vec![first];
+
+            let deadline = Instant::now() + window;
+            let disconnected = loop {
+                match source.recv_deadline(deadline) {
+                    Ok(event) => batch.push(event),
+                    Err(RecvTimeoutError::Timeout) => break false,
+                    Err(RecvTimeoutError::Disconnected) => break true,
+                }
+            };
+
+            for event in process(batch) {
+                if tx.send(event).is_err() {
+                    return;
+                }
+            }
+
+            if disconnected {
+                return;
+            }
+        }
+    });
+
+    rx
+}
+
+/// A set of pending events, kept in the order their paths were first seen.
+#[derive(Default)]
+struct Batch {
+    events: Vec<VfsEvent>,
+    indices: HashMap<PathBuf, usize>,
+}
+
+impl Batch {
+    fn push(&mut self, event: VfsEvent) {
+        let path = match &event {
+            VfsEvent::Create(path) | VfsEvent::Write(path) | VfsEvent::Remove(path) => path.clone(),
+
+            // Renames involve two paths, so they're kept as-is. Later events
+            // for either path start over so that they stay after the rename.
+            VfsEvent::Rename { from, to } => {
+                self.indices.remove(from);
+                self.indices.remove(to);
+                self.events.push(event);
+                return;
+            }
+        };
+
+        match self.indices.get(&path) {
+            Some(&index) => {
+                let merged = merge(&self.events[index], event);
+                self.events[index] = merged;
+            }
+            None => {
+                self.indices.insert(path, self.events.len());
+                self.events.push(event);
+            }
+        }
+    }
+}
+
+/// Combines two events for the same path into the single event that best
+/// describes both of them happening in order.
+fn merge(old: &VfsEvent, new: VfsEvent) -> VfsEvent {
+    match (old, new) {
+        (_, VfsEvent::Remove(path)) => VfsEvent::Remove(path),
+        (_, rename @ VfsEvent::Rename { .. }) => rename,
+        (VfsEvent::Create(_), VfsEvent::Create(path)) => VfsEvent::Create(path),
+        (_, VfsEvent::Create(path)) | (_, VfsEvent::Write(path)) => VfsEvent::Write(path),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path::Path;
+
+    use crate::{InMemoryFs, VfsBackend};
+
+    fn send_all(events: Vec<VfsEvent>) -> Vec<String> {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let debounced = debounce(rx, Duration::from_millis(50));
+
+        for event in events {
+            tx.send(event).unwrap();
+        }
+        drop(tx);
+
+        debounced
+            .iter()
+            .map(|event| format!("{:?}", event))
+            .collect()
+    }
+
+    #[test]
+    fn coalesces_per_path() {
+        let a = Path::new("/a.lua").to_path_buf();
+        let b = Path::new("/b.lua").to_path_buf();
+
+        let events = send_all(vec![
+            VfsEvent::Create(a.clone()),
+            VfsEvent::Create(b.clone()),
+            VfsEvent::Write(a.clone()),
+            VfsEvent::Write(a),
+            VfsEvent::Write(b.clone()),
+            VfsEvent::Remove(b),
+        ]);
+
+        assert_eq!(events, vec![r#"Write("/a.lua")"#, r#"Remove("/b.lua")"#]);
+    }
+
+    #[test]
+    fn keeps_renames_in_order() {
+        let a = PathBuf::from("/a.lua");
+        let b = PathBuf::from("/b.lua");
+
+        let events = send_all(vec![
+            VfsEvent::Write(a.clone()),
+            VfsEvent::Rename {
+                from: a.clone(),
+                to: b.clone(),
+            },
+            VfsEvent::Write(b.clone()),
+            VfsEvent::Write(b),
+        ]);
+
+        assert_eq!(
+            events,
+            vec![
+                r#"Write("/a.lua")"#,
+                r#"Rename { from: "/a.lua", to: "/b.lua" }"#,
+                r#"Write("/b.lua")"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn coalesces_recursive_remove() {
+        let mut imfs = InMemoryFs::new();
+        let coalesced = coalesce_dir_removes(imfs.event_receiver(), Duration::from_millis(50));
+
+        // Removing a directory is reported the same way notify reports it,
+        // with the directory's descendants first.
+        for path in ["/src/dir/a.lua", "/src/dir/nested/b.lua", "/src/dir/nested"] {
+            imfs.raise_event(VfsEvent::Remove(PathBuf::from(path)));
+        }
+        imfs.raise_event(VfsEvent::Write(PathBuf::from("/src/other.lua")));
+        imfs.raise_event(VfsEvent::Remove(PathBuf::from("/src/dir")));
+        imfs.raise_event(VfsEvent::Remove(PathBuf::from("/src/lone.lua")));
+        drop(imfs);
+
+        let events: Vec<String> = coalesced
+            .iter()
+            .map(|event| format!("{:?}", event))
+            .collect();
+
+        assert_eq!(
+            events,
+            vec![
+                r#"Write("/src/other.lua")"#,
+                r#"Remove("/src/dir")"#,
+                r#"Remove("/src/lone.lua")"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_lone_create() {
+        let events = send_all(vec![VfsEvent::Create(PathBuf::from("/a.lua"))]);
+
+        assert_eq!(events, vec![r#"Create("/a.lua")"#]);
+    }
+}
@@ -2,6 +2,7 @@ use std::collections::{BTreeSet, HashMap, VecDeque};
 use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
 use crossbeam_channel::{Receiver, Sender};
 
@@ -48,6 +49,13 @@ impl InMemoryFs {
         let inner = self.inner.lock().unwrap();
         inner.event_sender.send(event).unwrap();
     }
+
+    /// Returns how many times the contents of a file have been read from
+    /// this filesystem. This is useful in tests that check whether files are
+    /// read again when they haven't changed.
+    pub fn read_count(&self) -> usize {
+        self.inner.lock().unwrap().read_count
+    }
 }
 
 impl Default for InMemoryFs {
@@ -60,6 +68,7 @@ impl Default for InMemoryFs {
 struct InMemoryFsInner {
     entries: HashMap<PathBuf, Entry>,
     orphans: BTreeSet<PathBuf>,
+    read_count: usize,
 
     event_receiver: Receiver<VfsEvent>,
     event_sender: Sender<VfsEvent>,
@@ -72,6 +81,7 @@ impl InMemoryFsInner {
         Self {
             entries: HashMap::new(),
             orphans: BTreeSet::new(),
+            read_count: 0,
             event_receiver,
             event_sender,
         }
@@ -94,7 +104,13 @@ impl InMemoryFsInner {
 
         match snapshot {
             VfsSnapshot::File { contents } => {
-                self.entries.insert(path, Entry::File { contents });
+                self.entries.insert(
+                    path,
+                    Entry::File {
+                        contents,
+                        modified: SystemTime::now(),
+                    },
+                );
             }
             VfsSnapshot::Dir { children } => {
                 self.entries.insert(
@@ -114,6 +130,24 @@ impl InMemoryFsInner {
         Ok(())
     }
 
+    /// Builds a snapshot of everything at the given path, which must exist.
+    fn snapshot(&self, path: &Path) -> VfsSnapshot {
+        match &self.entries[path] {
+            Entry::File { contents, .. } => VfsSnapshot::File {
+                contents: contents.clone(),
+            },
+            Entry::Dir { children } => VfsSnapshot::Dir {
+                children: children
+                    .iter()
+                    .map(|child| {
+                        let name = child.file_name().unwrap().to_string_lossy().into_owned();
+                        (name, self.snapshot(child))
+                    })
+                    .collect(),
+            },
+        }
+    }
+
     fn remove(&mut self, root_path: PathBuf) {
         self.orphans.remove(&root_path);
 
@@ -130,17 +164,26 @@ impl InMemoryFsInner {
 
 #[derive(Debug)]
 enum Entry {
-    File { contents: Vec<u8> },
-
-    Dir { children: BTreeSet<PathBuf> },
+    File {
+        contents: Vec<u8>,
+        modified: SystemTime,
+    },
+
+    Dir {
+        children: BTreeSet<PathBuf>,
+    },
 }
 
 impl VfsBackend for InMemoryFs {
     fn read(&mut self, path: &Path) -> io::Result<Vec<u8>> {
-        let inner = self.inner.lock().unwrap();
+        let mut inner = self.inner.lock().unwrap();
 
         match inner.entries.get(path) {
-            Some(Entry::File { contents }) => Ok(contents.clone()),
+            Some(Entry::File { contents, .. }) => {
+                let contents = contents.clone();
+                inner.read_count += 1;
+                Ok(contents)
+            }
             Some(Entry::Dir { .. }) => must_be_file(path),
             None => not_found(path),
         }
@@ -202,12 +245,68 @@ impl VfsBackend for InMemoryFs {
         }
     }
 
+    fn rename(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+
+        if !inner.entries.contains_key(from) {
+            return not_found(from);
+        }
+
+        if let Some(parent) = to.parent() {
+            if let Some(Entry::File { .. }) = inner.entries.get(parent) {
+                return must_be_dir(parent);
+            }
+        }
+
+        let snapshot = inner.snapshot(from);
+        inner.remove(from.to_owned());
+
+        if let Some(Entry::Dir { children }) = from
+            .parent()
+            .and_then(|parent| inner.entries.get_mut(parent))
+        {
+            children.remove(from);
+        }
+
+        inner.load_snapshot(to.to_owned(), snapshot)
+    }
+
+    fn create_dir_all(&mut self, path: &Path) -> io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+
+        let mut missing: Vec<&Path> = Vec::new();
+        for ancestor in path.ancestors() {
+            match inner.entries.get(ancestor) {
+                Some(Entry::Dir { .. }) => break,
+                Some(Entry::File { .. }) => return must_be_dir(ancestor),
+                None if ancestor.as_os_str().is_empty() => break,
+                None => missing.push(ancestor),
+            }
+        }
+
+        for dir in missing.into_iter().rev() {
+            inner.load_snapshot(dir.to_owned(), VfsSnapshot::empty_dir())?;
+        }
+
+        Ok(())
+    }
+
     fn metadata(&mut self, path: &Path) -> io::Result<Metadata> {
         let inner = self.inner.lock().unwrap();
 
         match inner.entries.get(path) {
-            Some(Entry::File { .. }) => Ok(Metadata { is_file: true }),
-            Some(Entry::Dir { .. }) => Ok(Metadata { is_file: false }),
+            Some(Entry::File { contents, modified }) => Ok(Metadata {
+                is_file: true,
+                is_symlink: false,
+                len: contents.len() as u64,
+                modified: Some(*modified),
+            }),
+            Some(Entry::Dir { .. }) => Ok(Metadata {
+                is_file: false,
+                is_symlink: false,
+                len: 0,
+                modified: None,
+            }),
             None => not_found(path),
         }
     }
@@ -225,29 +324,27 @@ impl VfsBackend for InMemoryFs {
     fn unwatch(&mut self, _path: &Path) -> io::Result<()> {
         Ok(())
     }
+
+    fn unwatch_all(&mut self) -> io::Result<()> {
+        Ok(())
+    }
 }
 
-fn must_be_file<T>(path: &Path) -> io::Result<T> {
-    Err(io::Error::new(
-        io::ErrorKind::Other,
-        format!(
-            "path {} was a directory, but must be a file",
-            path.display()
-        ),
-    ))
+pub(crate) fn must_be_file<T>(path: &Path) -> io::Result<T> {
+    Err(io::Error::other(format!(
+        "path {} was a directory, but must be a file",
+        path.display()
+    )))
 }
 
-fn must_be_dir<T>(path: &Path) -> io::Result<T> {
-    Err(io::Error::new(
-        io::ErrorKind::Other,
-        format!(
-            "path {} was a file, but must be a directory",
-            path.display()
-        ),
-    ))
+pub(crate) fn must_be_dir<T>(path: &Path) -> io::Result<T> {
+    Err(io::Error::other(format!(
+        "path {} was a file, but must be a directory",
+        path.display()
+    )))
 }
 
-fn not_found<T>(path: &Path) -> io::Result<T> {
+pub(crate) fn not_found<T>(path: &Path) -> io::Result<T> {
     Err(io::Error::new(
         io::ErrorKind::NotFound,
         format!("path {} not found", path.display()),
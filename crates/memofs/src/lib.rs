@@ -11,25 +11,36 @@ memofs is currently an unstable minimum viable library. Its primary consumer is
     * `StdBackend`, which uses `std::fs` and the `notify` crate
     * `NoopBackend`, which always throws errors
     * `InMemoryFs`, a simple in-memory filesystem useful for testing
+    * `MemoryBackend`, a flat map of paths to contents with implicit directories
+    * `OverlayBackend`, which layers a writable backend over a read-only one
+    * `ZipBackend`, which reads from a zip archive
 
 ## Future Features
 * Hash-based hierarchical memoization keys (hence the name)
 * Configurable caching (write-through, write-around, write-back)
 */
 
+mod debounce;
 mod in_memory_fs;
+mod memory_backend;
 mod noop_backend;
+mod overlay_backend;
 mod snapshot;
 mod std_backend;
+mod zip_backend;
 
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
 use std::{io, str};
 
 pub use in_memory_fs::InMemoryFs;
+pub use memory_backend::MemoryBackend;
 pub use noop_backend::NoopBackend;
+pub use overlay_backend::OverlayBackend;
 pub use snapshot::VfsSnapshot;
 pub use std_backend::StdBackend;
+pub use zip_backend::ZipBackend;
 
 mod sealed {
     use super::*;
@@ -40,6 +51,9 @@ mod sealed {
     impl Sealed for NoopBackend {}
     impl Sealed for StdBackend {}
     impl Sealed for InMemoryFs {}
+    impl Sealed for MemoryBackend {}
+    impl Sealed for OverlayBackend {}
+    impl Sealed for ZipBackend {}
 }
 
 /// Trait that transforms `io::Result<T>` into `io::Result<Option<T>>`.
@@ -69,15 +83,79 @@ impl<T> IoResultExt<T> for io::Result<T> {
 /// This trait is sealed and cannot not be implemented outside this crate.
 pub trait VfsBackend: sealed::Sealed + Send + 'static {
     fn read(&mut self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Reads at most `max` bytes from the start of the file at `path`.
+    ///
+    /// The default implementation reads the entire file and truncates it.
+    fn read_prefix(&mut self, path: &Path, max: usize) -> io::Result<Vec<u8>> {
+        let mut contents = self.read(path)?;
+        contents.truncate(max);
+        Ok(contents)
+    }
+
     fn write(&mut self, path: &Path, data: &[u8]) -> io::Result<()>;
+
+    /// Writes several files so that either all of them are written or, if
+    /// any write fails, none of them are.
+    ///
+    /// The default implementation reads the current contents of every file
+    /// first, then writes the files one at a time and, if one fails, puts back
+    /// the contents of the files written before it as well as it can.
+    fn write_batch(&mut self, writes: &[(PathBuf, Vec<u8>)]) -> io::Result<()> {
+        // Everything is read before anything is written, so failing to read a
+        // file can't leave the batch half written.
+        let mut previous = Vec::with_capacity(writes.len());
+        for (path, _) in writes {
+            previous.push(self.read(path).with_not_found()?);
+        }
+
+        for (index, (path, data)) in writes.iter().enumerate() {
+            if let Err(err) = self.write(path, data) {
+                previous.truncate(index);
+
+                for ((path, _), previous) in writes[..index].iter().zip(previous).rev() {
+                    let _ = match previous {
+                        Some(contents) => self.write(path, &contents),
+                        None => self.remove_file(path),
+                    };
+                }
+
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
     fn read_dir(&mut self, path: &Path) -> io::Result<ReadDir>;
     fn metadata(&mut self, path: &Path) -> io::Result<Metadata>;
+
+    /// Queries metadata about the given path without following symbolic
+    /// links.
+    ///
+    /// The default implementation is the same as `metadata`, which suits
+    /// backends that don't have symbolic links.
+    fn symlink_metadata(&mut self, path: &Path) -> io::Result<Metadata> {
+        self.metadata(path)
+    }
+
     fn remove_file(&mut self, path: &Path) -> io::Result<()>;
     fn remove_dir_all(&mut self, path: &Path) -> io::Result<()>;
+    fn rename(&mut self, from: &Path, to: &Path) -> io::Result<()>;
+    fn create_dir_all(&mut self, path: &Path) -> io::Result<()>;
 
     fn event_receiver(&self) -> crossbeam_channel::Receiver<VfsEvent>;
     fn watch(&mut self, path: &Path) -> io::Result<()>;
     fn unwatch(&mut self, path: &Path) -> io::Result<()>;
+    fn unwatch_all(&mut self) -> io::Result<()>;
+
+    /// Returns every path that is being watched, in sorted order.
+    ///
+    /// The default implementation returns nothing, which suits backends that
+    /// don't need to watch paths to raise events.
+    fn watched_paths(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
 }
 
 /// Vfs equivalent to [`std::fs::DirEntry`][std::fs::DirEntry].
@@ -114,6 +192,9 @@ impl Iterator for ReadDir {
 #[derive(Debug)]
 pub struct Metadata {
     pub(crate) is_file: bool,
+    pub(crate) is_symlink: bool,
+    pub(crate) len: u64,
+    pub(crate) modified: Option<SystemTime>,
 }
 
 impl Metadata {
@@ -122,7 +203,28 @@ impl Metadata {
     }
 
     pub fn is_dir(&self) -> bool {
-        !self.is_file
+        !self.is_file && !self.is_symlink
+    }
+
+    /// Tells whether this metadata describes a symbolic link. This can only
+    /// be true for metadata from `symlink_metadata`, since `metadata` follows
+    /// links to whatever they point to.
+    pub fn is_symlink(&self) -> bool {
+        self.is_symlink
+    }
+
+    /// The size of the file in bytes at the time this metadata was queried.
+    ///
+    /// For directories, this is whatever the backend reports, which is
+    /// platform-specific for `StdBackend`.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// The last time the file was modified, if the backend keeps track of it.
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
     }
 }
 
@@ -134,6 +236,13 @@ pub enum VfsEvent {
     Create(PathBuf),
     Write(PathBuf),
     Remove(PathBuf),
+
+    /// A file or directory was moved. Backends only raise this when they can
+    /// tell both paths apart, and raise a `Remove` and `Create` otherwise.
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+    },
 }
 
 /// Contains implementation details of the Vfs, wrapped by `Vfs` and `VfsLock`,
@@ -155,6 +264,24 @@ impl VfsInner {
         Ok(Arc::new(contents))
     }
 
+    fn read_no_watch<P: AsRef<Path>>(&mut self, path: P) -> io::Result<Arc<Vec<u8>>> {
+        let path = path.as_ref();
+        let contents = self.backend.read(path)?;
+
+        Ok(Arc::new(contents))
+    }
+
+    fn read_prefix<P: AsRef<Path>>(&mut self, path: P, max: usize) -> io::Result<Arc<Vec<u8>>> {
+        let path = path.as_ref();
+        let contents = self.backend.read_prefix(path, max)?;
+
+        if self.watch_enabled {
+            self.backend.watch(path)?;
+        }
+
+        Ok(Arc::new(contents))
+    }
+
     fn read_to_string<P: AsRef<Path>>(&mut self, path: P) -> io::Result<Arc<String>> {
         let path = path.as_ref();
         let contents = self.backend.read(path)?;
@@ -179,6 +306,10 @@ impl VfsInner {
         self.backend.write(path, contents)
     }
 
+    fn write_batch(&mut self, writes: &[(PathBuf, Vec<u8>)]) -> io::Result<()> {
+        self.backend.write_batch(writes)
+    }
+
     fn read_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<ReadDir> {
         let path = path.as_ref();
         let dir = self.backend.read_dir(path)?;
@@ -190,6 +321,11 @@ impl VfsInner {
         Ok(dir)
     }
 
+    fn read_dir_no_watch<P: AsRef<Path>>(&mut self, path: P) -> io::Result<ReadDir> {
+        let path = path.as_ref();
+        self.backend.read_dir(path)
+    }
+
     fn remove_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
         let path = path.as_ref();
         let _ = self.backend.unwatch(path);
@@ -202,18 +338,67 @@ impl VfsInner {
         self.backend.remove_dir_all(path)
     }
 
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<()> {
+        self.backend.rename(from.as_ref(), to.as_ref())
+    }
+
+    fn create_dir_all<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        self.backend.create_dir_all(path)
+    }
+
     fn metadata<P: AsRef<Path>>(&mut self, path: P) -> io::Result<Metadata> {
         let path = path.as_ref();
         self.backend.metadata(path)
     }
 
+    fn symlink_metadata<P: AsRef<Path>>(&mut self, path: P) -> io::Result<Metadata> {
+        let path = path.as_ref();
+        self.backend.symlink_metadata(path)
+    }
+
+    fn exists<P: AsRef<Path>>(&mut self, path: P) -> io::Result<bool> {
+        let path = path.as_ref();
+        Ok(self.backend.metadata(path).with_not_found()?.is_some())
+    }
+
     fn event_receiver(&self) -> crossbeam_channel::Receiver<VfsEvent> {
         self.backend.event_receiver()
     }
 
+    fn unwatch_all(&mut self) -> io::Result<()> {
+        self.backend.unwatch_all()
+    }
+
+    fn watched_paths(&self) -> Vec<PathBuf> {
+        self.backend.watched_paths()
+    }
+
+    fn watch_root_for(&self, path: &Path) -> Option<PathBuf> {
+        let watched_paths = self.backend.watched_paths();
+
+        path.ancestors()
+            .find(|ancestor| {
+                watched_paths
+                    .binary_search_by(|watched| watched.as_path().cmp(ancestor))
+                    .is_ok()
+            })
+            .map(Path::to_path_buf)
+    }
+
     fn commit_event(&mut self, event: &VfsEvent) -> io::Result<()> {
-        if let VfsEvent::Remove(path) = event {
-            let _ = self.backend.unwatch(path);
+        match event {
+            VfsEvent::Remove(path) => {
+                let _ = self.backend.unwatch(path);
+            }
+            VfsEvent::Rename { from, to } => {
+                let _ = self.backend.unwatch(from);
+
+                if self.watch_enabled {
+                    let _ = self.backend.watch(to);
+                }
+            }
+            _ => {}
         }
 
         Ok(())
@@ -276,6 +461,36 @@ impl Vfs {
         self.inner.lock().unwrap().read(path)
     }
 
+    /// Read a file from the VFS without watching it for changes, regardless
+    /// of whether watching is enabled.
+    ///
+    /// This is useful for files that change often but whose changes aren't
+    /// interesting, like generated or scratch files.
+    #[inline]
+    pub fn read_no_watch<P: AsRef<Path>>(&self, path: P) -> io::Result<Arc<Vec<u8>>> {
+        let path = path.as_ref();
+        self.inner.lock().unwrap().read_no_watch(path)
+    }
+
+    /// Read at most `max` bytes from the start of a file. This is useful for
+    /// inspecting a file's header without reading all of it.
+    ///
+    /// The file is watched the same way as it would be by `read`.
+    #[inline]
+    pub fn read_prefix<P: AsRef<Path>>(&self, path: P, max: usize) -> io::Result<Arc<Vec<u8>>> {
+        let path = path.as_ref();
+        self.inner.lock().unwrap().read_prefix(path, max)
+    }
+
+    /// Read several files from the VFS while only taking the lock once.
+    ///
+    /// Each path behaves the same as if it were passed to `read`. The results
+    /// are returned in the same order as `paths`, and an error reading one
+    /// path does not stop the others from being read.
+    pub fn read_many<P: AsRef<Path>>(&self, paths: &[P]) -> Vec<io::Result<Arc<Vec<u8>>>> {
+        self.lock().read_many(paths)
+    }
+
     /// Read a file from the VFS (or from the underlying backend if it isn't
     /// resident) into a string.
     ///
@@ -315,6 +530,20 @@ impl Vfs {
         self.inner.lock().unwrap().write(path, contents)
     }
 
+    /// Write several files at once, under a single lock. If any of the writes
+    /// fail, the files that were already written are put back the way they
+    /// were, so that files that describe the same thing can't end up out of
+    /// sync with each other.
+    ///
+    /// `MemoryBackend` either writes every file or none of them. `StdBackend`
+    /// writes each file next to where it's going first and then moves them
+    /// all into place, which keeps the chance of only some files changing as
+    /// small as it can.
+    #[inline]
+    pub fn write_batch(&self, writes: &[(PathBuf, Vec<u8>)]) -> io::Result<()> {
+        self.inner.lock().unwrap().write_batch(writes)
+    }
+
     /// Read all of the children of a directory.
     ///
     /// Roughly equivalent to [`std::fs::read_dir`][std::fs::read_dir].
@@ -326,6 +555,14 @@ impl Vfs {
         self.inner.lock().unwrap().read_dir(path)
     }
 
+    /// Read all of the children of a directory without watching it for
+    /// changes, regardless of whether watching is enabled.
+    #[inline]
+    pub fn read_dir_no_watch<P: AsRef<Path>>(&self, path: P) -> io::Result<ReadDir> {
+        let path = path.as_ref();
+        self.inner.lock().unwrap().read_dir_no_watch(path)
+    }
+
     /// Remove a file.
     ///
     /// Roughly equivalent to [`std::fs::remove_file`][std::fs::remove_file].
@@ -348,6 +585,30 @@ impl Vfs {
         self.inner.lock().unwrap().remove_dir_all(path)
     }
 
+    /// Rename a file or directory, replacing `to` if it's a file that already
+    /// exists. Paths being watched beneath `from` are watched at their new
+    /// location instead.
+    ///
+    /// Roughly equivalent to [`std::fs::rename`][std::fs::rename].
+    ///
+    /// [std::fs::rename]: https://doc.rust-lang.org/stable/std/fs/fn.rename.html
+    #[inline]
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> io::Result<()> {
+        self.inner.lock().unwrap().rename(from, to)
+    }
+
+    /// Create a directory and any of its ancestors that don't exist yet.
+    /// Succeeds without doing anything if the directory already exists.
+    ///
+    /// Roughly equivalent to [`std::fs::create_dir_all`][std::fs::create_dir_all].
+    ///
+    /// [std::fs::create_dir_all]: https://doc.rust-lang.org/stable/std/fs/fn.create_dir_all.html
+    #[inline]
+    pub fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        self.inner.lock().unwrap().create_dir_all(path)
+    }
+
     /// Query metadata about the given path.
     ///
     /// Roughly equivalent to [`std::fs::metadata`][std::fs::metadata].
@@ -359,12 +620,80 @@ impl Vfs {
         self.inner.lock().unwrap().metadata(path)
     }
 
+    /// Query metadata about the given path without following symbolic links.
+    ///
+    /// Roughly equivalent to [`std::fs::symlink_metadata`][std::fs::symlink_metadata].
+    ///
+    /// [std::fs::symlink_metadata]: https://doc.rust-lang.org/stable/std/fs/fn.symlink_metadata.html
+    #[inline]
+    pub fn symlink_metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        let path = path.as_ref();
+        self.inner.lock().unwrap().symlink_metadata(path)
+    }
+
+    /// Tells whether the given path exists.
+    ///
+    /// Unlike [`std::path::Path::exists`][std::path::Path::exists], errors other
+    /// than the path not being found are returned instead of being treated as
+    /// the path not existing.
+    ///
+    /// [std::path::Path::exists]: https://doc.rust-lang.org/stable/std/path/struct.Path.html#method.exists
+    #[inline]
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        let path = path.as_ref();
+        self.inner.lock().unwrap().exists(path)
+    }
+
     /// Retrieve a handle to the event receiver for this `Vfs`.
     #[inline]
     pub fn event_receiver(&self) -> crossbeam_channel::Receiver<VfsEvent> {
         self.inner.lock().unwrap().event_receiver()
     }
 
+    /// Retrieve a handle to a receiver that coalesces events for the same path
+    /// arriving within `window` of each other into a single event.
+    ///
+    /// A `Create` and a `Write` for the same path are reported as a `Write`,
+    /// and any event followed by a `Remove` is reported as a `Remove`.
+    ///
+    /// Each call spawns a new thread to read from the event receiver. Because
+    /// receivers share their events, this should not be mixed with other
+    /// calls to `event_receiver`.
+    pub fn debounced_event_receiver(
+        &self,
+        window: Duration,
+    ) -> crossbeam_channel::Receiver<VfsEvent> {
+        debounce::debounce(self.event_receiver(), window)
+    }
+
+    /// Stop watching every path that is currently being watched.
+    ///
+    /// Paths will be watched again the next time they are read.
+    #[inline]
+    pub fn unwatch_all(&self) -> io::Result<()> {
+        self.inner.lock().unwrap().unwatch_all()
+    }
+
+    /// Returns every path that is currently being watched, in sorted order.
+    /// This is meant for diagnosing why changes to a file aren't noticed.
+    ///
+    /// Watching a directory also watches everything inside of it, so paths
+    /// inside of a watched directory are not listed separately.
+    #[inline]
+    pub fn watched_paths(&self) -> Vec<PathBuf> {
+        self.inner.lock().unwrap().watched_paths()
+    }
+
+    /// Returns the nearest watched path that covers the given path, which is
+    /// either the path itself or one of its ancestors. Changes to the path are
+    /// noticed because of that watch.
+    ///
+    /// Returns `None` if nothing covering the path is being watched.
+    #[inline]
+    pub fn watch_root_for<P: AsRef<Path>>(&self, path: P) -> Option<PathBuf> {
+        self.inner.lock().unwrap().watch_root_for(path.as_ref())
+    }
+
     /// Commit an event to this `Vfs`.
     #[inline]
     pub fn commit_event(&self, event: &VfsEvent) -> io::Result<()> {
@@ -401,6 +730,29 @@ impl VfsLock<'_> {
         self.inner.read(path)
     }
 
+    /// Read a file from the VFS without watching it for changes, regardless
+    /// of whether watching is enabled.
+    #[inline]
+    pub fn read_no_watch<P: AsRef<Path>>(&mut self, path: P) -> io::Result<Arc<Vec<u8>>> {
+        let path = path.as_ref();
+        self.inner.read_no_watch(path)
+    }
+
+    /// Read at most `max` bytes from the start of a file.
+    #[inline]
+    pub fn read_prefix<P: AsRef<Path>>(&mut self, path: P, max: usize) -> io::Result<Arc<Vec<u8>>> {
+        let path = path.as_ref();
+        self.inner.read_prefix(path, max)
+    }
+
+    /// Read several files from the VFS.
+    ///
+    /// Each path behaves the same as if it were passed to `read`. The results
+    /// are returned in the same order as `paths`.
+    pub fn read_many<P: AsRef<Path>>(&mut self, paths: &[P]) -> Vec<io::Result<Arc<Vec<u8>>>> {
+        paths.iter().map(|path| self.inner.read(path)).collect()
+    }
+
     /// Write a file to the VFS and the underlying backend.
     ///
     /// Roughly equivalent to [`std::fs::write`][std::fs::write].
@@ -417,6 +769,13 @@ impl VfsLock<'_> {
         self.inner.write(path, contents)
     }
 
+    /// Write several files at once. See [`Vfs::write_batch`] for how failed
+    /// writes are handled.
+    #[inline]
+    pub fn write_batch(&mut self, writes: &[(PathBuf, Vec<u8>)]) -> io::Result<()> {
+        self.inner.write_batch(writes)
+    }
+
     /// Read all of the children of a directory.
     ///
     /// Roughly equivalent to [`std::fs::read_dir`][std::fs::read_dir].
@@ -428,6 +787,14 @@ impl VfsLock<'_> {
         self.inner.read_dir(path)
     }
 
+    /// Read all of the children of a directory without watching it for
+    /// changes, regardless of whether watching is enabled.
+    #[inline]
+    pub fn read_dir_no_watch<P: AsRef<Path>>(&mut self, path: P) -> io::Result<ReadDir> {
+        let path = path.as_ref();
+        self.inner.read_dir_no_watch(path)
+    }
+
     /// Remove a file.
     ///
     /// Roughly equivalent to [`std::fs::remove_file`][std::fs::remove_file].
@@ -450,6 +817,30 @@ impl VfsLock<'_> {
         self.inner.remove_dir_all(path)
     }
 
+    /// Rename a file or directory, replacing `to` if it's a file that already
+    /// exists. Paths being watched beneath `from` are watched at their new
+    /// location instead.
+    ///
+    /// Roughly equivalent to [`std::fs::rename`][std::fs::rename].
+    ///
+    /// [std::fs::rename]: https://doc.rust-lang.org/stable/std/fs/fn.rename.html
+    #[inline]
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<()> {
+        self.inner.rename(from, to)
+    }
+
+    /// Create a directory and any of its ancestors that don't exist yet.
+    /// Succeeds without doing anything if the directory already exists.
+    ///
+    /// Roughly equivalent to [`std::fs::create_dir_all`][std::fs::create_dir_all].
+    ///
+    /// [std::fs::create_dir_all]: https://doc.rust-lang.org/stable/std/fs/fn.create_dir_all.html
+    #[inline]
+    pub fn create_dir_all<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        self.inner.create_dir_all(path)
+    }
+
     /// Query metadata about the given path.
     ///
     /// Roughly equivalent to [`std::fs::metadata`][std::fs::metadata].
@@ -461,12 +852,61 @@ impl VfsLock<'_> {
         self.inner.metadata(path)
     }
 
+    /// Query metadata about the given path without following symbolic links.
+    ///
+    /// Roughly equivalent to [`std::fs::symlink_metadata`][std::fs::symlink_metadata].
+    ///
+    /// [std::fs::symlink_metadata]: https://doc.rust-lang.org/stable/std/fs/fn.symlink_metadata.html
+    #[inline]
+    pub fn symlink_metadata<P: AsRef<Path>>(&mut self, path: P) -> io::Result<Metadata> {
+        let path = path.as_ref();
+        self.inner.symlink_metadata(path)
+    }
+
+    /// Tells whether the given path exists.
+    ///
+    /// Errors other than the path not being found are returned instead of
+    /// being treated as the path not existing.
+    #[inline]
+    pub fn exists<P: AsRef<Path>>(&mut self, path: P) -> io::Result<bool> {
+        let path = path.as_ref();
+        self.inner.exists(path)
+    }
+
     /// Retrieve a handle to the event receiver for this `Vfs`.
     #[inline]
     pub fn event_receiver(&self) -> crossbeam_channel::Receiver<VfsEvent> {
         self.inner.event_receiver()
     }
 
+    /// Stop watching every path that is currently being watched.
+    ///
+    /// Paths will be watched again the next time they are read.
+    #[inline]
+    pub fn unwatch_all(&mut self) -> io::Result<()> {
+        self.inner.unwatch_all()
+    }
+
+    /// Returns every path that is currently being watched, in sorted order.
+    /// This is meant for diagnosing why changes to a file aren't noticed.
+    ///
+    /// Watching a directory also watches everything inside of it, so paths
+    /// inside of a watched directory are not listed separately.
+    #[inline]
+    pub fn watched_paths(&self) -> Vec<PathBuf> {
+        self.inner.watched_paths()
+    }
+
+    /// Returns the nearest watched path that covers the given path, which is
+    /// either the path itself or one of its ancestors. Changes to the path are
+    /// noticed because of that watch.
+    ///
+    /// Returns `None` if nothing covering the path is being watched.
+    #[inline]
+    pub fn watch_root_for<P: AsRef<Path>>(&self, path: P) -> Option<PathBuf> {
+        self.inner.watch_root_for(path.as_ref())
+    }
+
     /// Commit an event to this `Vfs`.
     #[inline]
     pub fn commit_event(&mut self, event: &VfsEvent) -> io::Result<()> {
@@ -476,6 +916,8 @@ impl VfsLock<'_> {
 
 #[cfg(test)]
 mod test {
+    use std::io;
+
     use crate::{InMemoryFs, Vfs, VfsSnapshot};
 
     /// https://github.com/rojo-rbx/rojo/issues/899
@@ -492,4 +934,214 @@ mod test {
             "bar\nfoo\n\n"
         );
     }
+
+    #[test]
+    fn read_to_string_invalid_utf8() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/invalid.txt", VfsSnapshot::file(vec![0xff, 0xfe]))
+            .unwrap();
+
+        let vfs = Vfs::new(imfs);
+        let err = vfs.read_to_string("/invalid.txt").unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("/invalid.txt"));
+    }
+
+    #[test]
+    fn metadata_len() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/dir",
+            VfsSnapshot::dir([("file.txt", VfsSnapshot::file("hello"))]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        assert_eq!(vfs.metadata("/dir/file.txt").unwrap().len(), 5);
+        assert_eq!(vfs.metadata("/dir").unwrap().len(), 0);
+    }
+
+    #[test]
+    fn metadata_modified() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/dir",
+            VfsSnapshot::dir([("file.txt", VfsSnapshot::file("hello"))]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let before = vfs.metadata("/dir/file.txt").unwrap().modified().unwrap();
+        vfs.write("/dir/file.txt", "world").unwrap();
+        let after = vfs.metadata("/dir/file.txt").unwrap().modified().unwrap();
+
+        assert!(after >= before);
+        assert!(vfs.metadata("/dir").unwrap().modified().is_none());
+    }
+
+    #[test]
+    fn read_count() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/dir",
+            VfsSnapshot::dir([("file.txt", VfsSnapshot::file("hello"))]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        // Only reading the contents of a file that exists counts.
+        for _ in 0..3 {
+            vfs.metadata("/dir/file.txt").unwrap();
+            vfs.metadata("/dir").unwrap();
+        }
+        assert!(vfs.read("/dir/missing.txt").is_err());
+        assert_eq!(imfs.read_count(), 0);
+
+        vfs.read("/dir/file.txt").unwrap();
+        vfs.read("/dir/file.txt").unwrap();
+        vfs.metadata("/dir/file.txt").unwrap();
+        assert_eq!(imfs.read_count(), 2);
+    }
+
+    #[test]
+    fn read_many_preserves_order_and_errors() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/dir",
+            VfsSnapshot::dir([
+                ("a.txt", VfsSnapshot::file("a")),
+                ("b.txt", VfsSnapshot::file("b")),
+            ]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+        let results = vfs.read_many(&["/dir/b.txt", "/dir/missing.txt", "/dir/a.txt"]);
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().as_slice(), b"b");
+        assert_eq!(
+            results[1].as_ref().unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert_eq!(results[2].as_ref().unwrap().as_slice(), b"a");
+    }
+
+    #[test]
+    fn exists() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/dir",
+            VfsSnapshot::dir([("a.txt", VfsSnapshot::file("a"))]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        assert!(vfs.exists("/dir").unwrap());
+        assert!(vfs.exists("/dir/a.txt").unwrap());
+        assert!(!vfs.exists("/dir/b.txt").unwrap());
+    }
+
+    #[test]
+    fn rename() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/dir",
+            VfsSnapshot::dir([
+                ("a.txt", VfsSnapshot::file("a")),
+                (
+                    "nested",
+                    VfsSnapshot::dir([("b.txt", VfsSnapshot::file("b"))]),
+                ),
+            ]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+        vfs.rename("/dir/nested", "/dir/moved").unwrap();
+        vfs.rename("/dir/a.txt", "/dir/moved/a.txt").unwrap();
+
+        assert_eq!(vfs.read("/dir/moved/b.txt").unwrap().as_slice(), b"b");
+        assert_eq!(vfs.read("/dir/moved/a.txt").unwrap().as_slice(), b"a");
+        assert!(!vfs.exists("/dir/nested").unwrap());
+
+        let children: Vec<_> = vfs
+            .read_dir("/dir")
+            .unwrap()
+            .map(|entry| entry.unwrap().path().to_path_buf())
+            .collect();
+        assert_eq!(children, vec![std::path::PathBuf::from("/dir/moved")]);
+    }
+
+    #[test]
+    fn write_batch_reads_before_writing() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/dir",
+            VfsSnapshot::dir([
+                ("a.txt", VfsSnapshot::file("old")),
+                ("nested", VfsSnapshot::empty_dir()),
+            ]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        // Reading a directory fails, which should stop the batch before the
+        // file ahead of it is written.
+        vfs.write_batch(&[
+            ("/dir/a.txt".into(), b"new".to_vec()),
+            ("/dir/nested".into(), b"new".to_vec()),
+        ])
+        .unwrap_err();
+
+        assert_eq!(vfs.read("/dir/a.txt").unwrap().as_slice(), b"old");
+    }
+
+    #[test]
+    fn read_prefix() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/file.txt", VfsSnapshot::file("hello, world"))
+            .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        assert_eq!(
+            vfs.read_prefix("/file.txt", 5).unwrap().as_slice(),
+            b"hello"
+        );
+        assert_eq!(
+            vfs.read_prefix("/file.txt", 100).unwrap().as_slice(),
+            b"hello, world"
+        );
+    }
+
+    #[test]
+    fn watch_root_for() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let a = root.join("a");
+        let nested = a.join("nested");
+        let b = root.join("b");
+
+        fs_err::create_dir_all(&nested).unwrap();
+        fs_err::create_dir_all(&b).unwrap();
+
+        let vfs = Vfs::new_default();
+
+        // Reading a directory watches it. The nested directory is watched
+        // first, so watching its parent afterwards doesn't replace it.
+        vfs.read_dir(&nested).unwrap();
+        vfs.read_dir(&a).unwrap();
+
+        assert_eq!(vfs.watch_root_for(nested.join("file.txt")), Some(nested));
+        assert_eq!(vfs.watch_root_for(a.join("other.txt")), Some(a.clone()));
+        assert_eq!(vfs.watch_root_for(&a), Some(a));
+        assert_eq!(vfs.watch_root_for(b.join("file.txt")), None);
+    }
 }
@@ -0,0 +1,539 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::in_memory_fs::{must_be_dir, must_be_file, not_found};
+use crate::{DirEntry, Metadata, ReadDir, VfsBackend, VfsEvent};
+
+/// `VfsBackend` that keeps a flat map of file paths to their contents.
+///
+/// Unlike [`InMemoryFs`](struct.InMemoryFs.html), directories don't need to be
+/// stored explicitly. A directory exists whenever a file exists somewhere
+/// beneath it, so writing a file never requires its parent to be created
+/// first. Directories created with `create_dir_all` are kept track of so that
+/// they exist even while they're empty.
+///
+/// Writes and removals made through this backend raise the matching
+/// `VfsEvent` on its event receiver.
+#[derive(Debug)]
+pub struct MemoryBackend {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+    dirs: BTreeSet<PathBuf>,
+    event_sender: Sender<VfsEvent>,
+    event_receiver: Receiver<VfsEvent>,
+}
+
+impl MemoryBackend {
+    /// Create a new empty `MemoryBackend`.
+    pub fn new() -> Self {
+        let (event_sender, event_receiver) = crossbeam_channel::unbounded();
+
+        Self {
+            files: BTreeMap::new(),
+            dirs: BTreeSet::new(),
+            event_sender,
+            event_receiver,
+        }
+    }
+
+    /// Create a new `MemoryBackend` seeded with the given files. No events are
+    /// raised for these files.
+    pub fn with_files<P, C, I>(files: I) -> Self
+    where
+        P: Into<PathBuf>,
+        C: Into<Vec<u8>>,
+        I: IntoIterator<Item = (P, C)>,
+    {
+        let mut backend = Self::new();
+        backend.files.extend(
+            files
+                .into_iter()
+                .map(|(path, contents)| (path.into(), contents.into())),
+        );
+        backend
+    }
+
+    /// Returns an iterator over every file path that is a descendant of the
+    /// given path, not including the path itself.
+    fn descendants<'a>(&'a self, path: &'a Path) -> impl Iterator<Item = &'a PathBuf> + 'a {
+        // Paths are ordered by component, so all descendants of a path sort
+        // directly after it.
+        self.files
+            .range::<Path, _>((Bound::Excluded(path), Bound::Unbounded))
+            .map(|(file_path, _)| file_path)
+            .take_while(move |file_path| file_path.starts_with(path))
+    }
+
+    /// Returns an iterator over every explicitly created directory that is a
+    /// descendant of the given path, not including the path itself.
+    fn dir_descendants<'a>(&'a self, path: &'a Path) -> impl Iterator<Item = &'a PathBuf> + 'a {
+        self.dirs
+            .range::<Path, _>((Bound::Excluded(path), Bound::Unbounded))
+            .take_while(move |dir_path| dir_path.starts_with(path))
+    }
+
+    /// Writes a file without raising an event, returning the event that
+    /// should be raised for it.
+    fn insert_file(&mut self, path: &Path, data: &[u8]) -> io::Result<VfsEvent> {
+        if self.is_dir(path) {
+            must_be_file(path)?;
+        }
+
+        if let Some(ancestor) = path
+            .ancestors()
+            .skip(1)
+            .find(|ancestor| self.files.contains_key(*ancestor))
+        {
+            must_be_dir(ancestor)?;
+        }
+
+        let existed = self
+            .files
+            .insert(path.to_path_buf(), data.to_owned())
+            .is_some();
+
+        if existed {
+            Ok(VfsEvent::Write(path.to_path_buf()))
+        } else {
+            Ok(VfsEvent::Create(path.to_path_buf()))
+        }
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.dirs.contains(path)
+            || self.descendants(path).next().is_some()
+            || self.dir_descendants(path).next().is_some()
+    }
+
+    fn send_event(&self, event: VfsEvent) {
+        // The receiver half is owned by this struct, so sending can't fail.
+        let _ = self.event_sender.send(event);
+    }
+}
+
+impl Default for MemoryBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VfsBackend for MemoryBackend {
+    fn read(&mut self, path: &Path) -> io::Result<Vec<u8>> {
+        match self.files.get(path) {
+            Some(contents) => Ok(contents.clone()),
+            None if self.is_dir(path) => must_be_file(path),
+            None => not_found(path),
+        }
+    }
+
+    fn write(&mut self, path: &Path, data: &[u8]) -> io::Result<()> {
+        let event = self.insert_file(path, data)?;
+        self.send_event(event);
+
+        Ok(())
+    }
+
+    fn write_batch(&mut self, writes: &[(PathBuf, Vec<u8>)]) -> io::Result<()> {
+        let mut written: Vec<(&Path, Option<Vec<u8>>)> = Vec::with_capacity(writes.len());
+        let mut events = Vec::with_capacity(writes.len());
+
+        for (path, data) in writes {
+            let previous = self.files.get(path).cloned();
+
+            match self.insert_file(path, data) {
+                Ok(event) => {
+                    written.push((path, previous));
+                    events.push(event);
+                }
+                Err(err) => {
+                    // Restoring in reverse order puts back the original
+                    // contents of files that were written more than once.
+                    for (path, previous) in written.into_iter().rev() {
+                        match previous {
+                            Some(contents) => self.files.insert(path.to_path_buf(), contents),
+                            None => self.files.remove(path),
+                        };
+                    }
+
+                    return Err(err);
+                }
+            }
+        }
+
+        for event in events {
+            self.send_event(event);
+        }
+
+        Ok(())
+    }
+
+    fn read_dir(&mut self, path: &Path) -> io::Result<ReadDir> {
+        if self.files.contains_key(path) {
+            return must_be_dir(path);
+        }
+
+        let children: BTreeSet<PathBuf> = self
+            .descendants(path)
+            .chain(self.dir_descendants(path))
+            .filter_map(|descendant| {
+                let child_name = descendant.strip_prefix(path).ok()?.components().next()?;
+                Some(path.join(child_name))
+            })
+            .collect();
+
+        if children.is_empty() && !self.dirs.contains(path) {
+            return not_found(path);
+        }
+
+        Ok(ReadDir {
+            inner: Box::new(children.into_iter().map(|path| Ok(DirEntry { path }))),
+        })
+    }
+
+    fn metadata(&mut self, path: &Path) -> io::Result<Metadata> {
+        match self.files.get(path) {
+            Some(contents) => Ok(Metadata {
+                is_file: true,
+                is_symlink: false,
+                len: contents.len() as u64,
+                modified: None,
+            }),
+            None if self.is_dir(path) => Ok(Metadata {
+                is_file: false,
+                is_symlink: false,
+                len: 0,
+                modified: None,
+            }),
+            None => not_found(path),
+        }
+    }
+
+    fn remove_file(&mut self, path: &Path) -> io::Result<()> {
+        match self.files.remove(path) {
+            Some(_) => {
+                self.send_event(VfsEvent::Remove(path.to_path_buf()));
+                Ok(())
+            }
+            None if self.is_dir(path) => must_be_file(path),
+            None => not_found(path),
+        }
+    }
+
+    fn remove_dir_all(&mut self, path: &Path) -> io::Result<()> {
+        if self.files.contains_key(path) {
+            return must_be_dir(path);
+        }
+
+        if !self.is_dir(path) {
+            return not_found(path);
+        }
+
+        let to_remove: Vec<PathBuf> = self.descendants(path).cloned().collect();
+        for file_path in to_remove {
+            self.files.remove(&file_path);
+        }
+
+        let dirs_to_remove: Vec<PathBuf> = self.dir_descendants(path).cloned().collect();
+        for dir_path in dirs_to_remove {
+            self.dirs.remove(&dir_path);
+        }
+        self.dirs.remove(path);
+
+        self.send_event(VfsEvent::Remove(path.to_path_buf()));
+
+        Ok(())
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+        if to.starts_with(from) && to != from {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "cannot move {} inside of itself: {}",
+                    from.display(),
+                    to.display()
+                ),
+            ));
+        }
+
+        if let Some(ancestor) = to
+            .ancestors()
+            .skip(1)
+            .find(|ancestor| self.files.contains_key(*ancestor))
+        {
+            return must_be_dir(ancestor);
+        }
+
+        if let Some(contents) = self.files.get(from).cloned() {
+            if self.is_dir(to) {
+                return must_be_file(to);
+            }
+
+            self.files.remove(from);
+            self.files.insert(to.to_path_buf(), contents);
+        } else {
+            if !self.is_dir(from) {
+                return not_found(from);
+            }
+
+            if self.files.contains_key(to) {
+                return must_be_dir(to);
+            }
+
+            if self.is_dir(to) {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("directory already exists: {}", to.display()),
+                ));
+            }
+
+            let to_move: Vec<PathBuf> = self.descendants(from).cloned().collect();
+            for old_path in to_move {
+                let contents = self.files.remove(&old_path).unwrap();
+                let new_path = to.join(old_path.strip_prefix(from).unwrap());
+                self.files.insert(new_path, contents);
+            }
+
+            let dirs_to_move: Vec<PathBuf> = self.dir_descendants(from).cloned().collect();
+            for old_path in dirs_to_move {
+                self.dirs.remove(&old_path);
+                self.dirs
+                    .insert(to.join(old_path.strip_prefix(from).unwrap()));
+            }
+
+            if self.dirs.remove(from) {
+                self.dirs.insert(to.to_path_buf());
+            }
+        }
+
+        self.send_event(VfsEvent::Rename {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+        });
+
+        Ok(())
+    }
+
+    fn create_dir_all(&mut self, path: &Path) -> io::Result<()> {
+        if let Some(ancestor) = path
+            .ancestors()
+            .find(|ancestor| self.files.contains_key(*ancestor))
+        {
+            return must_be_dir(ancestor);
+        }
+
+        let mut created: Vec<PathBuf> = path
+            .ancestors()
+            .filter(|ancestor| ancestor.parent().is_some())
+            .take_while(|ancestor| !self.is_dir(ancestor))
+            .map(Path::to_path_buf)
+            .collect();
+
+        self.dirs.insert(path.to_path_buf());
+
+        created.reverse();
+        for dir in created {
+            self.send_event(VfsEvent::Create(dir));
+        }
+
+        Ok(())
+    }
+
+    fn event_receiver(&self) -> crossbeam_channel::Receiver<VfsEvent> {
+        self.event_receiver.clone()
+    }
+
+    fn watch(&mut self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn unwatch(&mut self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn unwatch_all(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::Vfs;
+
+    fn read_dir_paths(vfs: &Vfs, path: &str) -> Vec<PathBuf> {
+        vfs.read_dir(path)
+            .unwrap()
+            .map(|entry| entry.unwrap().path().to_path_buf())
+            .collect()
+    }
+
+    #[test]
+    fn implicit_directories() {
+        let vfs = Vfs::new(MemoryBackend::with_files([
+            ("/src/a.lua", "a"),
+            ("/src/nested/b.lua", "b"),
+            ("/src.lua", "not a child"),
+        ]));
+
+        assert!(vfs.metadata("/src").unwrap().is_dir());
+        assert!(vfs.metadata("/src/nested").unwrap().is_dir());
+        assert!(vfs.metadata("/src/a.lua").unwrap().is_file());
+        assert_eq!(
+            read_dir_paths(&vfs, "/src"),
+            vec![PathBuf::from("/src/a.lua"), PathBuf::from("/src/nested")]
+        );
+
+        assert_eq!(
+            vfs.metadata("/missing").unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert!(vfs.read("/src").is_err());
+        assert!(vfs.read_dir("/src/a.lua").is_err());
+    }
+
+    #[test]
+    fn write_batch_is_all_or_nothing() {
+        let vfs = Vfs::new(MemoryBackend::with_files([("/a.txt", "old")]));
+        let receiver = vfs.event_receiver();
+
+        let err = vfs
+            .write_batch(&[
+                (PathBuf::from("/a.txt"), b"new".to_vec()),
+                (PathBuf::from("/b.txt"), b"new".to_vec()),
+                (PathBuf::from("/a.txt/c.txt"), b"new".to_vec()),
+            ])
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "path /a.txt was a file, but must be a directory"
+        );
+        assert_eq!(vfs.read("/a.txt").unwrap().as_slice(), b"old");
+        assert!(!vfs.exists("/b.txt").unwrap());
+        assert!(receiver.try_recv().is_err());
+
+        vfs.write_batch(&[
+            (PathBuf::from("/a.txt"), b"new".to_vec()),
+            (PathBuf::from("/b.txt"), b"new".to_vec()),
+        ])
+        .unwrap();
+
+        assert_eq!(vfs.read("/a.txt").unwrap().as_slice(), b"new");
+        assert_eq!(vfs.read("/b.txt").unwrap().as_slice(), b"new");
+        assert_eq!(receiver.try_iter().count(), 2);
+    }
+
+    #[test]
+    fn write_and_remove_raise_events() {
+        let vfs = Vfs::new(MemoryBackend::new());
+        let receiver = vfs.event_receiver();
+
+        vfs.write("/foo/bar.txt", "one").unwrap();
+        vfs.write("/foo/bar.txt", "two").unwrap();
+        vfs.remove_file("/foo/bar.txt").unwrap();
+        vfs.write("/foo/baz.txt", "three").unwrap();
+        vfs.remove_dir_all("/foo").unwrap();
+
+        let events: Vec<String> = receiver
+            .try_iter()
+            .map(|event| format!("{:?}", event))
+            .collect();
+
+        assert_eq!(
+            events,
+            vec![
+                r#"Create("/foo/bar.txt")"#,
+                r#"Write("/foo/bar.txt")"#,
+                r#"Remove("/foo/bar.txt")"#,
+                r#"Create("/foo/baz.txt")"#,
+                r#"Remove("/foo")"#,
+            ]
+        );
+
+        assert_eq!(
+            vfs.metadata("/foo").unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn rename() {
+        let vfs = Vfs::new(MemoryBackend::with_files([
+            ("/src/a.lua", "a"),
+            ("/src/nested/b.lua", "b"),
+        ]));
+        let receiver = vfs.event_receiver();
+
+        vfs.rename("/src/a.lua", "/out/a2.lua").unwrap();
+        vfs.rename("/src/nested", "/out/moved").unwrap();
+
+        assert_eq!(vfs.read("/out/a2.lua").unwrap().as_slice(), b"a");
+        assert_eq!(vfs.read("/out/moved/b.lua").unwrap().as_slice(), b"b");
+        assert!(!vfs.exists("/src").unwrap());
+
+        let events: Vec<String> = receiver
+            .try_iter()
+            .map(|event| format!("{:?}", event))
+            .collect();
+
+        assert_eq!(
+            events,
+            vec![
+                r#"Rename { from: "/src/a.lua", to: "/out/a2.lua" }"#,
+                r#"Rename { from: "/src/nested", to: "/out/moved" }"#,
+            ]
+        );
+
+        assert_eq!(
+            vfs.rename("/missing", "/elsewhere").unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert!(vfs.rename("/out", "/out/inner").is_err());
+    }
+
+    #[test]
+    fn create_dir_all() {
+        let vfs = Vfs::new(MemoryBackend::with_files([("/src/a.lua", "a")]));
+        let receiver = vfs.event_receiver();
+
+        vfs.create_dir_all("/src/generated/nested").unwrap();
+        vfs.create_dir_all("/src/generated").unwrap();
+
+        assert!(vfs.metadata("/src/generated/nested").unwrap().is_dir());
+        assert_eq!(vfs.read_dir("/src/generated/nested").unwrap().count(), 0);
+        assert_eq!(
+            read_dir_paths(&vfs, "/src"),
+            vec![PathBuf::from("/src/a.lua"), PathBuf::from("/src/generated")]
+        );
+
+        let events: Vec<String> = receiver
+            .try_iter()
+            .map(|event| format!("{:?}", event))
+            .collect();
+
+        assert_eq!(
+            events,
+            vec![
+                r#"Create("/src/generated")"#,
+                r#"Create("/src/generated/nested")"#,
+            ]
+        );
+
+        assert!(vfs.create_dir_all("/src/a.lua/inner").is_err());
+
+        vfs.remove_dir_all("/src/generated").unwrap();
+        assert!(!vfs.exists("/src/generated/nested").unwrap());
+    }
+
+    #[test]
+    fn write_inside_file_fails() {
+        let vfs = Vfs::new(MemoryBackend::with_files([("/foo", "")]));
+
+        assert!(vfs.write("/foo/bar", "").is_err());
+    }
+}
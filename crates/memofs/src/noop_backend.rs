@@ -1,59 +1,111 @@
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 use crate::{Metadata, ReadDir, VfsBackend, VfsEvent};
 
+/// The path and contents of every write made to a recording `NoopBackend`.
+type WriteLog = Arc<Mutex<Vec<(PathBuf, Vec<u8>)>>>;
+
 /// `VfsBackend` that returns an error on every operation.
+///
+/// A `NoopBackend` can instead be made to record writes with
+/// [`NoopBackend::recording`], which makes it possible to check what would
+/// have been written without touching the disk. It's internally reference
+/// counted, so a copy can be kept to read the recorded writes after giving it
+/// to a [`Vfs`](struct.Vfs.html).
+#[derive(Debug, Clone)]
 #[non_exhaustive]
-pub struct NoopBackend;
+pub struct NoopBackend {
+    writes: Option<WriteLog>,
+    read_back: bool,
+}
 
 impl NoopBackend {
     pub fn new() -> Self {
-        Self
+        Self {
+            writes: None,
+            read_back: false,
+        }
+    }
+
+    /// Create a `NoopBackend` that records writes instead of returning an
+    /// error. Watching paths does nothing so that reads can succeed, and
+    /// every other operation still returns an error.
+    pub fn recording() -> Self {
+        Self {
+            writes: Some(Arc::new(Mutex::new(Vec::new()))),
+            read_back: false,
+        }
+    }
+
+    /// Makes reads of paths that have been written return the last contents
+    /// written to them. Only has an effect on recording backends.
+    pub fn with_read_back(self) -> Self {
+        Self {
+            read_back: true,
+            ..self
+        }
+    }
+
+    /// Returns every write that has been recorded, in the order they
+    /// happened.
+    pub fn writes(&self) -> Vec<(PathBuf, Vec<u8>)> {
+        match &self.writes {
+            Some(writes) => writes.lock().unwrap().clone(),
+            None => Vec::new(),
+        }
     }
 }
 
 impl VfsBackend for NoopBackend {
-    fn read(&mut self, _path: &Path) -> io::Result<Vec<u8>> {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            "NoopBackend doesn't do anything",
-        ))
+    fn read(&mut self, path: &Path) -> io::Result<Vec<u8>> {
+        if let (Some(writes), true) = (&self.writes, self.read_back) {
+            let writes = writes.lock().unwrap();
+
+            if let Some((_, contents)) = writes.iter().rev().find(|(written, _)| written == path) {
+                return Ok(contents.clone());
+            }
+        }
+
+        Err(io::Error::other("NoopBackend doesn't do anything"))
     }
 
-    fn write(&mut self, _path: &Path, _data: &[u8]) -> io::Result<()> {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            "NoopBackend doesn't do anything",
-        ))
+    fn write(&mut self, path: &Path, data: &[u8]) -> io::Result<()> {
+        match &self.writes {
+            Some(writes) => {
+                writes
+                    .lock()
+                    .unwrap()
+                    .push((path.to_path_buf(), data.to_owned()));
+                Ok(())
+            }
+            None => Err(io::Error::other("NoopBackend doesn't do anything")),
+        }
     }
 
     fn read_dir(&mut self, _path: &Path) -> io::Result<ReadDir> {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            "NoopBackend doesn't do anything",
-        ))
+        Err(io::Error::other("NoopBackend doesn't do anything"))
     }
 
     fn remove_file(&mut self, _path: &Path) -> io::Result<()> {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            "NoopBackend doesn't do anything",
-        ))
+        Err(io::Error::other("NoopBackend doesn't do anything"))
     }
 
     fn remove_dir_all(&mut self, _path: &Path) -> io::Result<()> {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            "NoopBackend doesn't do anything",
-        ))
+        Err(io::Error::other("NoopBackend doesn't do anything"))
+    }
+
+    fn rename(&mut self, _from: &Path, _to: &Path) -> io::Result<()> {
+        Err(io::Error::other("NoopBackend doesn't do anything"))
+    }
+
+    fn create_dir_all(&mut self, _path: &Path) -> io::Result<()> {
+        Err(io::Error::other("NoopBackend doesn't do anything"))
     }
 
     fn metadata(&mut self, _path: &Path) -> io::Result<Metadata> {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            "NoopBackend doesn't do anything",
-        ))
+        Err(io::Error::other("NoopBackend doesn't do anything"))
     }
 
     fn event_receiver(&self) -> crossbeam_channel::Receiver<VfsEvent> {
@@ -61,17 +113,21 @@ impl VfsBackend for NoopBackend {
     }
 
     fn watch(&mut self, _path: &Path) -> io::Result<()> {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            "NoopBackend doesn't do anything",
-        ))
+        match self.writes {
+            Some(_) => Ok(()),
+            None => Err(io::Error::other("NoopBackend doesn't do anything")),
+        }
     }
 
     fn unwatch(&mut self, _path: &Path) -> io::Result<()> {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            "NoopBackend doesn't do anything",
-        ))
+        match self.writes {
+            Some(_) => Ok(()),
+            None => Err(io::Error::other("NoopBackend doesn't do anything")),
+        }
+    }
+
+    fn unwatch_all(&mut self) -> io::Result<()> {
+        Ok(())
     }
 }
 
@@ -80,3 +136,40 @@ impl Default for NoopBackend {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::Vfs;
+
+    #[test]
+    fn records_writes() {
+        let backend = NoopBackend::recording();
+        let vfs = Vfs::new(backend.clone());
+
+        vfs.write("/a.txt", "one").unwrap();
+        vfs.write("/b.txt", "two").unwrap();
+
+        assert_eq!(
+            backend.writes(),
+            vec![
+                (PathBuf::from("/a.txt"), b"one".to_vec()),
+                (PathBuf::from("/b.txt"), b"two".to_vec()),
+            ]
+        );
+        assert!(vfs.read("/a.txt").is_err());
+        assert!(Vfs::new(NoopBackend::new()).write("/a.txt", "").is_err());
+    }
+
+    #[test]
+    fn reads_back_writes() {
+        let vfs = Vfs::new(NoopBackend::recording().with_read_back());
+
+        vfs.write("/a.txt", "one").unwrap();
+        vfs.write("/a.txt", "two").unwrap();
+
+        assert_eq!(vfs.read("/a.txt").unwrap().as_slice(), b"two");
+        assert!(vfs.read("/b.txt").is_err());
+    }
+}
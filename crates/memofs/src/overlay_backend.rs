@@ -0,0 +1,380 @@
+use std::collections::{BTreeSet, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use crossbeam_channel::{Receiver, Select};
+
+use crate::in_memory_fs::not_found;
+use crate::{DirEntry, IoResultExt, Metadata, ReadDir, VfsBackend, VfsEvent};
+
+/// `VfsBackend` that layers a writable overlay on top of a read-only base.
+///
+/// Reads check the overlay first and fall back to the base. Writes and
+/// removals only ever touch the overlay. Removing a path that exists in the
+/// base records a whiteout so that the path appears deleted, even though the
+/// base is left untouched.
+///
+/// Events from both backends are forwarded through a single receiver.
+pub struct OverlayBackend {
+    base: Box<dyn VfsBackend>,
+    overlay: Box<dyn VfsBackend>,
+    whiteouts: HashSet<PathBuf>,
+    event_receiver: Receiver<VfsEvent>,
+}
+
+impl OverlayBackend {
+    /// Create a new `OverlayBackend` that reads from `base` and directs all
+    /// modifications to `overlay`.
+    pub fn new<B: VfsBackend, O: VfsBackend>(base: B, overlay: O) -> Self {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let mut receivers = vec![base.event_receiver(), overlay.event_receiver()];
+
+        thread::spawn(move || {
+            while !receivers.is_empty() {
+                let mut select = Select::new();
+                for receiver in &receivers {
+                    select.recv(receiver);
+                }
+
+                let operation = select.select();
+                let index = operation.index();
+
+                match operation.recv(&receivers[index]) {
+                    Ok(event) => {
+                        if tx.send(event).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => {
+                        receivers.remove(index);
+                    }
+                }
+            }
+        });
+
+        Self {
+            base: Box::new(base),
+            overlay: Box::new(overlay),
+            whiteouts: HashSet::new(),
+            event_receiver: rx,
+        }
+    }
+
+    /// Tells whether the given path, or any of its ancestors, has been removed
+    /// from the base.
+    fn is_whited_out(&self, path: &Path) -> bool {
+        path.ancestors()
+            .any(|ancestor| self.whiteouts.contains(ancestor))
+    }
+
+    /// Makes `path` visible again after one of its ancestors was whited out,
+    /// without resurrecting any of that ancestor's other contents in the base.
+    fn clear_whiteouts(&mut self, path: &Path) -> io::Result<()> {
+        let whited_out = path
+            .ancestors()
+            .find(|ancestor| self.whiteouts.contains(*ancestor))
+            .map(Path::to_path_buf);
+
+        let whited_out = match whited_out {
+            Some(whited_out) => whited_out,
+            None => return Ok(()),
+        };
+
+        self.whiteouts.remove(&whited_out);
+
+        // Every directory between the old whiteout and the new path stays
+        // visible, so each of their other base children needs its own
+        // whiteout instead.
+        let mut visible: Vec<&Path> = path
+            .ancestors()
+            .take_while(|ancestor| *ancestor != whited_out)
+            .collect();
+        visible.push(&whited_out);
+        visible.reverse();
+
+        for pair in visible.windows(2) {
+            let (dir, keep) = (pair[0], pair[1]);
+
+            if let Some(entries) = self.base.read_dir(dir).with_not_found()? {
+                for entry in entries {
+                    let entry = entry?;
+
+                    if entry.path() != keep {
+                        self.whiteouts.insert(entry.path);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl VfsBackend for OverlayBackend {
+    fn read(&mut self, path: &Path) -> io::Result<Vec<u8>> {
+        if self.is_whited_out(path) {
+            return not_found(path);
+        }
+
+        match self.overlay.read(path).with_not_found()? {
+            Some(contents) => Ok(contents),
+            None => self.base.read(path),
+        }
+    }
+
+    fn write(&mut self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.clear_whiteouts(path)?;
+        self.overlay.write(path, data)
+    }
+
+    fn read_dir(&mut self, path: &Path) -> io::Result<ReadDir> {
+        if self.is_whited_out(path) {
+            return not_found(path);
+        }
+
+        let overlay_entries = self.overlay.read_dir(path).with_not_found()?;
+        let base_entries = self.base.read_dir(path).with_not_found()?;
+
+        if overlay_entries.is_none() && base_entries.is_none() {
+            return not_found(path);
+        }
+
+        let mut children = BTreeSet::new();
+        for entry in overlay_entries.into_iter().chain(base_entries).flatten() {
+            let entry = entry?;
+
+            if !self.whiteouts.contains(entry.path()) {
+                children.insert(entry.path);
+            }
+        }
+
+        Ok(ReadDir {
+            inner: Box::new(children.into_iter().map(|path| Ok(DirEntry { path }))),
+        })
+    }
+
+    fn metadata(&mut self, path: &Path) -> io::Result<Metadata> {
+        if self.is_whited_out(path) {
+            return not_found(path);
+        }
+
+        match self.overlay.metadata(path).with_not_found()? {
+            Some(metadata) => Ok(metadata),
+            None => self.base.metadata(path),
+        }
+    }
+
+    fn symlink_metadata(&mut self, path: &Path) -> io::Result<Metadata> {
+        if self.is_whited_out(path) {
+            return not_found(path);
+        }
+
+        match self.overlay.symlink_metadata(path).with_not_found()? {
+            Some(metadata) => Ok(metadata),
+            None => self.base.symlink_metadata(path),
+        }
+    }
+
+    fn remove_file(&mut self, path: &Path) -> io::Result<()> {
+        if self.is_whited_out(path) {
+            return not_found(path);
+        }
+
+        let in_overlay = self.overlay.remove_file(path).with_not_found()?.is_some();
+        let in_base = self.base.metadata(path).with_not_found()?.is_some();
+
+        if in_base {
+            self.whiteouts.insert(path.to_path_buf());
+        }
+
+        if in_overlay || in_base {
+            Ok(())
+        } else {
+            not_found(path)
+        }
+    }
+
+    fn remove_dir_all(&mut self, path: &Path) -> io::Result<()> {
+        if self.is_whited_out(path) {
+            return not_found(path);
+        }
+
+        let in_overlay = self
+            .overlay
+            .remove_dir_all(path)
+            .with_not_found()?
+            .is_some();
+        let in_base = self.base.metadata(path).with_not_found()?.is_some();
+
+        if in_base {
+            self.whiteouts.insert(path.to_path_buf());
+        }
+
+        if in_overlay || in_base {
+            Ok(())
+        } else {
+            not_found(path)
+        }
+    }
+
+    /// The base can't be modified, so renames copy everything at `from` into
+    /// the overlay and then remove it. Unlike other backends, they aren't
+    /// atomic.
+    fn rename(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+        if self.metadata(from)?.is_file() {
+            let contents = self.read(from)?;
+            self.write(to, &contents)?;
+            self.remove_file(from)
+        } else {
+            let children: Vec<PathBuf> = self
+                .read_dir(from)?
+                .map(|entry| entry.map(|entry| entry.path))
+                .collect::<io::Result<_>>()?;
+
+            for child in children {
+                self.rename(&child, &to.join(child.file_name().unwrap()))?;
+            }
+
+            self.remove_dir_all(from).with_not_found()?;
+            Ok(())
+        }
+    }
+
+    fn create_dir_all(&mut self, path: &Path) -> io::Result<()> {
+        self.clear_whiteouts(path)?;
+        self.overlay.create_dir_all(path)
+    }
+
+    fn event_receiver(&self) -> crossbeam_channel::Receiver<VfsEvent> {
+        self.event_receiver.clone()
+    }
+
+    fn watch(&mut self, path: &Path) -> io::Result<()> {
+        let base = self.base.watch(path);
+        let overlay = self.overlay.watch(path);
+
+        base.or(overlay)
+    }
+
+    fn unwatch(&mut self, path: &Path) -> io::Result<()> {
+        let base = self.base.unwatch(path);
+        let overlay = self.overlay.unwatch(path);
+
+        base.or(overlay)
+    }
+
+    fn unwatch_all(&mut self) -> io::Result<()> {
+        let base = self.base.unwatch_all();
+        let overlay = self.overlay.unwatch_all();
+
+        base.and(overlay)
+    }
+
+    fn watched_paths(&self) -> Vec<PathBuf> {
+        let paths: BTreeSet<PathBuf> = self
+            .base
+            .watched_paths()
+            .into_iter()
+            .chain(self.overlay.watched_paths())
+            .collect();
+
+        paths.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::time::Duration;
+
+    use crate::{MemoryBackend, Vfs};
+
+    fn read_dir_paths(vfs: &Vfs, path: &str) -> Vec<PathBuf> {
+        vfs.read_dir(path)
+            .unwrap()
+            .map(|entry| entry.unwrap().path().to_path_buf())
+            .collect()
+    }
+
+    fn base() -> MemoryBackend {
+        MemoryBackend::with_files([
+            ("/src/a.lua", "base a"),
+            ("/src/b.lua", "base b"),
+            ("/src/nested/c.lua", "base c"),
+        ])
+    }
+
+    #[test]
+    fn overlay_shadows_base() {
+        let vfs = Vfs::new(OverlayBackend::new(base(), MemoryBackend::new()));
+
+        vfs.write("/src/a.lua", "overlay a").unwrap();
+        vfs.write("/src/d.lua", "overlay d").unwrap();
+
+        assert_eq!(vfs.read("/src/a.lua").unwrap().as_slice(), b"overlay a");
+        assert_eq!(vfs.read("/src/b.lua").unwrap().as_slice(), b"base b");
+        assert_eq!(
+            read_dir_paths(&vfs, "/src"),
+            vec![
+                PathBuf::from("/src/a.lua"),
+                PathBuf::from("/src/b.lua"),
+                PathBuf::from("/src/d.lua"),
+                PathBuf::from("/src/nested"),
+            ]
+        );
+    }
+
+    #[test]
+    fn removals_white_out_base() {
+        let vfs = Vfs::new(OverlayBackend::new(base(), MemoryBackend::new()));
+
+        vfs.remove_file("/src/a.lua").unwrap();
+        vfs.remove_dir_all("/src/nested").unwrap();
+
+        assert_eq!(
+            vfs.read("/src/a.lua").unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert_eq!(
+            vfs.read("/src/nested/c.lua").unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert_eq!(
+            read_dir_paths(&vfs, "/src"),
+            vec![PathBuf::from("/src/b.lua")]
+        );
+
+        vfs.write("/src/a.lua", "restored").unwrap();
+        assert_eq!(vfs.read("/src/a.lua").unwrap().as_slice(), b"restored");
+    }
+
+    #[test]
+    fn write_inside_removed_dir_keeps_base_hidden() {
+        let vfs = Vfs::new(OverlayBackend::new(base(), MemoryBackend::new()));
+
+        vfs.remove_dir_all("/src").unwrap();
+        vfs.write("/src/new.lua", "new").unwrap();
+
+        assert_eq!(
+            read_dir_paths(&vfs, "/src"),
+            vec![PathBuf::from("/src/new.lua")]
+        );
+        assert_eq!(
+            vfs.read("/src/b.lua").unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn forwards_events() {
+        let vfs = Vfs::new(OverlayBackend::new(base(), MemoryBackend::new()));
+        let receiver = vfs.event_receiver();
+
+        vfs.write("/src/d.lua", "overlay d").unwrap();
+
+        let event = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(matches!(event, VfsEvent::Create(path) if path == Path::new("/src/d.lua")));
+    }
+}
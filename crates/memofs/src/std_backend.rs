@@ -1,19 +1,28 @@
+use std::ffi::OsString;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::thread;
 use std::time::Duration;
-use std::{collections::HashSet, io};
+use std::{
+    collections::HashSet,
+    io::{self, Read},
+};
 
-use crossbeam_channel::Receiver;
+use crossbeam_channel::{Receiver, Sender};
 use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
 
-use crate::{DirEntry, Metadata, ReadDir, VfsBackend, VfsEvent};
+use crate::debounce::coalesce_dir_removes;
+use crate::{DirEntry, IoResultExt, Metadata, ReadDir, VfsBackend, VfsEvent};
 
 /// `VfsBackend` that uses `std::fs` and the `notify` crate.
 pub struct StdBackend {
     watcher: RecommendedWatcher,
     watcher_receiver: Receiver<VfsEvent>,
+    event_sender: Sender<VfsEvent>,
     watches: HashSet<PathBuf>,
+    skip_symlinks: bool,
+    read_only: bool,
+    case_insensitive: bool,
 }
 
 impl StdBackend {
@@ -22,6 +31,7 @@ impl StdBackend {
         let watcher = watcher(notify_tx, Duration::from_millis(50)).unwrap();
 
         let (tx, rx) = crossbeam_channel::unbounded();
+        let event_sender = tx.clone();
 
         thread::spawn(move || {
             for event in notify_rx {
@@ -36,8 +46,7 @@ impl StdBackend {
                         tx.send(VfsEvent::Remove(path))?;
                     }
                     DebouncedEvent::Rename(from, to) => {
-                        tx.send(VfsEvent::Remove(from))?;
-                        tx.send(VfsEvent::Create(to))?;
+                        tx.send(VfsEvent::Rename { from, to })?;
                     }
                     _ => {}
                 }
@@ -49,24 +58,197 @@ impl StdBackend {
         Self {
             watcher,
             watcher_receiver: rx,
+            event_sender,
             watches: HashSet::new(),
+            skip_symlinks: false,
+            read_only: false,
+            case_insensitive: false,
         }
     }
+
+    /// Makes every operation that would change the filesystem fail with
+    /// `PermissionDenied` instead, without touching the disk.
+    pub fn read_only(self) -> StdBackend {
+        StdBackend {
+            read_only: true,
+            ..self
+        }
+    }
+
+    fn check_writable(&self, path: &Path) -> io::Result<()> {
+        if self.read_only {
+            Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "cannot modify {}, the filesystem is read-only",
+                    path.display()
+                ),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Leaves symbolic links out of the results of `read_dir`. Links that
+    /// point to one of their own ancestors would otherwise make walking a
+    /// directory loop forever.
+    pub fn skip_symlinks(self) -> StdBackend {
+        StdBackend {
+            skip_symlinks: true,
+            ..self
+        }
+    }
+
+    /// Makes reads of paths that don't exist fall back to a path that only
+    /// differs by case, logging a warning when they do. This lets projects
+    /// written on case-insensitive filesystems work on case-sensitive ones.
+    ///
+    /// Paths that match more than one entry in a directory are still not
+    /// found, and operations that change the filesystem are never affected.
+    pub fn case_insensitive(self) -> StdBackend {
+        StdBackend {
+            case_insensitive: true,
+            ..self
+        }
+    }
+
+    /// Reports removing a directory as a single event, instead of also
+    /// reporting the removal of each of its descendants. Events for individual
+    /// files are reported as usual.
+    pub fn coalesce_dir_events(self) -> StdBackend {
+        StdBackend {
+            watcher_receiver: coalesce_dir_removes(
+                self.watcher_receiver,
+                Duration::from_millis(50),
+            ),
+            ..self
+        }
+    }
+
+    /// Runs `operation` on the given path. If case-insensitive fallback is on
+    /// and the path isn't found, it's run again on a path that only differs
+    /// by case, if there is one.
+    fn with_case_fallback<T>(
+        &self,
+        path: &Path,
+        operation: impl Fn(&Path) -> io::Result<T>,
+    ) -> io::Result<T> {
+        match operation(path) {
+            Err(err) if self.case_insensitive && err.kind() == io::ErrorKind::NotFound => {
+                match resolve_case_insensitive(path) {
+                    Some(resolved) => {
+                        log::warn!(
+                            "{} does not exist, using {} instead. The case of these paths differs.",
+                            path.display(),
+                            resolved.display()
+                        );
+                        operation(&resolved)
+                    }
+                    None => Err(err),
+                }
+            }
+            result => result,
+        }
+    }
+}
+
+/// Where a file is written before it's moved over `path` by `write_batch`.
+/// It's in the same directory so that the move doesn't cross filesystems.
+fn temp_path_for(path: &Path) -> PathBuf {
+    let mut file_name = OsString::from(".");
+    file_name.push(path.file_name().unwrap_or_default());
+    file_name.push(".rojo-tmp");
+
+    path.with_file_name(file_name)
 }
 
 impl VfsBackend for StdBackend {
     fn read(&mut self, path: &Path) -> io::Result<Vec<u8>> {
-        fs_err::read(path)
+        self.with_case_fallback(path, |path| fs_err::read(path))
+    }
+
+    fn read_prefix(&mut self, path: &Path, max: usize) -> io::Result<Vec<u8>> {
+        let file = self.with_case_fallback(path, |path| fs_err::File::open(path))?;
+        let mut contents = Vec::new();
+        file.take(max as u64).read_to_end(&mut contents)?;
+
+        Ok(contents)
     }
 
     fn write(&mut self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.check_writable(path)?;
         fs_err::write(path, data)
     }
 
+    fn write_batch(&mut self, writes: &[(PathBuf, Vec<u8>)]) -> io::Result<()> {
+        for (path, _) in writes {
+            self.check_writable(path)?;
+        }
+
+        // Everything is written next to where it's going first, so that a
+        // write that fails partway through doesn't touch any of the files.
+        let mut staged = Vec::with_capacity(writes.len());
+
+        for (path, data) in writes {
+            let temp_path = temp_path_for(path);
+
+            let result = fs_err::read(path)
+                .with_not_found()
+                .and_then(|previous| fs_err::write(&temp_path, data).map(|_| previous));
+
+            match result {
+                Ok(previous) => staged.push((path.as_path(), temp_path, previous)),
+                Err(err) => {
+                    let _ = fs_err::remove_file(&temp_path);
+                    for (_, temp_path, _) in &staged {
+                        let _ = fs_err::remove_file(temp_path);
+                    }
+
+                    return Err(err);
+                }
+            }
+        }
+
+        for i in 0..staged.len() {
+            let (path, temp_path, _) = &staged[i];
+
+            if let Err(err) = fs_err::rename(temp_path, path) {
+                for (path, _, previous) in staged[..i].iter().rev() {
+                    let _ = match previous {
+                        Some(contents) => fs_err::write(path, contents),
+                        None => fs_err::remove_file(path),
+                    };
+                }
+
+                for (_, temp_path, _) in &staged[i..] {
+                    let _ = fs_err::remove_file(temp_path);
+                }
+
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
     fn read_dir(&mut self, path: &Path) -> io::Result<ReadDir> {
-        let entries: Result<Vec<_>, _> = fs_err::read_dir(path)?.collect();
+        let entries: Result<Vec<_>, _> = self
+            .with_case_fallback(path, |path| fs_err::read_dir(path))?
+            .collect();
         let mut entries = entries?;
 
+        if self.skip_symlinks {
+            let mut kept = Vec::with_capacity(entries.len());
+
+            for entry in entries {
+                if !entry.file_type()?.is_symlink() {
+                    kept.push(entry);
+                }
+            }
+
+            entries = kept;
+        }
+
         entries.sort_by_cached_key(|entry| entry.file_name());
 
         let inner = entries
@@ -79,18 +261,97 @@ impl VfsBackend for StdBackend {
     }
 
     fn remove_file(&mut self, path: &Path) -> io::Result<()> {
+        self.check_writable(path)?;
         fs_err::remove_file(path)
     }
 
     fn remove_dir_all(&mut self, path: &Path) -> io::Result<()> {
+        self.check_writable(path)?;
         fs_err::remove_dir_all(path)
     }
 
+    fn rename(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+        self.check_writable(from)?;
+        fs_err::rename(from, to)?;
+
+        // Watches are kept as small as possible, so if anything at or beneath
+        // `from` was watched, nothing above it was. Those watches are attached
+        // to paths that no longer exist and won't report the rename.
+        let moved: Vec<PathBuf> = self
+            .watches
+            .iter()
+            .filter(|path| path.starts_with(from))
+            .cloned()
+            .collect();
+
+        for old_path in &moved {
+            self.watches.remove(old_path);
+            let _ = self.watcher.unwatch(old_path);
+        }
+
+        // The rename already happened, so failing to watch the new paths
+        // shouldn't make it look like it didn't.
+        for old_path in &moved {
+            let new_path = to.join(old_path.strip_prefix(from).unwrap());
+
+            if let Err(err) = self.watch(&new_path) {
+                log::warn!(
+                    "Could not watch {} after renaming it: {}",
+                    new_path.display(),
+                    err
+                );
+            }
+        }
+
+        // Renames are reported whether or not anything was watched, like the
+        // ones that come from the watcher. The receiver half is owned by this
+        // struct, so sending can't fail.
+        let _ = self.event_sender.send(VfsEvent::Rename {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+        });
+
+        Ok(())
+    }
+
+    fn create_dir_all(&mut self, path: &Path) -> io::Result<()> {
+        self.check_writable(path)?;
+
+        let mut created: Vec<PathBuf> = path
+            .ancestors()
+            .take_while(|ancestor| !ancestor.as_os_str().is_empty() && !ancestor.exists())
+            .map(Path::to_path_buf)
+            .collect();
+
+        fs_err::create_dir_all(path)?;
+
+        created.reverse();
+        for dir in created {
+            let _ = self.event_sender.send(VfsEvent::Create(dir));
+        }
+
+        Ok(())
+    }
+
     fn metadata(&mut self, path: &Path) -> io::Result<Metadata> {
-        let inner = fs_err::metadata(path)?;
+        let inner = self.with_case_fallback(path, |path| fs_err::metadata(path))?;
+
+        Ok(Metadata {
+            is_file: inner.is_file(),
+            is_symlink: false,
+            len: inner.len(),
+            modified: inner.modified().ok(),
+        })
+    }
+
+    fn symlink_metadata(&mut self, path: &Path) -> io::Result<Metadata> {
+        let inner = self.with_case_fallback(path, |path| fs_err::symlink_metadata(path))?;
 
         Ok(Metadata {
             is_file: inner.is_file(),
+            is_symlink: inner.file_type().is_symlink(),
+            len: inner.len(),
+            modified: inner.modified().ok(),
         })
     }
 
@@ -99,6 +360,15 @@ impl VfsBackend for StdBackend {
     }
 
     fn watch(&mut self, path: &Path) -> io::Result<()> {
+        // Paths that were read through the case-insensitive fallback need to
+        // be watched using the case they have on disk.
+        let resolved = if self.case_insensitive && !path.exists() {
+            resolve_case_insensitive(path)
+        } else {
+            None
+        };
+        let path = resolved.as_deref().unwrap_or(path);
+
         if self.watches.contains(path)
             || path
                 .ancestors()
@@ -109,15 +379,56 @@ impl VfsBackend for StdBackend {
             self.watches.insert(path.to_path_buf());
             self.watcher
                 .watch(path, RecursiveMode::Recursive)
-                .map_err(|inner| io::Error::new(io::ErrorKind::Other, inner))
+                .map_err(io::Error::other)
         }
     }
 
     fn unwatch(&mut self, path: &Path) -> io::Result<()> {
         self.watches.remove(path);
-        self.watcher
-            .unwatch(path)
-            .map_err(|inner| io::Error::new(io::ErrorKind::Other, inner))
+        self.watcher.unwatch(path).map_err(io::Error::other)
+    }
+
+    fn unwatch_all(&mut self) -> io::Result<()> {
+        let mut result = Ok(());
+
+        for path in self.watches.drain() {
+            if let Err(err) = self.watcher.unwatch(&path) {
+                if result.is_ok() {
+                    result = Err(io::Error::other(err));
+                }
+            }
+        }
+
+        result
+    }
+
+    fn watched_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self.watches.iter().cloned().collect();
+        paths.sort();
+        paths
+    }
+}
+
+/// Finds the path on disk that matches the given one when ignoring case. Each
+/// component is matched against the entries of its parent, so this only
+/// succeeds if every component matches exactly one entry.
+fn resolve_case_insensitive(path: &Path) -> Option<PathBuf> {
+    if path.exists() {
+        return Some(path.to_path_buf());
+    }
+
+    let parent = resolve_case_insensitive(path.parent()?)?;
+    let name = path.file_name()?.to_str()?.to_lowercase();
+
+    let mut matches = fs_err::read_dir(&parent).ok()?.filter_map(|entry| {
+        let entry = entry.ok()?;
+        let matches = entry.file_name().to_str()?.to_lowercase() == name;
+        matches.then(|| entry.path())
+    });
+
+    match (matches.next(), matches.next()) {
+        (Some(found), None) => Some(found),
+        _ => None,
     }
 }
 
@@ -126,3 +437,233 @@ impl Default for StdBackend {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use tempfile::tempdir;
+
+    #[test]
+    fn rename_moves_watches() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let from = root.join("a");
+        let to = root.join("b").join("renamed");
+
+        fs_err::create_dir_all(&from).unwrap();
+        fs_err::create_dir_all(to.parent().unwrap()).unwrap();
+        fs_err::write(from.join("file.txt"), "hello").unwrap();
+
+        let mut backend = StdBackend::new();
+        let receiver = backend.event_receiver();
+        backend.watch(&from).unwrap();
+
+        backend.rename(&from, &to).unwrap();
+
+        assert_eq!(backend.read(&to.join("file.txt")).unwrap(), b"hello");
+        assert!(!from.exists());
+        assert!(backend.watches.contains(&to));
+        assert!(!backend.watches.contains(&from));
+
+        let event = receiver.try_recv().unwrap();
+        assert_eq!(
+            format!("{:?}", event),
+            format!("{:?}", VfsEvent::Rename { from, to })
+        );
+    }
+
+    #[test]
+    fn rename_unwatched() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let from = root.join("a.txt");
+        let to = root.join("b.txt");
+
+        fs_err::write(&from, "hello").unwrap();
+
+        let mut backend = StdBackend::new();
+        let receiver = backend.event_receiver();
+
+        backend.rename(&from, &to).unwrap();
+
+        assert_eq!(backend.read(&to).unwrap(), b"hello");
+        assert!(backend.watches.is_empty());
+
+        let event = receiver.try_recv().unwrap();
+        assert_eq!(
+            format!("{:?}", event),
+            format!("{:?}", VfsEvent::Rename { from, to })
+        );
+    }
+
+    #[test]
+    fn create_dir_all() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let nested = root.join("a").join("b");
+
+        let mut backend = StdBackend::new();
+        let receiver = backend.event_receiver();
+
+        backend.create_dir_all(&nested).unwrap();
+        backend.create_dir_all(&nested).unwrap();
+
+        assert!(nested.is_dir());
+
+        let events: Vec<VfsEvent> = receiver.try_iter().collect();
+        assert_eq!(
+            format!("{:?}", events),
+            format!(
+                "{:?}",
+                [VfsEvent::Create(root.join("a")), VfsEvent::Create(nested)]
+            )
+        );
+    }
+
+    #[test]
+    fn watched_paths() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let a = root.join("a");
+        let b = root.join("b");
+
+        fs_err::create_dir_all(a.join("nested")).unwrap();
+        fs_err::create_dir_all(&b).unwrap();
+
+        let mut backend = StdBackend::new();
+        backend.watch(&b).unwrap();
+        backend.watch(&a).unwrap();
+        backend.watch(&a.join("nested")).unwrap();
+
+        assert_eq!(backend.watched_paths(), vec![a.clone(), b.clone()]);
+
+        backend.unwatch(&a).unwrap();
+        assert_eq!(backend.watched_paths(), vec![b]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let target = root.join("target.txt");
+        let link = root.join("link.txt");
+
+        fs_err::write(&target, "hello").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let mut backend = StdBackend::new();
+
+        assert!(backend.symlink_metadata(&link).unwrap().is_symlink());
+        assert!(!backend.symlink_metadata(&link).unwrap().is_dir());
+        assert!(!backend.symlink_metadata(&target).unwrap().is_symlink());
+        assert!(backend.metadata(&link).unwrap().is_file());
+        assert_eq!(backend.read_dir(&root).unwrap().count(), 2);
+
+        let mut backend = StdBackend::new().skip_symlinks();
+        let entries: Vec<PathBuf> = backend
+            .read_dir(&root)
+            .unwrap()
+            .map(|entry| entry.unwrap().path)
+            .collect();
+
+        assert_eq!(entries, vec![target]);
+    }
+
+    #[test]
+    fn read_only() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let existing = root.join("existing.txt");
+        fs_err::write(&existing, "hello").unwrap();
+
+        let vfs = crate::Vfs::new(StdBackend::new().read_only());
+
+        let err = vfs.write(root.join("new.txt"), "").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(!root.join("new.txt").exists());
+
+        let err = vfs.remove_file(&existing).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(vfs.read(&existing).unwrap().as_slice(), b"hello");
+
+        let err = vfs.create_dir_all(root.join("dir")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(!root.join("dir").exists());
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs_err::create_dir_all(root.join("Models")).unwrap();
+        fs_err::write(root.join("Models").join("MyModel.rbxmx"), "model").unwrap();
+
+        let differently_cased = root.join("models").join("mymodel.rbxmx");
+
+        // The fallback only matters on case-sensitive filesystems.
+        if differently_cased.exists() {
+            return;
+        }
+
+        let strict = crate::Vfs::new(StdBackend::new());
+        let err = strict.read(&differently_cased).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        let vfs = crate::Vfs::new(StdBackend::new().case_insensitive());
+        assert_eq!(vfs.read(&differently_cased).unwrap().as_slice(), b"model");
+        assert!(vfs.metadata(&differently_cased).unwrap().is_file());
+
+        let entries: Vec<_> = vfs
+            .read_dir(root.join("MODELS"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path().to_path_buf())
+            .collect();
+        assert_eq!(entries, vec![root.join("Models").join("MyModel.rbxmx")]);
+
+        // Paths that could refer to more than one entry are left alone.
+        fs_err::write(root.join("Models").join("MYMODEL.rbxmx"), "other").unwrap();
+        let err = vfs.read(&differently_cased).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn rename_missing() {
+        let dir = tempdir().unwrap();
+        let mut backend = StdBackend::new();
+
+        let err = backend
+            .rename(&dir.path().join("missing"), &dir.path().join("other"))
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn write_batch_leaves_files_alone_on_error() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs_err::write(root.join("a.txt"), "old").unwrap();
+
+        let mut backend = StdBackend::new();
+        let writes = [
+            (root.join("a.txt"), b"new".to_vec()),
+            (root.join("missing").join("b.txt"), b"new".to_vec()),
+        ];
+
+        assert!(backend.write_batch(&writes).is_err());
+        assert_eq!(fs_err::read(root.join("a.txt")).unwrap(), b"old");
+        assert!(!temp_path_for(&root.join("a.txt")).exists());
+
+        let writes = [
+            (root.join("a.txt"), b"new".to_vec()),
+            (root.join("b.txt"), b"new".to_vec()),
+        ];
+
+        backend.write_batch(&writes).unwrap();
+        assert_eq!(fs_err::read(root.join("a.txt")).unwrap(), b"new");
+        assert_eq!(fs_err::read(root.join("b.txt")).unwrap(), b"new");
+        assert_eq!(fs_err::read_dir(root).unwrap().count(), 2);
+    }
+}
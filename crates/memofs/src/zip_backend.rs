@@ -0,0 +1,471 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::in_memory_fs::{must_be_dir, must_be_file, not_found};
+use crate::{DirEntry, Metadata, ReadDir, VfsBackend, VfsEvent};
+
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+const CENTRAL_DIRECTORY_HEADER: u32 = 0x0201_4b50;
+const LOCAL_FILE_HEADER: u32 = 0x0403_4b50;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+/// Read-only `VfsBackend` over the contents of a zip archive.
+///
+/// The archive is kept in memory and every entry in it appears beneath a root
+/// path chosen when the backend is created, so an entry named
+/// `src/init.lua` in an archive mounted at `/project` can be read from
+/// `/project/src/init.lua`. Directories exist whenever an entry exists
+/// somewhere beneath them.
+///
+/// Entries can be stored or compressed with deflate. Operations that would
+/// change the archive fail with `PermissionDenied`, and since archives never
+/// change, no events are ever raised.
+#[derive(Debug)]
+pub struct ZipBackend {
+    contents: Vec<u8>,
+    files: BTreeMap<PathBuf, ZipEntry>,
+    children: BTreeMap<PathBuf, BTreeSet<PathBuf>>,
+}
+
+#[derive(Debug)]
+struct ZipEntry {
+    method: u16,
+    local_header_offset: usize,
+    compressed_size: usize,
+    uncompressed_size: u64,
+}
+
+impl ZipBackend {
+    /// Create a new `ZipBackend` from the bytes of a zip archive, with its
+    /// entries placed beneath `root`.
+    pub fn new<P: Into<PathBuf>>(root: P, contents: Vec<u8>) -> io::Result<Self> {
+        let root = root.into();
+        let mut backend = Self {
+            contents,
+            files: BTreeMap::new(),
+            children: BTreeMap::new(),
+        };
+
+        backend.children.insert(root.clone(), BTreeSet::new());
+
+        let end = find_end_of_central_directory(&backend.contents)?;
+        let entry_count = read_u16(&backend.contents, end + 10)? as usize;
+        let mut offset = read_u32(&backend.contents, end + 16)? as usize;
+
+        for _ in 0..entry_count {
+            if read_u32(&backend.contents, offset)? != CENTRAL_DIRECTORY_HEADER {
+                return invalid_archive("central directory is malformed");
+            }
+
+            let flags = read_u16(&backend.contents, offset + 8)?;
+            let method = read_u16(&backend.contents, offset + 10)?;
+            let compressed_size = read_u32(&backend.contents, offset + 20)?;
+            let uncompressed_size = read_u32(&backend.contents, offset + 24)?;
+            let name_len = read_u16(&backend.contents, offset + 28)? as usize;
+            let extra_len = read_u16(&backend.contents, offset + 30)? as usize;
+            let comment_len = read_u16(&backend.contents, offset + 32)? as usize;
+            let local_header_offset = read_u32(&backend.contents, offset + 42)?;
+            let name = read_bytes(&backend.contents, offset + 46, name_len)?;
+
+            if flags & 1 != 0 {
+                return unsupported("encrypted entries are not supported");
+            }
+
+            if compressed_size == u32::MAX
+                || uncompressed_size == u32::MAX
+                || local_header_offset == u32::MAX
+            {
+                return unsupported("zip64 archives are not supported");
+            }
+
+            let name = std::str::from_utf8(name)
+                .map_err(|_| invalid_data("entry name is not valid UTF-8"))?;
+            let path = entry_path(&root, name)?;
+
+            if name.ends_with('/') {
+                backend.add_dir(&root, path);
+            } else {
+                backend.add_dir(&root, path.parent().unwrap().to_path_buf());
+                backend.add_child(&path);
+                backend.files.insert(
+                    path,
+                    ZipEntry {
+                        method,
+                        local_header_offset: local_header_offset as usize,
+                        compressed_size: compressed_size as usize,
+                        uncompressed_size: uncompressed_size as u64,
+                    },
+                );
+            }
+
+            offset += 46 + name_len + extra_len + comment_len;
+        }
+
+        Ok(backend)
+    }
+
+    /// Create a new `ZipBackend` from the zip archive at `path`, with its
+    /// entries placed beneath the archive's own path.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let contents = fs_err::read(path)?;
+
+        Self::new(path, contents)
+    }
+
+    /// Records a directory and every directory between it and the root.
+    fn add_dir(&mut self, root: &Path, path: PathBuf) {
+        let mut current = path;
+
+        while current != root && !self.children.contains_key(&current) {
+            self.children.insert(current.clone(), BTreeSet::new());
+            self.add_child(&current);
+            current = current.parent().unwrap().to_path_buf();
+        }
+    }
+
+    fn add_child(&mut self, path: &Path) {
+        if let Some(siblings) = self.children.get_mut(path.parent().unwrap()) {
+            siblings.insert(path.to_path_buf());
+        }
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.children.contains_key(path)
+    }
+
+    fn read_entry(&self, entry: &ZipEntry) -> io::Result<Vec<u8>> {
+        let offset = entry.local_header_offset;
+        if read_u32(&self.contents, offset)? != LOCAL_FILE_HEADER {
+            return invalid_archive("local file header is malformed");
+        }
+
+        // The name and extra field can differ from the central directory, so
+        // their lengths are read again here.
+        let name_len = read_u16(&self.contents, offset + 26)? as usize;
+        let extra_len = read_u16(&self.contents, offset + 28)? as usize;
+        let data = read_bytes(
+            &self.contents,
+            offset + 30 + name_len + extra_len,
+            entry.compressed_size,
+        )?;
+
+        let contents = match entry.method {
+            METHOD_STORED => data.to_vec(),
+            METHOD_DEFLATED => miniz_oxide::inflate::decompress_to_vec(data)
+                .map_err(|err| invalid_data(&format!("could not decompress entry: {}", err)))?,
+            method => {
+                return unsupported(&format!("compression method {} is not supported", method))
+            }
+        };
+
+        if contents.len() as u64 != entry.uncompressed_size {
+            return invalid_archive("entry has the wrong size");
+        }
+
+        Ok(contents)
+    }
+}
+
+impl VfsBackend for ZipBackend {
+    fn read(&mut self, path: &Path) -> io::Result<Vec<u8>> {
+        match self.files.get(path) {
+            Some(entry) => self.read_entry(entry),
+            None if self.is_dir(path) => must_be_file(path),
+            None => not_found(path),
+        }
+    }
+
+    fn write(&mut self, path: &Path, _data: &[u8]) -> io::Result<()> {
+        read_only(path)
+    }
+
+    fn read_dir(&mut self, path: &Path) -> io::Result<ReadDir> {
+        match self.children.get(path) {
+            Some(children) => {
+                let entries: Vec<_> = children
+                    .iter()
+                    .map(|child| {
+                        Ok(DirEntry {
+                            path: child.clone(),
+                        })
+                    })
+                    .collect();
+
+                Ok(ReadDir {
+                    inner: Box::new(entries.into_iter()),
+                })
+            }
+            None if self.files.contains_key(path) => must_be_dir(path),
+            None => not_found(path),
+        }
+    }
+
+    fn metadata(&mut self, path: &Path) -> io::Result<Metadata> {
+        if let Some(entry) = self.files.get(path) {
+            Ok(Metadata {
+                is_file: true,
+                is_symlink: false,
+                len: entry.uncompressed_size,
+                modified: None,
+            })
+        } else if self.is_dir(path) {
+            Ok(Metadata {
+                is_file: false,
+                is_symlink: false,
+                len: 0,
+                modified: None,
+            })
+        } else {
+            not_found(path)
+        }
+    }
+
+    fn remove_file(&mut self, path: &Path) -> io::Result<()> {
+        read_only(path)
+    }
+
+    fn remove_dir_all(&mut self, path: &Path) -> io::Result<()> {
+        read_only(path)
+    }
+
+    fn rename(&mut self, from: &Path, _to: &Path) -> io::Result<()> {
+        read_only(from)
+    }
+
+    fn create_dir_all(&mut self, path: &Path) -> io::Result<()> {
+        read_only(path)
+    }
+
+    fn event_receiver(&self) -> crossbeam_channel::Receiver<VfsEvent> {
+        crossbeam_channel::never()
+    }
+
+    fn watch(&mut self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn unwatch(&mut self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn unwatch_all(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Turns the name of an entry into the path it can be found at. Names that
+/// would escape the root, like those containing `..`, are rejected.
+fn entry_path(root: &Path, name: &str) -> io::Result<PathBuf> {
+    let mut path = root.to_path_buf();
+
+    for part in name.split('/').filter(|part| !part.is_empty()) {
+        let mut components = Path::new(part).components();
+
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => path.push(part),
+            _ => {
+                return Err(invalid_data(&format!(
+                    "entry name {} is not a relative path",
+                    name
+                )))
+            }
+        }
+    }
+
+    if path == root {
+        return Err(invalid_data("entry name is empty"));
+    }
+
+    Ok(path)
+}
+
+/// Finds the end of central directory record, which is at the end of the
+/// archive followed by a comment of up to 65535 bytes.
+fn find_end_of_central_directory(contents: &[u8]) -> io::Result<usize> {
+    let last = contents
+        .len()
+        .checked_sub(22)
+        .ok_or_else(|| invalid_data("archive is too short to be a zip file"))?;
+    let first = last.saturating_sub(u16::MAX as usize);
+
+    (first..=last)
+        .rev()
+        .find(|&offset| read_u32(contents, offset).ok() == Some(END_OF_CENTRAL_DIRECTORY))
+        .ok_or_else(|| invalid_data("could not find the end of the central directory"))
+}
+
+fn read_bytes(contents: &[u8], offset: usize, len: usize) -> io::Result<&[u8]> {
+    contents
+        .get(offset..offset.saturating_add(len))
+        .ok_or_else(|| invalid_data("archive is truncated"))
+}
+
+fn read_u16(contents: &[u8], offset: usize) -> io::Result<u16> {
+    let bytes = read_bytes(contents, offset, 2)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(contents: &[u8], offset: usize) -> io::Result<u32> {
+    let bytes = read_bytes(contents, offset, 4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_only<T>(path: &Path) -> io::Result<T> {
+    Err(io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        format!(
+            "cannot modify {}, zip archives are read-only",
+            path.display()
+        ),
+    ))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid zip archive: {}", message),
+    )
+}
+
+fn invalid_archive<T>(message: &str) -> io::Result<T> {
+    Err(invalid_data(message))
+}
+
+fn unsupported<T>(message: &str) -> io::Result<T> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        message.to_owned(),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::Vfs;
+
+    /// Builds a zip archive containing the given entries. Entries are stored
+    /// unless `deflate` is set, and directories are entries ending in `/`.
+    fn build_zip(entries: &[(&str, &str, bool)]) -> Vec<u8> {
+        let mut archive = Vec::new();
+        let mut central_directory = Vec::new();
+
+        for &(name, contents, deflate) in entries {
+            let (method, data) = if deflate {
+                let data = miniz_oxide::deflate::compress_to_vec(contents.as_bytes(), 6);
+                (METHOD_DEFLATED, data)
+            } else {
+                (METHOD_STORED, contents.as_bytes().to_vec())
+            };
+
+            let local_header_offset = archive.len() as u32;
+
+            // The CRC is left as zero since it isn't checked.
+            archive.extend_from_slice(&LOCAL_FILE_HEADER.to_le_bytes());
+            archive.extend_from_slice(&[20, 0, 0, 0]);
+            archive.extend_from_slice(&method.to_le_bytes());
+            archive.extend_from_slice(&[0; 8]);
+            archive.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            archive.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+            archive.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            archive.extend_from_slice(&[0, 0]);
+            archive.extend_from_slice(name.as_bytes());
+            archive.extend_from_slice(&data);
+
+            central_directory.extend_from_slice(&CENTRAL_DIRECTORY_HEADER.to_le_bytes());
+            central_directory.extend_from_slice(&[20, 0, 20, 0, 0, 0]);
+            central_directory.extend_from_slice(&method.to_le_bytes());
+            central_directory.extend_from_slice(&[0; 8]);
+            central_directory.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            central_directory.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+            central_directory.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            central_directory.extend_from_slice(&[0; 12]);
+            central_directory.extend_from_slice(&local_header_offset.to_le_bytes());
+            central_directory.extend_from_slice(name.as_bytes());
+        }
+
+        let central_directory_offset = archive.len() as u32;
+        archive.extend_from_slice(&central_directory);
+
+        archive.extend_from_slice(&END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+        archive.extend_from_slice(&[0; 4]);
+        archive.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        archive.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        archive.extend_from_slice(&(central_directory.len() as u32).to_le_bytes());
+        archive.extend_from_slice(&central_directory_offset.to_le_bytes());
+        archive.extend_from_slice(&[0, 0]);
+
+        archive
+    }
+
+    #[test]
+    fn read_entries() {
+        let archive = build_zip(&[
+            ("default.project.json", "{}", false),
+            ("src/init.lua", "return 'compressed'", true),
+            ("src/nested/", "", false),
+        ]);
+        let vfs = Vfs::new(ZipBackend::new("/project", archive).unwrap());
+
+        assert_eq!(
+            vfs.read("/project/default.project.json")
+                .unwrap()
+                .as_slice(),
+            b"{}"
+        );
+        assert_eq!(
+            vfs.read("/project/src/init.lua").unwrap().as_slice(),
+            b"return 'compressed'"
+        );
+
+        let metadata = vfs.metadata("/project/src/init.lua").unwrap();
+        assert!(metadata.is_file());
+        assert_eq!(metadata.len(), 19);
+        assert!(vfs.metadata("/project/src/nested").unwrap().is_dir());
+
+        let mut children: Vec<_> = vfs
+            .read_dir("/project/src")
+            .unwrap()
+            .map(|entry| entry.unwrap().path().to_path_buf())
+            .collect();
+        children.sort();
+        assert_eq!(
+            children,
+            vec![
+                PathBuf::from("/project/src/init.lua"),
+                PathBuf::from("/project/src/nested"),
+            ]
+        );
+
+        let err = vfs.read("/project/missing.lua").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn read_only() {
+        let archive = build_zip(&[("a.txt", "a", false)]);
+        let vfs = Vfs::new(ZipBackend::new("/project", archive).unwrap());
+
+        let err = vfs.write("/project/a.txt", "b").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+
+        let err = vfs.remove_file("/project/a.txt").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+
+        assert_eq!(vfs.read("/project/a.txt").unwrap().as_slice(), b"a");
+    }
+
+    #[test]
+    fn invalid_archives() {
+        let err = ZipBackend::new("/project", b"not a zip".to_vec()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let archive = build_zip(&[("../escape.txt", "", false)]);
+        let err = ZipBackend::new("/project", archive).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
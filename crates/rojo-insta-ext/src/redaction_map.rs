@@ -10,6 +10,12 @@ pub struct RedactionMap {
     last_id: usize,
 }
 
+impl Default for RedactionMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RedactionMap {
     pub fn new() -> Self {
         Self {
@@ -0,0 +1,3 @@
+local Util = require(game.ReplicatedStorage.Shared.Util)
+
+print(Util.Strings.shout("hello"))
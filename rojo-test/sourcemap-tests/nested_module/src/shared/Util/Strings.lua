@@ -0,0 +1,7 @@
+local Strings = {}
+
+function Strings.shout(text)
+	return string.upper(text) .. "!"
+end
+
+return Strings
@@ -0,0 +1,3 @@
+return {
+	Strings = require(script.Strings),
+}
@@ -0,0 +1,76 @@
+use std::path::Path;
+
+use memofs::Vfs;
+use rbx_dom_weak::WeakDom;
+
+use crate::{
+    serve_session::{load_root_project, tree_from_snapshot},
+    snapshot::{
+        apply_patch_set, compute_patch_set, normalize_dom_script_classes,
+        normalize_snapshot_script_classes, InstanceContext, InstanceSnapshot, RojoTree,
+    },
+    snapshot_middleware::{resolve_ref_paths, snapshot_from_vfs},
+};
+
+/// Builds the instances described by a project, the same way `rojo build`
+/// does, without starting a serve session or watching for changes.
+///
+/// `start_path` can either be a project file or a folder containing a
+/// `default.project.json` file. Everything is read through the given `Vfs`, so
+/// projects that only exist in memory can be built by using a backend like
+/// `InMemoryFs`.
+pub fn build_tree(vfs: &Vfs, start_path: &Path) -> anyhow::Result<WeakDom> {
+    let (_, snapshot) = snapshot_project(vfs, start_path)?;
+
+    Ok(tree_from_snapshot(snapshot).into_inner())
+}
+
+/// Updates an existing tree of instances, like one loaded from a place file,
+/// to match a project.
+///
+/// This goes through the same steps Rojo uses to update its tree when files
+/// change during a serve session. Instances that line up with the project
+/// are updated in place and keep their referents, and new instances are
+/// added. Instances that the project doesn't describe are kept under nodes
+/// that ignore unknown instances, like nodes without a `$path`, and removed
+/// everywhere else. The project is read the same way as in `build_tree`.
+///
+/// Scripts in both the input and the project are converted to the classes
+/// that the project's `emitLegacyScripts` setting calls for first, so a
+/// `LocalScript` in the input lines up with a client script in the project
+/// instead of having its class changed.
+pub fn patch_tree(mut input: WeakDom, vfs: &Vfs, start_path: &Path) -> anyhow::Result<WeakDom> {
+    let (context, mut snapshot) = snapshot_project(vfs, start_path)?;
+
+    normalize_dom_script_classes(&context, &mut input);
+    if let Some(snapshot) = &mut snapshot {
+        normalize_snapshot_script_classes(&context, snapshot);
+    }
+
+    let mut tree = RojoTree::from_dom(input);
+    let root_id = tree.get_root_id();
+
+    log::trace!("Computing patch set");
+    let patch_set = compute_patch_set(snapshot, &tree, root_id);
+
+    log::trace!("Applying patch set");
+    apply_patch_set(&mut tree, patch_set);
+
+    Ok(tree.into_inner())
+}
+
+fn snapshot_project(
+    vfs: &Vfs,
+    start_path: &Path,
+) -> anyhow::Result<(InstanceContext, Option<InstanceSnapshot>)> {
+    let root_project = load_root_project(vfs, start_path)?;
+    let context = InstanceContext::with_emit_legacy_scripts(root_project.emit_legacy_scripts);
+
+    let mut snapshot = snapshot_from_vfs(&context, vfs, start_path)?;
+
+    if let Some(snapshot) = &mut snapshot {
+        resolve_ref_paths(snapshot)?;
+    }
+
+    Ok((context, snapshot))
+}
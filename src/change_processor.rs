@@ -11,9 +11,10 @@ use rbx_dom_weak::types::{Ref, Variant};
 use crate::{
     message_queue::MessageQueue,
     snapshot::{
-        apply_patch_set, compute_patch_set, AppliedPatchSet, InstigatingSource, PatchSet, RojoTree,
+        apply_patch_set, compute_patch_set, AppliedPatchSet, InstanceSnapshot, InstigatingSource,
+        PatchSet, RojoTree, SnapshotCache,
     },
-    snapshot_middleware::{snapshot_from_vfs, snapshot_project_node},
+    snapshot_middleware::{resolve_ref_paths_in_tree, snapshot_from_vfs, snapshot_project_node},
 };
 
 /// Processes file change events, updates the DOM, and sends those updates
@@ -51,6 +52,7 @@ impl ChangeProcessor {
         vfs: Arc<Vfs>,
         message_queue: Arc<MessageQueue<AppliedPatchSet>>,
         tree_mutation_receiver: Receiver<PatchSet>,
+        snapshot_cache: SnapshotCache,
     ) -> Self {
         let (shutdown_sender, shutdown_receiver) = crossbeam_channel::bounded(1);
         let vfs_receiver = vfs.event_receiver();
@@ -58,6 +60,7 @@ impl ChangeProcessor {
             tree,
             vfs,
             message_queue,
+            snapshot_cache,
         };
 
         let job_thread = jod_thread::Builder::new()
@@ -111,6 +114,10 @@ struct JobThreadContext {
     /// Whenever changes are applied to the DOM, we should push those changes
     /// into this message queue to inform any connected clients.
     message_queue: Arc<MessageQueue<AppliedPatchSet>>,
+
+    /// Snapshots of files shared with the tree's instance contexts. Entries
+    /// are invalidated here whenever the files they came from change.
+    snapshot_cache: SnapshotCache,
 }
 
 impl JobThreadContext {
@@ -124,19 +131,34 @@ impl JobThreadContext {
 
         // For a given VFS event, we might have many changes to different parts
         // of the tree. Calculate and apply all of these changes.
-        let applied_patches = match event {
+        let changed_paths = match &event {
             VfsEvent::Create(path) | VfsEvent::Remove(path) | VfsEvent::Write(path) => {
-                let mut tree = self.tree.lock().unwrap();
-                let mut applied_patches = Vec::new();
-
-                // Find the nearest ancestor to this path that has
-                // associated instances in the tree. This helps make sure
-                // that we handle additions correctly, especially if we
-                // receive events for descendants of a large tree being
-                // created all at once.
-                let mut current_path = path.as_path();
+                vec![path.as_path()]
+            }
+            // Whatever was at the old path is gone, and the new path may be
+            // somewhere else in the tree entirely, so both need updating.
+            VfsEvent::Rename { from, to } => vec![from.as_path(), to.as_path()],
+            _ => {
+                log::warn!("Unhandled VFS event: {:?}", event);
+                Vec::new()
+            }
+        };
+
+        let mut applied_patches = Vec::new();
+
+        if !changed_paths.is_empty() {
+            let mut tree = self.tree.lock().unwrap();
+
+            for path in changed_paths {
+                self.snapshot_cache.invalidate(path);
+
+                // Find the nearest ancestor to this path that has associated
+                // instances in the tree. This helps make sure that we handle
+                // additions correctly, especially if we receive events for
+                // descendants of a large tree being created all at once.
+                let mut current_path = path;
                 let affected_ids = loop {
-                    let ids = tree.get_ids_at_path(&current_path);
+                    let ids = tree.get_ids_at_path(current_path);
 
                     log::trace!("Path {} affects IDs {:?}", current_path.display(), ids);
 
@@ -158,14 +180,8 @@ impl JobThreadContext {
                         }
                     }
                 }
-
-                applied_patches
-            }
-            _ => {
-                log::warn!("Unhandled VFS event: {:?}", event);
-                Vec::new()
             }
-        };
+        }
 
         // Notify anyone listening to the message queue about the changes we
         // just made.
@@ -288,7 +304,9 @@ fn compute_and_apply_changes(tree: &mut RojoTree, vfs: &Vfs, id: Ref) -> Option<
                 // path still exists. We can generate a snapshot starting at
                 // that path and use it as the source for our patch.
 
-                let snapshot = match snapshot_from_vfs(&metadata.context, vfs, path) {
+                let snapshot = match snapshot_from_vfs(&metadata.context, vfs, path)
+                    .and_then(|snapshot| with_ref_paths(snapshot, tree))
+                {
                     Ok(snapshot) => snapshot,
                     Err(err) => {
                         log::error!("Snapshot error: {:?}", err);
@@ -331,7 +349,8 @@ fn compute_and_apply_changes(tree: &mut RojoTree, vfs: &Vfs, id: Ref) -> Option<
                 parent_class.as_ref().map(|name| name.as_str()),
             );
 
-            let snapshot = match snapshot_result {
+            let snapshot = match snapshot_result.and_then(|snapshot| with_ref_paths(snapshot, tree))
+            {
                 Ok(snapshot) => snapshot,
                 Err(err) => {
                     log::error!("{:?}", err);
@@ -346,3 +365,18 @@ fn compute_and_apply_changes(tree: &mut RojoTree, vfs: &Vfs, id: Ref) -> Option<
 
     Some(applied_patch_set)
 }
+
+/// Resolves Refs given by path in a snapshot of part of the tree against the
+/// rest of the tree.
+fn with_ref_paths(
+    snapshot: Option<InstanceSnapshot>,
+    tree: &RojoTree,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let mut snapshot = snapshot;
+
+    if let Some(snapshot) = &mut snapshot {
+        resolve_ref_paths_in_tree(snapshot, tree)?;
+    }
+
+    Ok(snapshot)
+}
@@ -1,19 +1,50 @@
 use std::{
-    io::{BufWriter, Write},
+    collections::HashSet,
+    env,
+    io::{self, BufWriter, Read, Write},
     mem::forget,
     path::{Path, PathBuf},
+    str::FromStr,
+    thread,
+    time::{Duration, SystemTime},
 };
 
-use anyhow::{bail, Context};
+use anyhow::{bail, format_err, Context};
 use clap::{CommandFactory, Parser};
 use fs_err::File;
-use memofs::Vfs;
+use memofs::{IoResultExt, MemoryBackend, OverlayBackend, StdBackend, Vfs};
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    InstanceBuilder, WeakDom,
+};
 use roblox_install::RobloxStudio;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
 use tokio::runtime::Runtime;
 
-use crate::serve_session::ServeSession;
+use crate::{
+    serve_session::{ServeSession, SessionOptions},
+    snapshot::{RojoTree, SnapshotProgress},
+};
+
+use super::{
+    compression::{recompress, Compression},
+    exit_code::Unsupported,
+    progress::ProgressReporter,
+    resolve_path,
+    xml_format::{reformat, XmlFormat},
+};
+
+/// The project path that means the project should be read from stdin.
+const STDIN_PROJECT_ARG: &str = "-";
 
-use super::resolve_path;
+/// Where a project read from stdin appears to be, relative to the current
+/// directory. Its name only needs to be one that Rojo sees as a project file.
+const STDIN_PROJECT_FILE_NAME: &str = "stdin.project.json";
+
+/// How long to wait for more changes before rebuilding in watch mode.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(50);
 
 const UNKNOWN_OUTPUT_KIND_ERR: &str = "Could not detect what kind of file to build. \
                                        Expected output file to end in .rbxl, .rbxlx, .rbxm, or .rbxmx.";
@@ -24,6 +55,9 @@ const UNKNOWN_PLUGIN_KIND_ERR: &str = "Could not detect what kind of file to bui
 #[derive(Debug, Parser)]
 pub struct BuildCommand {
     /// Path to the project to serve. Defaults to the current directory.
+    ///
+    /// Pass - to read the project from stdin instead. Paths in the project are
+    /// then relative to the current directory.
     #[clap(default_value = "")]
     pub project: PathBuf,
 
@@ -39,52 +73,162 @@ pub struct BuildCommand {
     #[clap(long, short, conflicts_with = "output")]
     pub plugin: Option<PathBuf>,
 
+    /// Alternative to the output flag that writes each child of the project's
+    /// root to its own .rbxmx file in the given directory.
+    ///
+    /// An index.json file listing the files in order is written alongside them.
+    #[clap(long, conflicts_with_all = &["output", "plugin"])]
+    pub unpacked: Option<PathBuf>,
+
+    /// What kind of file to build. Valid values are place, model, and plugin.
+    ///
+    /// Places contain the children of the project's root, models contain the
+    /// root itself, and plugins contain the children of a root that isn't a
+    /// DataModel. Defaults to place for .rbxl and .rbxlx files and to model
+    /// otherwise.
+    #[clap(long, conflicts_with = "unpacked")]
+    pub kind: Option<BuildKind>,
+
+    /// Only build the instance at the given path and its descendants, like
+    /// ReplicatedStorage.Shared. The instance is always built as a model.
+    #[clap(long, conflicts_with_all = &["unpacked", "kind"])]
+    pub select: Option<String>,
+
+    /// Leave instances of the given class, and everything inside them, out of
+    /// the output. Can be given more than once. Refs to instances that are
+    /// left out are cleared.
+    #[clap(long, value_name = "CLASS")]
+    pub exclude_class: Vec<String>,
+
+    /// Also write a hash of the output to a file next to it, named after the
+    /// output with the algorithm added as an extension. Valid values are
+    /// sha256, blake3, and none.
+    #[clap(long, default_value = "none", conflicts_with = "unpacked")]
+    pub hash: HashAlgorithm,
+
+    /// How much to compress binary output. Valid values are none, fast, and
+    /// best. Has no effect on XML output.
+    #[clap(long, default_value = "fast", conflicts_with = "unpacked")]
+    pub compression: Compression,
+
+    /// How to lay out XML output. Valid values are pretty, which puts each
+    /// element on its own indented line, and compact, which leaves out all
+    /// whitespace between elements. Has no effect on binary output.
+    #[clap(long, default_value = "pretty")]
+    pub xml_format: XmlFormat,
+
     /// Whether to automatically rebuild when any input files change.
     #[clap(long)]
     pub watch: bool,
+
+    /// Treat problems that are usually only warned about or allowed, like
+    /// files whose names only differ by case, unknown properties in XML
+    /// models, or unknown `$` keys in projects, as errors.
+    #[clap(long)]
+    pub strict: bool,
 }
 
 impl BuildCommand {
     pub fn run(self) -> anyhow::Result<()> {
-        let (output_path, output_kind) = match (self.output, self.plugin) {
-            (None, None) => {
+        // A selected instance is always built on its own.
+        let kind = match self.select {
+            Some(_) => Some(BuildKind::Model),
+            None => self.kind,
+        };
+
+        let output = match (self.output, self.plugin, self.unpacked) {
+            (None, None, None) => {
                 BuildCommand::command()
                     .error(
                         clap::ErrorKind::MissingRequiredArgument,
-                        "one of the following arguments must be provided: \n    --output <OUTPUT>\n    --plugin <PLUGIN>",
+                        "one of the following arguments must be provided: \n    --output <OUTPUT>\n    --plugin <PLUGIN>\n    --unpacked <UNPACKED>",
                     )
                     .exit();
             }
-            (Some(output), None) => {
-                let output_kind =
-                    OutputKind::from_output_path(&output).context(UNKNOWN_OUTPUT_KIND_ERR)?;
-
-                (output, output_kind)
+            (Some(output), None, None) => {
+                let output_kind = OutputKind::from_output_path(&output)
+                    .ok_or(Unsupported(UNKNOWN_OUTPUT_KIND_ERR))?;
+
+                let build_kind = kind.unwrap_or_else(|| output_kind.default_build_kind());
+
+                BuildOutput::File(FileOutput {
+                    path: output,
+                    output_kind,
+                    build_kind,
+                    hash: self.hash,
+                    compression: self.compression,
+                    xml_format: self.xml_format,
+                    select: self.select.clone(),
+                })
             }
-            (None, Some(plugin)) => {
+            (None, Some(plugin), None) => {
                 if plugin.is_absolute() {
                     bail!("plugin flag path cannot be absolute.")
                 }
 
-                let output_kind =
-                    OutputKind::from_plugin_path(&plugin).context(UNKNOWN_PLUGIN_KIND_ERR)?;
+                let output_kind = OutputKind::from_plugin_path(&plugin)
+                    .ok_or(Unsupported(UNKNOWN_PLUGIN_KIND_ERR))?;
                 let studio = RobloxStudio::locate()?;
 
-                (studio.plugins_path().join(&plugin), output_kind)
+                let build_kind = kind.unwrap_or_else(|| output_kind.default_build_kind());
+
+                BuildOutput::File(FileOutput {
+                    path: studio.plugins_path().join(&plugin),
+                    output_kind,
+                    build_kind,
+                    hash: self.hash,
+                    compression: self.compression,
+                    xml_format: self.xml_format,
+                    select: self.select.clone(),
+                })
             }
+            (None, None, Some(unpacked)) => BuildOutput::Unpacked {
+                path: unpacked,
+                xml_format: self.xml_format,
+            },
             _ => unreachable!(),
         };
 
-        let project_path = resolve_path(&self.project);
-
         log::trace!("Constructing in-memory filesystem");
-        let vfs = Vfs::new_default();
+        // Building should never change the project, so the filesystem is
+        // read-only to make sure of it.
+        let (vfs, project_path) = if self.project == Path::new(STDIN_PROJECT_ARG) {
+            stdin_project_vfs()?
+        } else {
+            let vfs = Vfs::new(StdBackend::new().read_only());
+            (vfs, resolve_path(&self.project).into_owned())
+        };
         vfs.set_watch_enabled(self.watch);
 
-        let session = ServeSession::new(vfs, project_path)?;
+        // A single build reports every broken file at once. While watching,
+        // broken files are reported as they change instead.
+        let progress = SnapshotProgress::new();
+        let options = SessionOptions {
+            collect_errors: !self.watch,
+            strict: self.strict,
+            progress: Some(progress.clone()),
+        };
+
+        let reporter = ProgressReporter::start(progress);
+        let result = ServeSession::with_options(vfs, project_path, options);
+        drop(reporter);
+
+        let (session, errors) = result?;
+
+        if !errors.is_empty() {
+            for error in &errors {
+                log::error!("{}", error);
+            }
+
+            return Err(FilesHadErrors {
+                count: errors.len(),
+            }
+            .into());
+        }
+
         let mut cursor = session.message_queue().cursor();
 
-        write_model(&session, &output_path, output_kind)?;
+        output.write(&session, &self.exclude_class)?;
 
         if self.watch {
             let rt = Runtime::new().unwrap();
@@ -94,7 +238,27 @@ impl BuildCommand {
                 let (new_cursor, _patch_set) = rt.block_on(receiver).unwrap();
                 cursor = new_cursor;
 
-                write_model(&session, &output_path, output_kind)?;
+                // Keep waiting until changes stop arriving so that saving
+                // several files at once only causes one rebuild.
+                loop {
+                    thread::sleep(WATCH_DEBOUNCE);
+
+                    let latest = session.message_queue().cursor();
+                    if latest == cursor {
+                        break;
+                    }
+                    cursor = latest;
+                }
+
+                // A failed rebuild shouldn't stop watching, since the next
+                // change might fix it.
+                match output.write(&session, &self.exclude_class) {
+                    Ok(()) => println!(
+                        "[{}] Rebuilt project",
+                        humantime::format_rfc3339_seconds(SystemTime::now())
+                    ),
+                    Err(err) => log::error!("Could not rebuild project: {:?}", err),
+                }
             }
         }
 
@@ -106,6 +270,68 @@ impl BuildCommand {
     }
 }
 
+/// Returned when files in the project had errors, after each of them has been
+/// logged.
+#[derive(Debug, Error)]
+#[error(
+    "Could not build the project because {count} {} had errors",
+    if *.count == 1 { "file" } else { "files" }
+)]
+pub(super) struct FilesHadErrors {
+    count: usize,
+}
+
+/// Where the result of a build is written.
+enum BuildOutput {
+    /// A single model or place file.
+    File(FileOutput),
+
+    /// A directory containing one model file for each child of the root.
+    Unpacked {
+        path: PathBuf,
+        xml_format: XmlFormat,
+    },
+}
+
+/// How a single model or place file is built.
+struct FileOutput {
+    path: PathBuf,
+    output_kind: OutputKind,
+    build_kind: BuildKind,
+    hash: HashAlgorithm,
+    compression: Compression,
+    xml_format: XmlFormat,
+
+    /// The path of the instance to build instead of the project's root.
+    select: Option<String>,
+}
+
+impl BuildOutput {
+    fn write(&self, session: &ServeSession, excluded_classes: &[String]) -> anyhow::Result<()> {
+        println!("Building project '{}'", session.project_name());
+
+        let session_tree = session.tree();
+
+        // Instances are excluded from a copy of the tree, since the session's
+        // tree needs to stay whole for changes to be applied to it when
+        // watching.
+        let excluded_tree;
+        let tree = if excluded_classes.is_empty() {
+            &*session_tree
+        } else {
+            let mut tree = copy_tree(&session_tree);
+            exclude_classes(&mut tree, excluded_classes);
+            excluded_tree = tree;
+            &excluded_tree
+        };
+
+        match self {
+            BuildOutput::File(output) => write_model(tree, output),
+            BuildOutput::Unpacked { path, xml_format } => write_unpacked(tree, path, *xml_format),
+        }
+    }
+}
+
 /// The different kinds of output that Rojo can build to.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum OutputKind {
@@ -144,60 +370,418 @@ impl OutputKind {
             _ => None,
         }
     }
-}
 
-fn xml_encode_config() -> rbx_xml::EncodeOptions<'static> {
-    rbx_xml::EncodeOptions::new().property_behavior(rbx_xml::EncodePropertyBehavior::WriteUnknown)
+    /// The kind of build to use when one isn't given with `--kind`.
+    fn default_build_kind(self) -> BuildKind {
+        match self {
+            OutputKind::Rbxl | OutputKind::Rbxlx => BuildKind::Place,
+            OutputKind::Rbxm | OutputKind::Rbxmx => BuildKind::Model,
+        }
+    }
+
+    fn is_binary(self) -> bool {
+        matches!(self, OutputKind::Rbxm | OutputKind::Rbxl)
+    }
 }
 
-#[profiling::function]
-fn write_model(
-    session: &ServeSession,
-    output: &Path,
-    output_kind: OutputKind,
-) -> anyhow::Result<()> {
-    println!("Building project '{}'", session.project_name());
+/// Decides which instances from the tree end up at the top of a built file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildKind {
+    /// The children of the root, which is usually a DataModel. Place files
+    /// don't contain an entry for the DataModel, but our WeakDom
+    /// representation does.
+    Place,
+
+    /// The root instance of the tree and all of its descendants.
+    Model,
+
+    /// The children of the root, like a place, but the root can't be a
+    /// DataModel. Plugins are usually a folder of scripts that Roblox Studio
+    /// loads without the folder itself.
+    Plugin,
+}
 
-    let tree = session.tree();
-    let root_id = tree.get_root_id();
+impl FromStr for BuildKind {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match source {
+            "place" => Ok(BuildKind::Place),
+            "model" => Ok(BuildKind::Model),
+            "plugin" => Ok(BuildKind::Plugin),
+            attempted => Err(format_err!(
+                "Invalid build kind '{}'. Valid kinds are: place, model, plugin",
+                attempted
+            )),
+        }
+    }
+}
 
-    log::trace!("Opening output file for write");
-    let mut file = BufWriter::new(File::create(output)?);
+/// The algorithms that can be used to hash a built file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Blake3,
+    None,
+}
 
-    match output_kind {
-        OutputKind::Rbxm => {
-            rbx_binary::to_writer(&mut file, tree.inner(), &[root_id])?;
+impl HashAlgorithm {
+    /// Returns the hash of the given bytes as lowercase hex, or `None` if
+    /// nothing should be hashed.
+    fn hash(self, contents: &[u8]) -> Option<String> {
+        match self {
+            HashAlgorithm::Sha256 => Some(format!("{:x}", Sha256::digest(contents))),
+            HashAlgorithm::Blake3 => Some(blake3::hash(contents).to_hex().to_string()),
+            HashAlgorithm::None => None,
         }
-        OutputKind::Rbxl => {
-            let root_instance = tree.get_instance(root_id).unwrap();
-            let top_level_ids = root_instance.children();
+    }
 
-            rbx_binary::to_writer(&mut file, tree.inner(), top_level_ids)?;
+    fn extension(self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Blake3 => "blake3",
+            HashAlgorithm::None => "",
         }
-        OutputKind::Rbxmx => {
-            // Model files include the root instance of the tree and all its
-            // descendants.
+    }
+}
 
-            rbx_xml::to_writer(&mut file, tree.inner(), &[root_id], xml_encode_config())?;
+impl FromStr for HashAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match source {
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            "blake3" => Ok(HashAlgorithm::Blake3),
+            "none" => Ok(HashAlgorithm::None),
+            attempted => Err(format_err!(
+                "Invalid hash algorithm '{}'. Valid algorithms are: sha256, blake3, none",
+                attempted
+            )),
         }
-        OutputKind::Rbxlx => {
-            // Place files don't contain an entry for the DataModel, but our
-            // WeakDom representation does.
+    }
+}
+
+/// Reads a project from stdin and creates a filesystem where it appears as a
+/// file in the current directory, on top of the real filesystem. Returns the
+/// filesystem and the path of the project in it.
+fn stdin_project_vfs() -> anyhow::Result<(Vfs, PathBuf)> {
+    let mut contents = Vec::new();
+    io::stdin()
+        .read_to_end(&mut contents)
+        .context("Could not read the project from stdin")?;
 
-            let root_instance = tree.get_instance(root_id).unwrap();
-            let top_level_ids = root_instance.children();
+    let project_path = env::current_dir()?.join(STDIN_PROJECT_FILE_NAME);
+    let overlay = MemoryBackend::with_files([(project_path.clone(), contents)]);
+    let vfs = Vfs::new(OverlayBackend::new(StdBackend::new().read_only(), overlay));
 
-            rbx_xml::to_writer(&mut file, tree.inner(), top_level_ids, xml_encode_config())?;
+    Ok((vfs, project_path))
+}
+
+fn xml_encode_config() -> rbx_xml::EncodeOptions<'static> {
+    rbx_xml::EncodeOptions::new().property_behavior(rbx_xml::EncodePropertyBehavior::WriteUnknown)
+}
+
+#[profiling::function]
+fn write_model(tree: &RojoTree, output: &FileOutput) -> anyhow::Result<()> {
+    let root_id = match &output.select {
+        Some(path) => select_instance(tree, path)?,
+        None => tree.get_root_id(),
+    };
+    let root_instance = tree.get_instance(root_id).unwrap();
+
+    let top_level_ids = match output.build_kind {
+        BuildKind::Model => vec![root_id],
+        BuildKind::Place => root_instance.children().to_vec(),
+        BuildKind::Plugin => {
+            if root_instance.class_name() == "DataModel" {
+                bail!(
+                    "Plugins can't be built from a project whose root is a DataModel. \
+                     Use a Folder as the root of the project instead."
+                );
+            }
+
+            root_instance.children().to_vec()
         }
+    };
+
+    // The file is serialized in memory first so that it can be hashed.
+    let mut contents = Vec::new();
+
+    if output.output_kind.is_binary() {
+        rbx_binary::to_writer(&mut contents, tree.inner(), &top_level_ids)?;
+        contents = recompress(contents, output.compression)?;
+    } else {
+        rbx_xml::to_writer(
+            &mut contents,
+            tree.inner(),
+            &top_level_ids,
+            xml_encode_config(),
+        )?;
+        contents = reformat(contents, output.xml_format);
     }
 
-    file.flush()?;
-
     let filename = output
+        .path
         .file_name()
         .and_then(|name| name.to_str())
         .unwrap_or("<invalid utf-8>");
+
+    let hash_path = output.hash.hash(&contents).map(|digest| {
+        let mut hash_path = output.path.as_os_str().to_owned();
+        hash_path.push(".");
+        hash_path.push(output.hash.extension());
+        (PathBuf::from(hash_path), digest)
+    });
+
+    // A hash from an earlier build is removed first, and the new one is only
+    // written once the output has been, so that a hash never describes a file
+    // that failed to write.
+    if let Some((hash_path, _)) = &hash_path {
+        fs_err::remove_file(hash_path).with_not_found()?;
+    }
+
+    log::trace!("Opening output file for write");
+    let mut file = BufWriter::new(File::create(&output.path)?);
+    file.write_all(&contents)?;
+    file.flush()?;
+
+    if let Some((hash_path, digest)) = hash_path {
+        // This matches the format of tools like sha256sum so that the file can
+        // be checked with them.
+        log::trace!("Writing hash to {}", hash_path.display());
+        fs_err::write(&hash_path, format!("{}  {}\n", digest, filename))?;
+    }
+
     println!("Built project to {}", filename);
 
     Ok(())
 }
+
+/// Copies a tree to build from. Refs between the root's descendants point to
+/// the copied instances, and Refs to or from the root are cleared. The copy has
+/// none of Rojo's metadata.
+fn copy_tree(tree: &RojoTree) -> RojoTree {
+    let source = tree.inner();
+    let root = source.root();
+
+    let mut dom = WeakDom::new(
+        InstanceBuilder::new(root.class.as_str())
+            .with_name(root.name.as_str())
+            .with_properties(root.properties.clone()),
+    );
+
+    let root_id = dom.root_ref();
+    for child_id in source.clone_multiple_into_external(root.children(), &mut dom) {
+        dom.transfer_within(child_id, root_id);
+    }
+
+    RojoTree::from_dom(dom)
+}
+
+/// Removes every instance of the given classes from the tree, along with its
+/// descendants. Refs to removed instances are cleared so that they don't point
+/// to instances that aren't in the output. The root is always kept.
+fn exclude_classes(tree: &mut RojoTree, class_names: &[String]) {
+    if class_names.is_empty() {
+        return;
+    }
+
+    let root_id = tree.get_root_id();
+    let excluded: Vec<Ref> = tree
+        .descendants(root_id)
+        .skip(1)
+        .filter(|instance| class_names.iter().any(|name| name == instance.class_name()))
+        .map(|instance| instance.id())
+        .collect();
+
+    if excluded.is_empty() {
+        return;
+    }
+
+    for id in excluded {
+        // Instances inside of another excluded instance are already gone.
+        if let Some(instance) = tree.get_instance(id) {
+            log::trace!("Excluding {}", instance.name());
+            tree.remove(id);
+        }
+    }
+
+    let remaining: Vec<Ref> = tree
+        .descendants(root_id)
+        .map(|instance| instance.id())
+        .collect();
+
+    for id in remaining {
+        let dangling: Vec<String> = tree
+            .get_instance(id)
+            .unwrap()
+            .properties()
+            .iter()
+            .filter(|(_, value)| match value {
+                Variant::Ref(target) => target.is_some() && tree.get_instance(*target).is_none(),
+                _ => false,
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        if !dangling.is_empty() {
+            let mut instance = tree.get_instance_mut(id).unwrap();
+            for key in dangling {
+                instance
+                    .properties_mut()
+                    .insert(key, Variant::Ref(Ref::none()));
+            }
+        }
+    }
+}
+
+/// Finds the instance picked with the select flag.
+fn select_instance(tree: &RojoTree, path: &str) -> anyhow::Result<Ref> {
+    if let Some(id) = tree.find_by_path(path) {
+        return Ok(id);
+    }
+
+    let root_instance = tree.get_instance(tree.get_root_id()).unwrap();
+    let top_level_names: Vec<_> = root_instance
+        .children()
+        .iter()
+        .map(|&id| tree.get_instance(id).unwrap().name())
+        .collect();
+
+    bail!(
+        "Could not find an instance at path {} to select. \
+         The project's top-level instances are: {}",
+        path,
+        top_level_names.join(", ")
+    );
+}
+
+/// Describes the files written by an unpacked build, in the order the
+/// instances they contain appear in the project.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UnpackedIndex<'a> {
+    name: &'a str,
+    class_name: &'a str,
+    children: Vec<UnpackedEntry<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UnpackedEntry<'a> {
+    name: &'a str,
+    class_name: &'a str,
+    file: String,
+}
+
+#[profiling::function]
+fn write_unpacked(tree: &RojoTree, output: &Path, xml_format: XmlFormat) -> anyhow::Result<()> {
+    let root_instance = tree.get_instance(tree.get_root_id()).unwrap();
+
+    fs_err::create_dir_all(output)?;
+
+    let mut used_names = HashSet::new();
+    let mut children = Vec::new();
+
+    for &child_id in root_instance.children() {
+        let child = tree.get_instance(child_id).unwrap();
+        let file = unique_file_name(child.name(), &mut used_names);
+
+        log::trace!("Writing {}", file);
+        let mut contents = Vec::new();
+        rbx_xml::to_writer(
+            &mut contents,
+            tree.inner(),
+            &[child_id],
+            xml_encode_config(),
+        )?;
+        fs_err::write(output.join(&file), reformat(contents, xml_format))?;
+
+        children.push(UnpackedEntry {
+            name: child.name(),
+            class_name: child.class_name(),
+            file,
+        });
+    }
+
+    let index = UnpackedIndex {
+        name: root_instance.name(),
+        class_name: root_instance.class_name(),
+        children,
+    };
+
+    let mut writer = BufWriter::new(File::create(output.join("index.json"))?);
+    serde_json::to_writer_pretty(&mut writer, &index)?;
+    writer.flush()?;
+
+    println!("Built project to {}", output.display());
+
+    Ok(())
+}
+
+/// Picks a file name for an instance that's safe to use on every platform and
+/// doesn't collide with any file name picked before it.
+fn unique_file_name(instance_name: &str, used_names: &mut HashSet<String>) -> String {
+    let base: String = instance_name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    let mut file = format!("{}.rbxmx", base);
+    let mut suffix = 2;
+
+    while !used_names.insert(file.to_lowercase()) {
+        file = format!("{} ({}).rbxmx", base, suffix);
+        suffix += 1;
+    }
+
+    file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exclude_classes_from_copy() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("Folder").with_name("Root"));
+        let root_id = dom.root_ref();
+        dom.insert(root_id, InstanceBuilder::new("Script").with_name("Main"));
+        let shared_id = dom.insert(
+            root_id,
+            InstanceBuilder::new("ModuleScript").with_name("Shared"),
+        );
+        dom.insert(
+            root_id,
+            InstanceBuilder::new("ObjectValue")
+                .with_name("Pointer")
+                .with_property("Value", shared_id),
+        );
+        let tree = RojoTree::from_dom(dom);
+
+        let mut copy = copy_tree(&tree);
+        exclude_classes(&mut copy, &["Script".to_owned()]);
+
+        let root = tree.get_instance(tree.get_root_id()).unwrap();
+        assert_eq!(root.children().len(), 3);
+
+        let copy_root = copy.get_instance(copy.get_root_id()).unwrap();
+        let names: Vec<_> = copy_root
+            .children()
+            .iter()
+            .map(|&id| copy.get_instance(id).unwrap().name())
+            .collect();
+        assert_eq!(names, ["Shared", "Pointer"]);
+
+        let copied_shared = copy_root.children()[0];
+        let pointer = copy.get_instance(copy_root.children()[1]).unwrap();
+        assert_eq!(
+            pointer.properties().get("Value"),
+            Some(&Variant::Ref(copied_shared))
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    io::{self, Write},
+    str::FromStr,
+};
+
+use anyhow::format_err;
+use lz4::block::CompressionMode;
+
+/// The size of the header at the start of every binary model or place file.
+const HEADER_LEN: usize = 32;
+
+/// The size of the header at the start of every chunk in a binary file.
+const CHUNK_HEADER_LEN: usize = 16;
+
+/// The chunk that ends a binary file, which must never be compressed.
+const END_CHUNK: &[u8] = b"END\0";
+
+/// How much to compress the chunks of a binary model or place file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Chunks are stored uncompressed.
+    None,
+
+    /// Chunks are compressed with LZ4's default settings. This is what
+    /// rbx_binary writes on its own.
+    Fast,
+
+    /// Chunks are compressed with LZ4's high compression mode at its highest
+    /// level, which is slower but gives smaller files.
+    Best,
+}
+
+impl FromStr for Compression {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match source {
+            "none" => Ok(Compression::None),
+            "fast" => Ok(Compression::Fast),
+            "best" => Ok(Compression::Best),
+            attempted => Err(format_err!(
+                "Invalid compression level '{}'. Valid levels are: none, fast, best",
+                attempted
+            )),
+        }
+    }
+}
+
+/// Rewrites a binary file produced by rbx_binary so that its chunks use the
+/// given compression.
+///
+/// rbx_binary doesn't let us pick how chunks are compressed, so each chunk is
+/// decompressed and compressed again instead.
+pub(super) fn recompress(contents: Vec<u8>, compression: Compression) -> io::Result<Vec<u8>> {
+    // The level passed to LZ4's high compression mode, if chunks should be
+    // compressed at all.
+    let level = match compression {
+        Compression::Fast => return Ok(contents),
+        Compression::None => None,
+        Compression::Best => Some(12),
+    };
+
+    if contents.len() < HEADER_LEN {
+        return Err(invalid_data("binary file is missing its header"));
+    }
+
+    let mut output = Vec::with_capacity(contents.len());
+    output.extend_from_slice(&contents[..HEADER_LEN]);
+
+    let mut rest = &contents[HEADER_LEN..];
+
+    while !rest.is_empty() {
+        if rest.len() < CHUNK_HEADER_LEN {
+            return Err(invalid_data("binary file has a truncated chunk header"));
+        }
+
+        let name = &rest[0..4];
+        let compressed_len = read_u32(&rest[4..8]);
+        let len = read_u32(&rest[8..12]);
+        let stored_len = if compressed_len == 0 {
+            len
+        } else {
+            compressed_len
+        };
+
+        let end = CHUNK_HEADER_LEN + stored_len as usize;
+        if rest.len() < end {
+            return Err(invalid_data("binary file has a truncated chunk"));
+        }
+
+        let stored = &rest[CHUNK_HEADER_LEN..end];
+        let data = if compressed_len == 0 {
+            stored.to_vec()
+        } else {
+            lz4::block::decompress(stored, Some(len as i32))?
+        };
+
+        output.write_all(name)?;
+
+        match level {
+            Some(level) if name != END_CHUNK => {
+                let mode = CompressionMode::HIGHCOMPRESSION(level);
+                let compressed = lz4::block::compress(&data, Some(mode), false)?;
+
+                output.write_all(&(compressed.len() as u32).to_le_bytes())?;
+                output.write_all(&len.to_le_bytes())?;
+                output.write_all(&0u32.to_le_bytes())?;
+                output.write_all(&compressed)?;
+            }
+            _ => {
+                output.write_all(&0u32.to_le_bytes())?;
+                output.write_all(&len.to_le_bytes())?;
+                output.write_all(&0u32.to_le_bytes())?;
+                output.write_all(&data)?;
+            }
+        }
+
+        rest = &rest[end..];
+    }
+
+    Ok(output)
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes.try_into().unwrap())
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::{InstanceBuilder, WeakDom};
+
+    fn encoded() -> Vec<u8> {
+        let mut dom = WeakDom::new(InstanceBuilder::new("Folder").with_name("Root"));
+
+        for i in 0..50 {
+            let child = InstanceBuilder::new("StringValue")
+                .with_name(format!("Value{}", i))
+                .with_property("Value", "the same text, over and over again ".repeat(20));
+
+            dom.insert(dom.root_ref(), child);
+        }
+
+        let mut contents = Vec::new();
+        rbx_binary::to_writer(&mut contents, &dom, &[dom.root_ref()]).unwrap();
+        contents
+    }
+
+    fn child_count(contents: &[u8]) -> usize {
+        let dom = rbx_binary::from_reader(contents).unwrap();
+        let root = dom.get_by_ref(dom.root().children()[0]).unwrap();
+        root.children().len()
+    }
+
+    #[test]
+    fn levels_decode_to_the_same_tree() {
+        let fast = encoded();
+        let none = recompress(fast.clone(), Compression::None).unwrap();
+        let best = recompress(fast.clone(), Compression::Best).unwrap();
+
+        assert!(none.len() > fast.len());
+        assert!(best.len() <= fast.len());
+
+        for contents in [&fast, &none, &best] {
+            assert_eq!(child_count(contents), 50);
+        }
+    }
+
+    #[test]
+    fn truncated_files_are_rejected() {
+        let mut contents = encoded();
+        contents.truncate(contents.len() - 4);
+
+        assert!(recompress(contents, Compression::None).is_err());
+    }
+}
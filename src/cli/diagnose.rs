@@ -0,0 +1,223 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use clap::Parser;
+use memofs::{IoResultExt, Vfs};
+use serde::Serialize;
+
+use crate::{
+    project::{Project, ProjectNode},
+    snapshot::{InstanceContext, InstanceSnapshot},
+    snapshot_middleware::snapshot_from_vfs,
+};
+
+use super::resolve_path;
+
+/// Summarize the state of a Rojo project to help track down problems.
+///
+/// This lists how many instances of each class the project turns into, any
+/// files that couldn't be read, and which paths Rojo watches for changes.
+/// Nothing is written to disk.
+#[derive(Debug, Parser)]
+pub struct DiagnoseCommand {
+    /// Path to the project to diagnose. Defaults to the current directory.
+    #[clap(default_value = "")]
+    pub project: PathBuf,
+
+    /// Print the report as JSON instead of text.
+    #[clap(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DiagnoseReport {
+    project_path: PathBuf,
+    instance_count: usize,
+    class_counts: BTreeMap<String, usize>,
+    unreadable_files: Vec<UnreadableFile>,
+    snapshot_error: Option<String>,
+    watched_paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+struct UnreadableFile {
+    path: PathBuf,
+    error: String,
+}
+
+impl UnreadableFile {
+    fn new(path: &Path, err: io::Error) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            error: err.to_string(),
+        }
+    }
+}
+
+impl DiagnoseCommand {
+    pub fn run(self) -> anyhow::Result<()> {
+        let base_path = resolve_path(&self.project);
+        let project_path = if Project::is_project_file(&base_path) {
+            base_path.to_path_buf()
+        } else {
+            base_path.join("default.project.json")
+        };
+
+        let contents = fs_err::read(&project_path)
+            .context("A project file is required to run 'rojo diagnose'")?;
+        let mut project = Project::load_from_slice(&contents, &project_path)?;
+
+        let vfs = Vfs::new_default();
+        vfs.set_watch_enabled(true);
+
+        let mut unreadable_files = Vec::new();
+
+        // Included projects can point to paths of their own, but a missing
+        // include will also be reported as a snapshot error below.
+        if let Err(err) = project.resolve_includes(&vfs) {
+            log::debug!("Could not resolve includes: {}", err);
+        }
+
+        let mut paths = Vec::new();
+        collect_paths(&project.tree, project.folder_location(), &mut paths);
+        for path in paths {
+            check_readable(&vfs, &path, &mut unreadable_files);
+        }
+
+        let context = InstanceContext::with_emit_legacy_scripts(project.emit_legacy_scripts);
+        let mut class_counts = BTreeMap::new();
+        let snapshot_error = match snapshot_from_vfs(&context, &vfs, &project_path) {
+            Ok(snapshot) => {
+                if let Some(snapshot) = snapshot {
+                    count_classes(&snapshot, &mut class_counts);
+                }
+                None
+            }
+            Err(err) => Some(format!("{:?}", err)),
+        };
+
+        let report = DiagnoseReport {
+            project_path,
+            instance_count: class_counts.values().sum(),
+            class_counts,
+            unreadable_files,
+            snapshot_error,
+            watched_paths: vfs.watched_paths(),
+        };
+
+        let stdout = io::stdout();
+        let mut output = stdout.lock();
+
+        if self.json {
+            serde_json::to_writer_pretty(&mut output, &report)?;
+            writeln!(output)?;
+        } else {
+            write_report(&mut output, &report)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Collects the path of every node in the project that has one.
+fn collect_paths(node: &ProjectNode, base_path: &Path, paths: &mut Vec<PathBuf>) {
+    if let Some(path_node) = &node.path {
+        paths.push(base_path.join(path_node.path()));
+    }
+
+    for child in node.children.values() {
+        collect_paths(child, base_path, paths);
+    }
+}
+
+/// Tries to read everything at the given path, recording anything that fails
+/// instead of stopping.
+fn check_readable(vfs: &Vfs, path: &Path, unreadable_files: &mut Vec<UnreadableFile>) {
+    let metadata = match vfs.metadata(path).with_not_found() {
+        Ok(Some(metadata)) => metadata,
+        // Optional paths are allowed to be missing, and required ones will
+        // cause a snapshot error that's more useful than one from here.
+        Ok(None) => return,
+        Err(err) => {
+            unreadable_files.push(UnreadableFile::new(path, err));
+            return;
+        }
+    };
+
+    if metadata.is_file() {
+        if let Err(err) = vfs.read(path) {
+            unreadable_files.push(UnreadableFile::new(path, err));
+        }
+        return;
+    }
+
+    let entries = match vfs.read_dir(path) {
+        Ok(entries) => entries,
+        Err(err) => {
+            unreadable_files.push(UnreadableFile::new(path, err));
+            return;
+        }
+    };
+
+    for entry in entries {
+        match entry {
+            Ok(entry) => check_readable(vfs, entry.path(), unreadable_files),
+            Err(err) => unreadable_files.push(UnreadableFile::new(path, err)),
+        }
+    }
+}
+
+fn count_classes(snapshot: &InstanceSnapshot, class_counts: &mut BTreeMap<String, usize>) {
+    *class_counts
+        .entry(snapshot.class_name.to_string())
+        .or_default() += 1;
+
+    for child in &snapshot.children {
+        count_classes(child, class_counts);
+    }
+}
+
+fn write_report(output: &mut impl Write, report: &DiagnoseReport) -> io::Result<()> {
+    writeln!(output, "Project: {}", report.project_path.display())?;
+    writeln!(output)?;
+
+    writeln!(output, "Instances: {}", report.instance_count)?;
+    for (class_name, count) in &report.class_counts {
+        writeln!(output, "  {}: {}", class_name, count)?;
+    }
+    writeln!(output)?;
+
+    if let Some(err) = &report.snapshot_error {
+        writeln!(output, "Snapshot failed:")?;
+        for line in err.lines() {
+            writeln!(output, "  {}", line)?;
+        }
+        writeln!(output)?;
+    }
+
+    if report.unreadable_files.is_empty() {
+        writeln!(output, "Unreadable files: none")?;
+    } else {
+        writeln!(
+            output,
+            "Unreadable files: {}",
+            report.unreadable_files.len()
+        )?;
+        for file in &report.unreadable_files {
+            writeln!(output, "  {}: {}", file.path.display(), file.error)?;
+        }
+    }
+    writeln!(output)?;
+
+    writeln!(output, "Watched paths: {}", report.watched_paths.len())?;
+    for path in &report.watched_paths {
+        writeln!(output, "  {}", path.display())?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use memofs::{Vfs, VfsEvent};
+use serde::Serialize;
+
+use super::resolve_path;
+
+/// Print the changes Rojo sees to a project's files as they happen.
+///
+/// Each change is printed as a JSON object on its own line, with a `kind` of
+/// create, write, remove, or rename and the paths involved. Paths inside the
+/// project's folder are relative to it. Rojo watches the same files that it
+/// reads to build the project.
+#[derive(Debug, Parser)]
+pub struct EventsCommand {
+    /// Path to the project to watch. Defaults to the current directory.
+    #[clap(default_value = "")]
+    pub project: PathBuf,
+}
+
+impl EventsCommand {
+    pub fn run(self) -> anyhow::Result<()> {
+        let project_path = resolve_path(&self.project);
+
+        let vfs = Vfs::new_default();
+
+        // Reading the project is what tells the filesystem which paths to
+        // watch.
+        crate::build_tree(&vfs, &project_path)?;
+
+        let base_path = if vfs.metadata(&project_path)?.is_file() {
+            project_path.parent().unwrap_or(&project_path)
+        } else {
+            &project_path
+        };
+
+        let stdout = io::stdout();
+        write_events(
+            &vfs,
+            base_path,
+            vfs.event_receiver().iter(),
+            &mut stdout.lock(),
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+enum Event {
+    Create { path: PathBuf },
+    Write { path: PathBuf },
+    Remove { path: PathBuf },
+    Rename { from: PathBuf, to: PathBuf },
+}
+
+impl Event {
+    fn new(event: &VfsEvent, base_path: &Path) -> Option<Self> {
+        let relative = |path: &Path| path.strip_prefix(base_path).unwrap_or(path).to_path_buf();
+
+        let event = match event {
+            VfsEvent::Create(path) => Event::Create {
+                path: relative(path),
+            },
+            VfsEvent::Write(path) => Event::Write {
+                path: relative(path),
+            },
+            VfsEvent::Remove(path) => Event::Remove {
+                path: relative(path),
+            },
+            VfsEvent::Rename { from, to } => Event::Rename {
+                from: relative(from),
+                to: relative(to),
+            },
+            _ => {
+                log::warn!("Unhandled VFS event: {:?}", event);
+                return None;
+            }
+        };
+
+        Some(event)
+    }
+}
+
+/// Writes a line of JSON for each event, flushing after every line so that
+/// whatever is reading the output sees events right away.
+fn write_events(
+    vfs: &Vfs,
+    base_path: &Path,
+    events: impl IntoIterator<Item = VfsEvent>,
+    output: &mut impl Write,
+) -> anyhow::Result<()> {
+    for event in events {
+        vfs.commit_event(&event)?;
+
+        // New directories aren't watched until they're read.
+        if let VfsEvent::Create(path) | VfsEvent::Rename { to: path, .. } = &event {
+            watch_new_directory(vfs, path);
+        }
+
+        if let Some(event) = Event::new(&event, base_path) {
+            serde_json::to_writer(&mut *output, &event)?;
+            writeln!(output)?;
+            output.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn watch_new_directory(vfs: &Vfs, path: &Path) {
+    let entries = match vfs.read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        watch_new_directory(vfs, entry.path());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use memofs::{InMemoryFs, VfsSnapshot};
+
+    #[test]
+    fn lines() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/project",
+            VfsSnapshot::dir([(
+                "src",
+                VfsSnapshot::dir([("a.lua", VfsSnapshot::file("return 1"))]),
+            )]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        imfs.raise_event(VfsEvent::Write(PathBuf::from("/project/src/a.lua")));
+        imfs.raise_event(VfsEvent::Rename {
+            from: PathBuf::from("/project/src/a.lua"),
+            to: PathBuf::from("/project/src/b.lua"),
+        });
+        imfs.raise_event(VfsEvent::Remove(PathBuf::from("/elsewhere/c.lua")));
+
+        let mut output = Vec::new();
+        write_events(
+            &vfs,
+            Path::new("/project"),
+            vfs.event_receiver().try_iter(),
+            &mut output,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            concat!(
+                "{\"kind\":\"write\",\"path\":\"src/a.lua\"}\n",
+                "{\"kind\":\"rename\",\"from\":\"src/a.lua\",\"to\":\"src/b.lua\"}\n",
+                "{\"kind\":\"remove\",\"path\":\"/elsewhere/c.lua\"}\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use std::{error::Error as StdError, io};
+
+use thiserror::Error;
+
+use crate::{serve_session::ServeSessionError, snapshot_middleware::MiddlewareError, ProjectError};
+
+use super::build::FilesHadErrors;
+
+/// The exit codes Rojo uses when a command fails, so that scripts can tell
+/// what kind of problem stopped it.
+///
+/// Exit code 2 is left out, since it's what Rojo exits with when it's given
+/// invalid arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// Any failure that doesn't fit one of the other kinds.
+    Failure = 1,
+
+    /// The project, or a file that it points to, couldn't be loaded.
+    ProjectLoad = 3,
+
+    /// Reading or writing a file failed.
+    Io = 4,
+
+    /// The result couldn't be serialized.
+    Serialization = 5,
+
+    /// Rojo was asked to do something it doesn't support, like building to a
+    /// file with an unknown extension.
+    Unsupported = 6,
+}
+
+impl ExitCode {
+    /// Picks the exit code for an error returned by a command. Errors are
+    /// classified by the outermost cause that has a known kind.
+    pub fn for_error(err: &anyhow::Error) -> ExitCode {
+        err.chain().find_map(classify).unwrap_or(ExitCode::Failure)
+    }
+}
+
+/// An error for something Rojo doesn't support.
+#[derive(Debug, Error)]
+#[error("{0}")]
+pub struct Unsupported(pub &'static str);
+
+fn classify(err: &(dyn StdError + 'static)) -> Option<ExitCode> {
+    if let Some(err) = err.downcast_ref::<ServeSessionError>() {
+        return match err {
+            ServeSessionError::Io { .. } => Some(ExitCode::Io),
+
+            // Errors from snapshotting the project end up here. Since they're
+            // transparent, their outermost cause isn't part of the chain.
+            ServeSessionError::Other { source } => Some(
+                source
+                    .chain()
+                    .find_map(classify)
+                    .unwrap_or(ExitCode::ProjectLoad),
+            ),
+
+            _ => Some(ExitCode::ProjectLoad),
+        };
+    }
+
+    if err.is::<ProjectError>() || err.is::<MiddlewareError>() || err.is::<FilesHadErrors>() {
+        Some(ExitCode::ProjectLoad)
+    } else if err.is::<Unsupported>() {
+        Some(ExitCode::Unsupported)
+    } else if err.is::<rbx_binary::EncodeError>()
+        || err.is::<rbx_xml::EncodeError>()
+        || err.is::<serde_json::Error>()
+    {
+        Some(ExitCode::Serialization)
+    } else if err.is::<io::Error>() {
+        Some(ExitCode::Io)
+    } else {
+        None
+    }
+}
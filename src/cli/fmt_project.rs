@@ -1,18 +1,39 @@
-use std::path::PathBuf;
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use clap::Parser;
+use serde::Serialize;
+use serde_json::{
+    ser::{CharEscape, Formatter, PrettyFormatter},
+    Value,
+};
 
-use crate::project::Project;
+use crate::{json5, project::Project};
 
 use super::resolve_path;
 
 /// Reformat a Rojo project using the standard JSON formatting rules.
+///
+/// Keys in each node are written in a fixed order, starting with `$className`,
+/// `$path`, and `$properties`, followed by children sorted by name. Projects
+/// that use JSON5 syntax, like comments, are rewritten as plain JSON.
 #[derive(Debug, Parser)]
 pub struct FmtProjectCommand {
     /// Path to the project to format. Defaults to the current directory.
     #[clap(default_value = "")]
     pub project: PathBuf,
+
+    /// Instead of writing the formatted project, exit with an error if it
+    /// isn't already formatted.
+    #[clap(long)]
+    pub check: bool,
+
+    /// Write the formatted project to stdout instead of back to the file.
+    #[clap(long, conflicts_with = "check")]
+    pub stdout: bool,
 }
 
 impl FmtProjectCommand {
@@ -21,12 +42,240 @@ impl FmtProjectCommand {
         let project = Project::load_fuzzy(&base_path)?
             .context("A project file is required to run 'rojo fmt-project'")?;
 
-        let serialized = serde_json::to_string_pretty(&project)
-            .context("could not re-encode project file as JSON")?;
+        // Formatting would drop unknown keys from the project, so they need to
+        // be fixed first.
+        if let Some(error) = project.unknown_key_errors().next() {
+            return Err(error.into());
+        }
+
+        let current = fs_err::read_to_string(&project.file_location)?;
+        let formatted = format_project(&project, &current)?;
+
+        if self.stdout {
+            std::io::stdout().write_all(formatted.as_bytes())?;
+            return Ok(());
+        }
 
-        fs_err::write(&project.file_location, serialized)
+        if current == formatted {
+            return Ok(());
+        }
+
+        if self.check {
+            bail!(
+                "Project {} is not formatted. Run 'rojo fmt-project' to format it.",
+                project.file_location.display()
+            );
+        }
+
+        fs_err::write(&project.file_location, formatted)
             .context("could not write back to project file")?;
 
         Ok(())
     }
 }
+
+/// Formats a project, keeping integer literals from `source`, the project's
+/// current contents, where they were decoded into floats.
+fn format_project(project: &Project, source: &str) -> anyhow::Result<String> {
+    let original = match serde_json::from_str(source) {
+        Ok(value) => value,
+        Err(_) => json5::to_json(source)
+            .ok()
+            .and_then(|converted| serde_json::from_str(&converted.json).ok())
+            .unwrap_or(Value::Null),
+    };
+
+    let mut buffer = Vec::new();
+    let formatter = NumberPreservingFormatter {
+        inner: PrettyFormatter::new(),
+        original: &original,
+        path: Vec::new(),
+        in_key: false,
+    };
+    project
+        .serialize(&mut serde_json::Serializer::with_formatter(
+            &mut buffer,
+            formatter,
+        ))
+        .context("could not re-encode project file as JSON")?;
+
+    let mut formatted =
+        String::from_utf8(buffer).context("could not re-encode project file as JSON")?;
+    formatted.push('\n');
+
+    Ok(formatted)
+}
+
+/// One step from the root of a JSON document to the value being written.
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Pretty prints JSON, but writes floats as the integer they were written as
+/// in the original document, if there was one at the same position.
+///
+/// Values like `Vector3`s are always decoded as floats, so without this,
+/// formatting would turn `[1, 2, 3]` into `[1.0, 2.0, 3.0]`.
+struct NumberPreservingFormatter<'a> {
+    inner: PrettyFormatter<'a>,
+    original: &'a Value,
+    path: Vec<PathSegment>,
+    in_key: bool,
+}
+
+impl NumberPreservingFormatter<'_> {
+    /// Writes the original integer at the current position, returning whether
+    /// there was one that matched `value`.
+    fn write_original<W: ?Sized + io::Write>(
+        &self,
+        writer: &mut W,
+        value: f64,
+    ) -> io::Result<bool> {
+        let mut current = self.original;
+
+        for segment in &self.path {
+            let next = match segment {
+                PathSegment::Key(key) => current.get(key.as_str()),
+                PathSegment::Index(index) => current.get(index),
+            };
+
+            match next {
+                Some(next) => current = next,
+                None => return Ok(false),
+            }
+        }
+
+        match current {
+            Value::Number(number)
+                if (number.is_i64() || number.is_u64()) && number.as_f64() == Some(value) =>
+            {
+                writer.write_all(number.to_string().as_bytes())?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Returns the key being written, if the formatter is inside one.
+    fn current_key(&mut self) -> Option<&mut String> {
+        if !self.in_key {
+            return None;
+        }
+
+        match self.path.last_mut() {
+            Some(PathSegment::Key(key)) => Some(key),
+            _ => None,
+        }
+    }
+}
+
+impl Formatter for NumberPreservingFormatter<'_> {
+    fn write_f32<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: f32) -> io::Result<()> {
+        if self.write_original(writer, value.into())? {
+            return Ok(());
+        }
+
+        self.inner.write_f32(writer, value)
+    }
+
+    fn write_f64<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: f64) -> io::Result<()> {
+        if self.write_original(writer, value)? {
+            return Ok(());
+        }
+
+        self.inner.write_f64(writer, value)
+    }
+
+    fn write_string_fragment<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        fragment: &str,
+    ) -> io::Result<()> {
+        if let Some(key) = self.current_key() {
+            key.push_str(fragment);
+        }
+
+        self.inner.write_string_fragment(writer, fragment)
+    }
+
+    fn write_char_escape<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        char_escape: CharEscape,
+    ) -> io::Result<()> {
+        if let Some(key) = self.current_key() {
+            key.push(match char_escape {
+                CharEscape::Quote => '"',
+                CharEscape::ReverseSolidus => '\\',
+                CharEscape::Solidus => '/',
+                CharEscape::Backspace => '\u{8}',
+                CharEscape::FormFeed => '\u{c}',
+                CharEscape::LineFeed => '\n',
+                CharEscape::CarriageReturn => '\r',
+                CharEscape::Tab => '\t',
+                CharEscape::AsciiControl(byte) => byte.into(),
+            });
+        }
+
+        self.inner.write_char_escape(writer, char_escape)
+    }
+
+    fn begin_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.path.push(PathSegment::Index(0));
+        self.inner.begin_array(writer)
+    }
+
+    fn end_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.path.pop();
+        self.inner.end_array(writer)
+    }
+
+    fn begin_array_value<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.inner.begin_array_value(writer, first)
+    }
+
+    fn end_array_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        if let Some(PathSegment::Index(index)) = self.path.last_mut() {
+            *index += 1;
+        }
+
+        self.inner.end_array_value(writer)
+    }
+
+    fn begin_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.begin_object(writer)
+    }
+
+    fn end_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.end_object(writer)
+    }
+
+    fn begin_object_key<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.path.push(PathSegment::Key(String::new()));
+        self.in_key = true;
+        self.inner.begin_object_key(writer, first)
+    }
+
+    fn end_object_key<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.in_key = false;
+        self.inner.end_object_key(writer)
+    }
+
+    fn begin_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.begin_object_value(writer)
+    }
+
+    fn end_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.path.pop();
+        self.inner.end_object_value(writer)
+    }
+}
@@ -0,0 +1,123 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use clap::Parser;
+use serde::Serialize;
+
+use crate::{
+    project::Project,
+    snapshot::SyncRule,
+    snapshot_middleware::{default_sync_rules, Middleware},
+};
+
+use super::resolve_path;
+
+/// List the kinds of files Rojo knows how to turn into instances.
+///
+/// Rules are listed in the order Rojo checks them: the sync rules from the
+/// project, if there is one, followed by Rojo's built-in rules. The first rule
+/// that matches a file decides how it's turned into an instance.
+#[derive(Debug, Parser)]
+pub struct ListMiddlewareCommand {
+    /// Path to the project whose sync rules should be listed. Defaults to the
+    /// current directory. Only the built-in rules are listed if there's no
+    /// project.
+    #[clap(default_value = "")]
+    pub project: PathBuf,
+
+    /// Print the rules as JSON instead of text.
+    #[clap(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ListedRule<'a> {
+    source: RuleSource,
+    #[serde(flatten)]
+    rule: &'a SyncRule,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum RuleSource {
+    Project,
+    Default,
+}
+
+impl ListMiddlewareCommand {
+    pub fn run(self) -> anyhow::Result<()> {
+        let base_path = resolve_path(&self.project);
+        let project = Project::load_fuzzy(&base_path)?;
+
+        let project_rules = project.iter().flat_map(|project| &project.sync_rules);
+        let rules: Vec<_> = project_rules
+            .map(|rule| ListedRule {
+                source: RuleSource::Project,
+                rule,
+            })
+            .chain(default_sync_rules().iter().map(|rule| ListedRule {
+                source: RuleSource::Default,
+                rule,
+            }))
+            .collect();
+
+        let stdout = io::stdout();
+        let mut output = stdout.lock();
+
+        if self.json {
+            serde_json::to_writer_pretty(&mut output, &rules)?;
+            writeln!(output)?;
+        } else {
+            if let Some(project) = &project {
+                writeln!(output, "Project: {}", project.file_location.display())?;
+                writeln!(output)?;
+            }
+
+            write_rules(&mut output, &rules)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_rules(output: &mut impl Write, rules: &[ListedRule]) -> anyhow::Result<()> {
+    let width = rules
+        .iter()
+        .map(|listed| listed.rule.include.as_str().len())
+        .max()
+        .unwrap_or(0);
+
+    for listed in rules {
+        let source = match listed.source {
+            RuleSource::Project => "project",
+            RuleSource::Default => "default",
+        };
+
+        write!(
+            output,
+            "{:<7}  {:<width$}  {}",
+            source,
+            listed.rule.include.as_str(),
+            middleware_name(listed.rule.middleware)?,
+            width = width
+        )?;
+
+        if let Some(exclude) = &listed.rule.exclude {
+            write!(output, " (excluding {})", exclude.as_str())?;
+        }
+
+        writeln!(output)?;
+    }
+
+    Ok(())
+}
+
+/// The name of a middleware as it's written in a project's sync rules.
+fn middleware_name(middleware: Middleware) -> anyhow::Result<String> {
+    let value = serde_json::to_value(middleware)?;
+
+    Ok(value.as_str().unwrap_or_default().to_owned())
+}
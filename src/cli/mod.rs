@@ -1,27 +1,42 @@
 //! Defines Rojo's CLI through clap types.
 
 mod build;
+mod compression;
+mod diagnose;
 mod doc;
+mod events;
+mod exit_code;
 mod fmt_project;
 mod init;
+mod list_middleware;
 mod plugin;
+mod progress;
 mod serve;
 mod sourcemap;
+mod unpack_model;
 mod upload;
+mod xml_format;
 
 use std::{borrow::Cow, env, path::Path, str::FromStr};
 
 use clap::Parser;
 use thiserror::Error;
 
-pub use self::build::BuildCommand;
+pub use self::build::{BuildCommand, BuildKind, HashAlgorithm};
+pub use self::compression::Compression;
+pub use self::diagnose::DiagnoseCommand;
 pub use self::doc::DocCommand;
+pub use self::events::EventsCommand;
+pub use self::exit_code::ExitCode;
 pub use self::fmt_project::FmtProjectCommand;
 pub use self::init::{InitCommand, InitKind};
+pub use self::list_middleware::ListMiddlewareCommand;
 pub use self::plugin::{PluginCommand, PluginSubcommand};
 pub use self::serve::ServeCommand;
 pub use self::sourcemap::SourcemapCommand;
+pub use self::unpack_model::UnpackModelCommand;
 pub use self::upload::UploadCommand;
+pub use self::xml_format::XmlFormat;
 
 /// Command line options that Rojo accepts, defined using the clap crate.
 #[derive(Debug, Parser)]
@@ -44,6 +59,10 @@ impl Options {
             Subcommand::Upload(subcommand) => subcommand.run(),
             Subcommand::Sourcemap(subcommand) => subcommand.run(),
             Subcommand::FmtProject(subcommand) => subcommand.run(),
+            Subcommand::Diagnose(subcommand) => subcommand.run(),
+            Subcommand::ListMiddleware(subcommand) => subcommand.run(),
+            Subcommand::Events(subcommand) => subcommand.run(),
+            Subcommand::UnpackModel(subcommand) => subcommand.run(),
             Subcommand::Doc(subcommand) => subcommand.run(),
             Subcommand::Plugin(subcommand) => subcommand.run(),
         }
@@ -117,6 +136,10 @@ pub enum Subcommand {
     Upload(UploadCommand),
     Sourcemap(SourcemapCommand),
     FmtProject(FmtProjectCommand),
+    Diagnose(DiagnoseCommand),
+    ListMiddleware(ListMiddlewareCommand),
+    Events(EventsCommand),
+    UnpackModel(UnpackModelCommand),
     Doc(DocCommand),
     Plugin(PluginCommand),
 }
@@ -0,0 +1,101 @@
+use std::{
+    io::{self, IsTerminal, Write},
+    time::Duration,
+};
+
+use crossbeam_channel::{RecvTimeoutError, Sender};
+use jod_thread::JoinHandle;
+
+use crate::snapshot::SnapshotProgress;
+
+/// How often the progress line is redrawn.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Draws how many files have been snapshotted on a single line of stderr,
+/// updating it in place until the reporter is dropped.
+pub(super) struct ProgressReporter {
+    shutdown_sender: Sender<()>,
+
+    // Joined when the reporter is dropped, after it's been told to stop.
+    _thread: JoinHandle<()>,
+}
+
+impl ProgressReporter {
+    /// Starts reporting progress, unless stderr isn't a terminal. Redirected
+    /// output shouldn't fill up with progress lines.
+    pub fn start(progress: SnapshotProgress) -> Option<Self> {
+        if !io::stderr().is_terminal() {
+            return None;
+        }
+
+        let (shutdown_sender, shutdown_receiver) = crossbeam_channel::bounded(1);
+
+        let thread = jod_thread::Builder::new()
+            .name("Progress thread".to_owned())
+            .spawn(move || {
+                let stderr = io::stderr();
+
+                loop {
+                    let _ = write!(
+                        stderr.lock(),
+                        "\r\x1b[KSnapshotting project: {} files ({})",
+                        progress.files(),
+                        format_bytes(progress.bytes())
+                    );
+
+                    match shutdown_receiver.recv_timeout(REDRAW_INTERVAL) {
+                        Err(RecvTimeoutError::Timeout) => {}
+                        _ => break,
+                    }
+                }
+
+                // Clear the line so that whatever is printed next starts from
+                // an empty line.
+                let _ = write!(stderr.lock(), "\r\x1b[K");
+            })
+            .expect("Could not start progress thread");
+
+        Some(Self {
+            shutdown_sender,
+            _thread: thread,
+        })
+    }
+}
+
+impl Drop for ProgressReporter {
+    fn drop(&mut self) {
+        let _ = self.shutdown_sender.send(());
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bytes() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024 * 1024), "3072.0 GiB");
+    }
+}
@@ -2,6 +2,8 @@ use std::{
     io::{BufWriter, Write},
     mem::forget,
     path::{Path, PathBuf},
+    thread,
+    time::Duration,
 };
 
 use clap::Parser;
@@ -21,6 +23,11 @@ use super::resolve_path;
 
 const PATH_STRIP_FAILED_ERR: &str = "Failed to create relative paths for project file!";
 
+/// How long to wait for more changes before rewriting the sourcemap in watch
+/// mode. Editors and tools like git often touch several files at once, and
+/// each of those would otherwise cause its own rewrite.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(50);
+
 /// Representation of a node in the generated sourcemap tree.
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -51,6 +58,10 @@ pub struct SourcemapCommand {
     pub output: Option<PathBuf>,
 
     /// If non-script files should be included or not. Defaults to false.
+    ///
+    /// When this is off, instances that aren't scripts are only included if
+    /// they contain a script somewhere below them, so folders and models
+    /// without any scripts are left out entirely.
     #[clap(long)]
     pub include_non_scripts: bool,
 
@@ -63,6 +74,15 @@ impl SourcemapCommand {
     pub fn run(self) -> anyhow::Result<()> {
         let project_path = resolve_path(&self.project);
 
+        // Pre-build a rayon threadpool with a low number of threads to avoid
+        // dynamic creation overhead on systems with a high number of cpus.
+        // This has to happen before anything uses the global pool, including
+        // snapshotting the project.
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(num_cpus::get().min(6))
+            .build_global()
+            .unwrap();
+
         log::trace!("Constructing in-memory filesystem");
         let vfs = Vfs::new_default();
         vfs.set_watch_enabled(self.watch);
@@ -76,13 +96,6 @@ impl SourcemapCommand {
             filter_non_scripts
         };
 
-        // Pre-build a rayon threadpool with a low number of threads to avoid
-        // dynamic creation overhead on systems with a high number of cpus.
-        rayon::ThreadPoolBuilder::new()
-            .num_threads(num_cpus::get().min(6))
-            .build_global()
-            .unwrap();
-
         write_sourcemap(&session, self.output.as_deref(), filter)?;
 
         if self.watch {
@@ -90,9 +103,23 @@ impl SourcemapCommand {
 
             loop {
                 let receiver = session.message_queue().subscribe(cursor);
-                let (new_cursor, patch_set) = rt.block_on(receiver).unwrap();
+                let (new_cursor, mut patch_set) = rt.block_on(receiver).unwrap();
                 cursor = new_cursor;
 
+                // Keep collecting changes until none have arrived for a while.
+                loop {
+                    thread::sleep(WATCH_DEBOUNCE);
+
+                    if session.message_queue().cursor() == cursor {
+                        break;
+                    }
+
+                    let receiver = session.message_queue().subscribe(cursor);
+                    let (new_cursor, more_patches) = rt.block_on(receiver).unwrap();
+                    cursor = new_cursor;
+                    patch_set.extend(more_patches);
+                }
+
                 if patch_set_affects_sourcemap(&session, &patch_set, filter) {
                     write_sourcemap(&session, self.output.as_deref(), filter)?;
                 }
@@ -130,24 +157,22 @@ fn patch_set_affects_sourcemap(
         // 1. An instance was removed, in which case it will no
         // longer exist in the tree and we cant check the filter
         !set.removed.is_empty()
-            // 2. A newly added instance passes the filter
+            // 2. A newly added instance passes the filter, or has already
+            // been removed by a later patch
             || set.added.iter().any(|referent| {
-                let instance = tree
-                    .get_instance(*referent)
-                    .expect("instance did not exist when updating sourcemap");
-                filter(&instance)
+                tree.get_instance(*referent)
+                    .map_or(true, |instance| filter(&instance))
             })
             // 3. An existing instance has its class name, name,
-            // or file paths changed, and passes the filter
+            // or file paths changed, and passes the filter or has since
+            // been removed
             || set.updated.iter().any(|updated| {
                 let changed = updated.changed_class_name.is_some()
                     || updated.changed_name.is_some()
                     || updated.changed_metadata.is_some();
                 if changed {
-                    let instance = tree
-                        .get_instance(updated.id)
-                        .expect("instance did not exist when updating sourcemap");
-                    filter(&instance)
+                    tree.get_instance(updated.id)
+                        .map_or(true, |instance| filter(&instance))
                 } else {
                     false
                 }
@@ -0,0 +1,536 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    Instance, WeakDom,
+};
+use serde::Serialize;
+
+use crate::resolution::{RefPath, UnresolvedValue};
+
+use super::resolve_path;
+
+/// The directory inside the output that holds the model's top-level instance.
+const SOURCE_DIR: &str = "src";
+
+/// Split a model file into the files and directories Rojo would turn back into
+/// the same model.
+///
+/// Scripts become Lua files, folders and instances with children become
+/// directories, and other instances become JSON models. Any other properties
+/// are written to meta files. A project file is written next to the model's
+/// files, so the output can be built or served right away. Children are read
+/// back in the order of their file names, which may not be the order they were
+/// in before.
+#[derive(Debug, Parser)]
+pub struct UnpackModelCommand {
+    /// Path to the model to unpack, either an .rbxm or an .rbxmx file.
+    pub model: PathBuf,
+
+    /// Directory to write the unpacked model to. It must be empty or not exist
+    /// yet.
+    #[clap(long, short)]
+    pub output: PathBuf,
+}
+
+impl UnpackModelCommand {
+    pub fn run(self) -> anyhow::Result<()> {
+        let model_path = resolve_path(&self.model);
+        let output_path = resolve_path(&self.output);
+
+        let dom = read_model(&model_path)?;
+
+        let top_level = dom.root().children();
+        if top_level.len() != 1 {
+            bail!(
+                "Only models with one top-level instance can be unpacked, but {} has {}",
+                model_path.display(),
+                top_level.len()
+            );
+        }
+
+        if output_path.exists() && fs_err::read_dir(output_path.as_ref())?.next().is_some() {
+            bail!(
+                "Could not unpack the model because {} is not empty",
+                output_path.display()
+            );
+        }
+
+        unpack(&dom, top_level[0], &output_path)?;
+
+        println!(
+            "Unpacked {} to {}",
+            model_path.display(),
+            output_path.display()
+        );
+
+        Ok(())
+    }
+}
+
+fn read_model(path: &Path) -> anyhow::Result<WeakDom> {
+    let contents = fs_err::read(path)?;
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("rbxmx") => {
+            let options = rbx_xml::DecodeOptions::new()
+                .property_behavior(rbx_xml::DecodePropertyBehavior::ReadUnknown);
+
+            rbx_xml::from_reader(contents.as_slice(), options).context("Malformed rbxmx file")
+        }
+        Some("rbxm") => rbx_binary::from_reader(contents.as_slice()).context("Malformed rbxm file"),
+        _ => bail!(
+            "Could not unpack {}. Only .rbxm and .rbxmx files can be unpacked",
+            path.display()
+        ),
+    }
+}
+
+/// Writes the instance and its descendants to `output`, along with a project
+/// that builds them.
+fn unpack(dom: &WeakDom, root_id: Ref, output: &Path) -> anyhow::Result<()> {
+    let root = dom.get_by_ref(root_id).unwrap();
+
+    let unpacker = Unpacker {
+        dom,
+        ref_paths: ref_paths(dom, root_id),
+    };
+
+    if !unpacker.can_write_children(root) {
+        bail!(
+            "Could not unpack the model because some of the children of {} \
+             have names that can't be used as file names, or share a name",
+            root.name
+        );
+    }
+
+    unpacker.write_dir(root, &output.join(SOURCE_DIR))?;
+
+    // Scripts are written the same way whichever way the project defaults
+    // scripts to be read.
+    let project = serde_json::json!({
+        "name": root.name,
+        "emitLegacyScripts": true,
+        "tree": {
+            "$path": SOURCE_DIR,
+        },
+    });
+
+    let mut contents = serde_json::to_string_pretty(&project)?;
+    contents.push('\n');
+    fs_err::write(output.join("default.project.json"), contents)?;
+
+    Ok(())
+}
+
+/// The contents of a meta file or JSON model.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InstanceJson<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    class_name: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    properties: BTreeMap<&'a str, UnresolvedValue>,
+}
+
+impl InstanceJson<'_> {
+    fn is_empty(&self) -> bool {
+        self.class_name.is_none() && self.properties.is_empty()
+    }
+
+    fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let mut contents = serde_json::to_string_pretty(self)?;
+        contents.push('\n');
+        fs_err::write(path, contents)?;
+
+        Ok(())
+    }
+}
+
+struct Unpacker<'a> {
+    dom: &'a WeakDom,
+
+    /// The path of every instance that Refs can point to by path once the
+    /// model is built again.
+    ref_paths: HashMap<Ref, String>,
+}
+
+impl Unpacker<'_> {
+    fn write_instance(&self, instance: &Instance, parent_dir: &Path) -> anyhow::Result<()> {
+        let name = instance.name.as_str();
+
+        if !self.can_write_children(instance) {
+            log::warn!(
+                "Some of the children of {} can't be written as files, so it \
+                 will be written as a model file instead",
+                name
+            );
+
+            let mut contents = Vec::new();
+            rbx_xml::to_writer(
+                &mut contents,
+                self.dom,
+                &[instance.referent()],
+                rbx_xml::EncodeOptions::new()
+                    .property_behavior(rbx_xml::EncodePropertyBehavior::WriteUnknown),
+            )?;
+            fs_err::write(parent_dir.join(format!("{}.rbxmx", name)), contents)?;
+
+            return Ok(());
+        }
+
+        let is_leaf = instance.children().is_empty();
+
+        if let Some(suffix) = script_suffix(&instance.class) {
+            if is_leaf {
+                let meta = self.meta(instance);
+                write_source(instance, &parent_dir.join(format!("{}{}", name, suffix)))?;
+
+                if !meta.is_empty() {
+                    meta.write(&parent_dir.join(format!("{}.meta.json", name)))?;
+                }
+
+                Ok(())
+            } else {
+                self.write_dir(instance, &parent_dir.join(name))
+            }
+        } else if is_leaf && instance.class != "Folder" && !self.has_refs(instance) {
+            let model = InstanceJson {
+                class_name: Some(&instance.class),
+                properties: self.properties(instance),
+            };
+
+            model.write(&parent_dir.join(format!("{}.model.json", name)))
+        } else {
+            self.write_dir(instance, &parent_dir.join(name))
+        }
+    }
+
+    /// Writes the instance as a directory. Scripts are written to an init
+    /// script inside of it.
+    fn write_dir(&self, instance: &Instance, path: &Path) -> anyhow::Result<()> {
+        fs_err::create_dir_all(path)?;
+
+        if let Some(suffix) = script_suffix(&instance.class) {
+            write_source(instance, &path.join(format!("init{}", suffix)))?;
+        }
+
+        let meta = self.meta(instance);
+        if !meta.is_empty() {
+            meta.write(&path.join("init.meta.json"))?;
+        }
+
+        for &child_id in instance.children() {
+            let child = self.dom.get_by_ref(child_id).unwrap();
+            self.write_instance(child, path)?;
+        }
+
+        Ok(())
+    }
+
+    fn meta<'a>(&self, instance: &'a Instance) -> InstanceJson<'a> {
+        // Directories are Folders and scripts get their class from their file
+        // name, so only other classes need to be written down.
+        let class_name = match instance.class.as_str() {
+            "Folder" => None,
+            class_name if script_suffix(class_name).is_some() => None,
+            class_name => Some(class_name),
+        };
+
+        InstanceJson {
+            class_name,
+            properties: self.properties(instance),
+        }
+    }
+
+    fn properties<'a>(&self, instance: &'a Instance) -> BTreeMap<&'a str, UnresolvedValue> {
+        let is_script = script_suffix(&instance.class).is_some();
+        let mut properties = BTreeMap::new();
+
+        for (key, value) in &instance.properties {
+            if is_script && key == "Source" {
+                continue;
+            }
+
+            let value = match value {
+                Variant::Ref(target) if target.is_none() => continue,
+                Variant::Ref(target) => match self.ref_paths.get(target) {
+                    Some(path) => UnresolvedValue::RefPath(RefPath { path: path.clone() }),
+                    None => {
+                        log::warn!(
+                            "Property {} of {} refers to an instance that can't be \
+                             found by its path, so it won't be unpacked",
+                            key,
+                            instance.name
+                        );
+                        continue;
+                    }
+                },
+                value => UnresolvedValue::FullyQualified(value.clone()),
+            };
+
+            properties.insert(key.as_str(), value);
+        }
+
+        properties
+    }
+
+    fn has_refs(&self, instance: &Instance) -> bool {
+        instance
+            .properties
+            .values()
+            .any(|value| matches!(value, Variant::Ref(target) if target.is_some()))
+    }
+
+    /// Whether every child of the instance can be written to its own file
+    /// that Rojo reads back with the same name.
+    fn can_write_children(&self, instance: &Instance) -> bool {
+        let mut names = HashSet::new();
+
+        instance.children().iter().all(|&child_id| {
+            let name = &self.dom.get_by_ref(child_id).unwrap().name;
+
+            is_valid_file_name(name) && names.insert(name.to_lowercase())
+        })
+    }
+}
+
+/// The file name suffix for scripts of the given class, or `None` if the class
+/// isn't a script.
+fn script_suffix(class_name: &str) -> Option<&'static str> {
+    match class_name {
+        "Script" => Some(".server.luau"),
+        "LocalScript" => Some(".client.luau"),
+        "ModuleScript" => Some(".luau"),
+        _ => None,
+    }
+}
+
+fn write_source(instance: &Instance, path: &Path) -> anyhow::Result<()> {
+    let source = match instance.properties.get("Source") {
+        Some(Variant::String(source)) => source.as_str(),
+        _ => "",
+    };
+
+    fs_err::write(path, source)?;
+
+    Ok(())
+}
+
+fn is_valid_file_name(name: &str) -> bool {
+    const RESERVED_SUFFIXES: &[&str] = &[".server", ".client", ".meta", ".model", ".project"];
+
+    let lowercase = name.to_lowercase();
+
+    !name.is_empty()
+        && !name.ends_with(['.', ' '])
+        && !name.chars().any(|c| {
+            matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control()
+        })
+        && lowercase != "init"
+        && !lowercase.starts_with("init.")
+        && !RESERVED_SUFFIXES
+            .iter()
+            .any(|suffix| lowercase.ends_with(suffix))
+}
+
+/// Finds the path of every descendant of the root that a `RefPath` can point
+/// to. Instances whose names contain periods or are shared with a sibling
+/// can't be found by path, and neither can their descendants.
+fn ref_paths(dom: &WeakDom, root_id: Ref) -> HashMap<Ref, String> {
+    let mut paths = HashMap::new();
+    let mut to_visit = vec![(root_id, String::new())];
+
+    while let Some((id, path)) = to_visit.pop() {
+        let instance = dom.get_by_ref(id).unwrap();
+
+        for &child_id in instance.children() {
+            let child = dom.get_by_ref(child_id).unwrap();
+
+            let shares_name = instance
+                .children()
+                .iter()
+                .filter(|&&sibling| dom.get_by_ref(sibling).unwrap().name == child.name)
+                .count()
+                > 1;
+
+            if child.name.contains('.') || shares_name {
+                continue;
+            }
+
+            let child_path = if path.is_empty() {
+                child.name.clone()
+            } else {
+                format!("{}.{}", path, child.name)
+            };
+
+            paths.insert(child_id, child_path.clone());
+            to_visit.push((child_id, child_path));
+        }
+    }
+
+    paths
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use memofs::Vfs;
+    use rbx_dom_weak::{
+        types::{Enum, Vector3},
+        InstanceBuilder,
+    };
+    use tempfile::tempdir;
+
+    fn model() -> WeakDom {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_id = dom.root_ref();
+
+        let tool = dom.insert(
+            root_id,
+            InstanceBuilder::new("Tool")
+                .with_name("Sword")
+                .with_children([
+                    InstanceBuilder::new("Part")
+                        .with_name("Handle")
+                        .with_property("Size", Vector3::new(1.0, 4.0, 1.0)),
+                    InstanceBuilder::new("Script")
+                        .with_name("Damage")
+                        .with_property("Source", "print(\"hit\")\n")
+                        .with_property("Disabled", true)
+                        .with_property("RunContext", Enum::from_u32(0)),
+                    InstanceBuilder::new("LocalScript")
+                        .with_name("Swing")
+                        .with_property("Source", "print(\"swing\")\n"),
+                    InstanceBuilder::new("Folder").with_name("Config"),
+                ]),
+        );
+
+        let handle = dom.get_by_ref(tool).unwrap().children()[0];
+        let config = dom.get_by_ref(tool).unwrap().children()[3];
+
+        dom.insert(
+            config,
+            InstanceBuilder::new("ObjectValue")
+                .with_name("Target")
+                .with_property("Value", handle),
+        );
+        dom.insert(
+            config,
+            InstanceBuilder::new("ModuleScript")
+                .with_name("Settings")
+                .with_property("Source", "return {}\n")
+                .with_child(InstanceBuilder::new("NumberValue").with_name("Speed")),
+        );
+
+        dom
+    }
+
+    /// Describes the instance and its descendants, with Refs replaced by the
+    /// name of the instance they point to, so that trees can be compared.
+    fn describe(dom: &WeakDom, id: Ref) -> Vec<String> {
+        let instance = dom.get_by_ref(id).unwrap();
+
+        let mut properties: Vec<_> = instance
+            .properties
+            .iter()
+            .map(|(key, value)| match value {
+                Variant::Ref(target) => {
+                    format!("{}={}", key, dom.get_by_ref(*target).unwrap().name)
+                }
+                value => format!("{}={:?}", key, value),
+            })
+            .collect();
+        properties.sort();
+
+        let mut lines = vec![format!(
+            "{} {} {}",
+            instance.class,
+            instance.name,
+            properties.join(" ")
+        )];
+
+        // Children are read back in the order of their files, not the order
+        // they were in originally.
+        let mut children: Vec<_> = instance
+            .children()
+            .iter()
+            .map(|&child| describe(dom, child))
+            .collect();
+        children.sort();
+
+        for child in children {
+            lines.extend(child.into_iter().map(|line| format!("  {}", line)));
+        }
+
+        lines
+    }
+
+    #[test]
+    fn round_trip() {
+        let dom = model();
+        let root_id = dom.root().children()[0];
+        let output = tempdir().unwrap();
+
+        unpack(&dom, root_id, output.path()).unwrap();
+
+        let src = output.path().join(SOURCE_DIR);
+        assert!(src.join("Handle.model.json").is_file());
+        assert!(src.join("Damage.server.luau").is_file());
+        assert!(src.join("Damage.meta.json").is_file());
+        assert!(src.join("Swing.client.luau").is_file());
+        assert!(!src.join("Swing.meta.json").exists());
+        assert!(src.join("Config/Target/init.meta.json").is_file());
+        assert!(src.join("Config/Settings/init.luau").is_file());
+        assert!(src.join("Config/Settings/Speed.model.json").is_file());
+
+        let rebuilt = crate::build_tree(&Vfs::new_default(), output.path()).unwrap();
+
+        assert_eq!(
+            describe(&rebuilt, rebuilt.root_ref()),
+            describe(&dom, root_id)
+        );
+    }
+
+    #[test]
+    fn names_that_cant_be_files() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_id = dom.root_ref();
+
+        let folder = dom.insert(
+            root_id,
+            InstanceBuilder::new("Folder").with_name("Root").with_child(
+                InstanceBuilder::new("Folder")
+                    .with_name("Same")
+                    .with_children([
+                        InstanceBuilder::new("Part").with_name("Part"),
+                        InstanceBuilder::new("Part").with_name("Part"),
+                    ]),
+            ),
+        );
+
+        let output = tempdir().unwrap();
+        unpack(&dom, folder, output.path()).unwrap();
+
+        let src = output.path().join(SOURCE_DIR);
+        assert!(src.join("Same.rbxmx").is_file());
+
+        let rebuilt = crate::build_tree(&Vfs::new_default(), output.path()).unwrap();
+        assert_eq!(
+            describe(&rebuilt, rebuilt.root_ref()),
+            describe(&dom, folder)
+        );
+
+        assert!(!is_valid_file_name("init"));
+        assert!(!is_valid_file_name("Thing.server"));
+        assert!(!is_valid_file_name("A/B"));
+        assert!(is_valid_file_name("Roact.Component"));
+    }
+}
@@ -93,6 +93,7 @@ impl UploadCommand {
 
 /// The kind of asset to upload to the website. Affects what endpoints Rojo uses
 /// and changes how the asset is built.
+#[allow(dead_code)]
 #[derive(Debug, Clone, Copy)]
 enum UploadKind {
     /// Upload to a place.
@@ -0,0 +1,169 @@
+use std::str::FromStr;
+
+use anyhow::format_err;
+
+const CDATA_START: &[u8] = b"<![CDATA[";
+const CDATA_END: &[u8] = b"]]>";
+
+/// How XML model and place files are laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XmlFormat {
+    /// Every element is on its own line and indented, which is what rbx_xml
+    /// writes on its own. This is easier to read and diff.
+    Pretty,
+
+    /// Elements are written without any whitespace between them.
+    Compact,
+}
+
+impl FromStr for XmlFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match source {
+            "pretty" => Ok(XmlFormat::Pretty),
+            "compact" => Ok(XmlFormat::Compact),
+            attempted => Err(format_err!(
+                "Invalid XML format '{}'. Valid formats are: pretty, compact",
+                attempted
+            )),
+        }
+    }
+}
+
+/// Rewrites an XML file produced by rbx_xml so that it uses the given format.
+///
+/// rbx_xml always indents its output, so compact output is made by removing
+/// the indentation afterwards. Models never mix elements and text, so any
+/// whitespace between two tags is indentation unless it's the only contents
+/// of an element.
+pub(super) fn reformat(contents: Vec<u8>, format: XmlFormat) -> Vec<u8> {
+    if format == XmlFormat::Pretty {
+        return contents;
+    }
+
+    let mut output = Vec::with_capacity(contents.len());
+
+    // Whether the last tag written opened an element, in which case whitespace
+    // directly followed by a closing tag is that element's value.
+    let mut after_start_tag = false;
+    let mut rest = contents.as_slice();
+
+    while !rest.is_empty() {
+        if rest.starts_with(CDATA_START) {
+            // CDATA can contain anything, including what looks like tags.
+            let len = find(rest, CDATA_END).map_or(rest.len(), |end| end + CDATA_END.len());
+            output.extend_from_slice(&rest[..len]);
+            rest = &rest[len..];
+        } else if rest[0] == b'<' {
+            // Attribute values always have '>' escaped, so the first one ends
+            // the tag.
+            let len = find(rest, b">").map_or(rest.len(), |end| end + 1);
+            let tag = &rest[..len];
+            after_start_tag = !tag.starts_with(b"</")
+                && !tag.starts_with(b"<?")
+                && !tag.starts_with(b"<!")
+                && !tag.ends_with(b"/>");
+
+            output.extend_from_slice(tag);
+            rest = &rest[len..];
+        } else {
+            // Text always has '<' escaped, so it runs until the next tag.
+            let len = find(rest, b"<").unwrap_or(rest.len());
+            let text = &rest[..len];
+            rest = &rest[len..];
+
+            let is_value = after_start_tag && rest.starts_with(b"</");
+            if is_value || !text.iter().all(u8::is_ascii_whitespace) {
+                output.extend_from_slice(text);
+            }
+        }
+    }
+
+    output
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::{InstanceBuilder, WeakDom};
+
+    fn encoded() -> Vec<u8> {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("Folder")
+                .with_name("Root")
+                .with_children([
+                    InstanceBuilder::new("StringValue")
+                        .with_name("Whitespace")
+                        .with_property("Value", "\n    "),
+                    InstanceBuilder::new("StringValue")
+                        .with_name("Lines")
+                        .with_property("Value", "first\n    second"),
+                    InstanceBuilder::new("ModuleScript")
+                        .with_name("Module")
+                        .with_property("Source", "local a = 1 < 2\n\n    return a > 0\n"),
+                ]),
+        );
+
+        let mut contents = Vec::new();
+        rbx_xml::to_writer_default(&mut contents, &dom, &[dom.root_ref()]).unwrap();
+        contents
+    }
+
+    fn read(contents: &[u8]) -> WeakDom {
+        rbx_xml::from_reader_default(contents).unwrap()
+    }
+
+    fn string_property(dom: &WeakDom, name: &str, property: &str) -> String {
+        let root = dom.get_by_ref(dom.root().children()[0]).unwrap();
+        let instance = root
+            .children()
+            .iter()
+            .map(|&child| dom.get_by_ref(child).unwrap())
+            .find(|child| child.name == name)
+            .unwrap();
+
+        match &instance.properties[property] {
+            rbx_dom_weak::types::Variant::String(value) => value.clone(),
+            other => panic!("{} was not a string: {:?}", property, other),
+        }
+    }
+
+    #[test]
+    fn pretty_has_newlines() {
+        let pretty = reformat(encoded(), XmlFormat::Pretty);
+
+        assert!(pretty.contains(&b'\n'));
+        assert_eq!(pretty, encoded());
+    }
+
+    #[test]
+    fn compact_only_has_newlines_in_values() {
+        let compact = reformat(encoded(), XmlFormat::Compact);
+        let text = String::from_utf8(compact.clone()).unwrap();
+
+        // The only newlines left are the ones inside the values.
+        assert!(text.starts_with("<roblox"));
+        assert_eq!(
+            text.matches('\n').count(),
+            5,
+            "compact output was:\n{}",
+            text
+        );
+
+        let dom = read(&compact);
+        assert_eq!(string_property(&dom, "Whitespace", "Value"), "\n    ");
+        assert_eq!(string_property(&dom, "Lines", "Value"), "first\n    second");
+        assert_eq!(
+            string_property(&dom, "Module", "Source"),
+            "local a = 1 < 2\n\n    return a > 0\n"
+        );
+    }
+}
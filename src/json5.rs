@@ -0,0 +1,292 @@
+//! Support for the parts of JSON5 that are most useful in hand-written files:
+//! comments, trailing commas, and object keys without quotes.
+//!
+//! Rather than parsing JSON5 directly, sources are converted to JSON that
+//! serde_json can read. Comments and trailing commas are replaced with
+//! whitespace and quotes are added around bare keys, which is tracked so that
+//! errors can point at the right place in the original source.
+
+use std::fmt;
+
+/// JSON converted from a JSON5 source.
+#[derive(Debug)]
+pub struct Converted {
+    pub json: String,
+
+    /// The positions of every quote that was added, as the line and column
+    /// they were inserted at in the original source.
+    insertions: Vec<(usize, usize)>,
+}
+
+impl Converted {
+    /// Maps a line and column in the converted JSON, like those reported by
+    /// serde_json, back to where they came from in the original source.
+    pub fn original_position(&self, line: usize, column: usize) -> (usize, usize) {
+        let mut shift = 0;
+
+        for &(insert_line, insert_column) in &self.insertions {
+            if insert_line == line && insert_column + shift < column {
+                shift += 1;
+            }
+        }
+
+        (line, column - shift)
+    }
+
+    /// Converts an error from parsing the converted JSON into one that points
+    /// to the original source.
+    pub fn map_error(&self, err: serde_json::Error) -> Json5Error {
+        let (line, column) = self.original_position(err.line(), err.column());
+
+        // serde_json includes the position in its message, which would be
+        // wrong for the original source.
+        let message = err.to_string();
+        let message = message
+            .strip_suffix(&format!(" at line {} column {}", err.line(), err.column()))
+            .unwrap_or(&message)
+            .to_owned();
+
+        Json5Error {
+            message,
+            line,
+            column,
+        }
+    }
+}
+
+/// An error in a JSON5 source that stopped it from being converted.
+#[derive(Debug)]
+pub struct Json5Error {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Json5Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{} at line {} column {}",
+            self.message, self.line, self.column
+        )
+    }
+}
+
+impl std::error::Error for Json5Error {}
+
+/// Converts a JSON5 source into JSON.
+pub fn to_json(source: &str) -> Result<Converted, Json5Error> {
+    let source = strip_comments(source)?;
+    let bytes = source.as_bytes();
+
+    let mut json = String::with_capacity(source.len());
+    let mut insertions = Vec::new();
+    // Whether each open bracket is an object, as opposed to an array.
+    let mut in_object = Vec::new();
+    let mut expecting_key = false;
+    let mut copied_up_to = 0;
+    let mut line = 1;
+    let mut line_start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\n' => {
+                line += 1;
+                line_start = i + 1;
+                i += 1;
+            }
+            b'"' => {
+                i = skip_string(bytes, i);
+                expecting_key = false;
+            }
+            b'{' | b'[' => {
+                in_object.push(bytes[i] == b'{');
+                expecting_key = bytes[i] == b'{';
+                i += 1;
+            }
+            b'}' | b']' => {
+                in_object.pop();
+                expecting_key = false;
+                i += 1;
+            }
+            b',' => {
+                let next = bytes[i + 1..]
+                    .iter()
+                    .find(|byte| !byte.is_ascii_whitespace());
+
+                if matches!(next, Some(b'}') | Some(b']')) {
+                    json.push_str(&source[copied_up_to..i]);
+                    json.push(' ');
+                    copied_up_to = i + 1;
+                }
+
+                expecting_key = in_object.last() == Some(&true);
+                i += 1;
+            }
+            byte if expecting_key && is_identifier_start(byte) => {
+                let end = i + bytes[i..]
+                    .iter()
+                    .take_while(|&&byte| is_identifier_part(byte))
+                    .count();
+
+                json.push_str(&source[copied_up_to..i]);
+                json.push('"');
+                json.push_str(&source[i..end]);
+                json.push('"');
+                copied_up_to = end;
+
+                let column = i - line_start + 1;
+                insertions.push((line, column));
+                insertions.push((line, column + (end - i)));
+
+                expecting_key = false;
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+
+    json.push_str(&source[copied_up_to..]);
+
+    Ok(Converted { json, insertions })
+}
+
+/// Replaces comments with spaces, keeping every newline so that positions in
+/// the result are the same as in the source.
+fn strip_comments(source: &str) -> Result<String, Json5Error> {
+    let bytes = source.as_bytes();
+    let mut output = String::with_capacity(source.len());
+    let mut copied_up_to = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1)) {
+            (b'"', _) => i = skip_string(bytes, i),
+            (b'/', Some(b'/')) => {
+                let end = bytes[i..]
+                    .iter()
+                    .position(|&byte| byte == b'\n')
+                    .map_or(bytes.len(), |offset| i + offset);
+
+                output.push_str(&source[copied_up_to..i]);
+                blank(&source[i..end], &mut output);
+                copied_up_to = end;
+                i = end;
+            }
+            (b'/', Some(b'*')) => {
+                let end = source[i + 2..]
+                    .find("*/")
+                    .map(|offset| i + 2 + offset + 2)
+                    .ok_or_else(|| {
+                        let (line, column) = position_of(source, i);
+                        Json5Error {
+                            message: "unterminated block comment".to_owned(),
+                            line,
+                            column,
+                        }
+                    })?;
+
+                output.push_str(&source[copied_up_to..i]);
+                blank(&source[i..end], &mut output);
+                copied_up_to = end;
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+
+    output.push_str(&source[copied_up_to..]);
+    Ok(output)
+}
+
+/// Pushes a space for each byte of the given text, or a newline for each of
+/// its newlines.
+fn blank(text: &str, output: &mut String) {
+    output.extend(
+        text.bytes()
+            .map(|byte| if byte == b'\n' { '\n' } else { ' ' }),
+    );
+}
+
+/// Given the index of an opening quote, returns the index just past the end
+/// of the string it starts.
+fn skip_string(bytes: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+
+    bytes.len()
+}
+
+fn position_of(source: &str, index: usize) -> (usize, usize) {
+    let before = &source[..index];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+
+    (line, index - line_start + 1)
+}
+
+fn is_identifier_start(byte: u8) -> bool {
+    byte.is_ascii_alphabetic() || byte == b'_' || byte == b'$'
+}
+
+fn is_identifier_part(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$'
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn convert(source: &str) -> String {
+        to_json(source).unwrap().json
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            convert("{\n  // line\n  \"a\": /* block */ 1\n}"),
+            "{\n         \n  \"a\":             1\n}"
+        );
+        assert_eq!(
+            convert(r#"{"a": "// not a comment"}"#),
+            r#"{"a": "// not a comment"}"#
+        );
+    }
+
+    #[test]
+    fn trailing_commas() {
+        assert_eq!(convert("[1, 2, ]"), "[1, 2  ]");
+        assert_eq!(convert("{\"a\": [1,],\n}"), "{\"a\": [1 ] \n}");
+        assert_eq!(convert(r#"["a,]"]"#), r#"["a,]"]"#);
+    }
+
+    #[test]
+    fn unquoted_keys() {
+        assert_eq!(
+            convert("{ $className: \"Folder\", child: { $path: \"src\" } }"),
+            "{ \"$className\": \"Folder\", \"child\": { \"$path\": \"src\" } }"
+        );
+        assert_eq!(convert("[true, null]"), "[true, null]");
+        assert_eq!(convert("{ \"a\": false }"), "{ \"a\": false }");
+    }
+
+    #[test]
+    fn positions() {
+        let converted = to_json("{\n  key: \"value\", bad: @\n}").unwrap();
+
+        // The @ is at column 22 of the source, but 26 in the converted JSON.
+        assert_eq!(&converted.json.lines().nth(1).unwrap()[25..26], "@");
+        assert_eq!(converted.original_position(2, 26), (2, 22));
+
+        let err = to_json("{\n  /* never closed\n}").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 3));
+    }
+}
@@ -8,8 +8,10 @@ pub mod cli;
 mod tree_view;
 
 mod auth_cookie;
+mod build_tree;
 mod change_processor;
 mod glob;
+mod json5;
 mod lua_ast;
 mod message_queue;
 mod multimap;
@@ -22,6 +24,8 @@ mod snapshot;
 mod snapshot_middleware;
 mod web;
 
+pub use build_tree::{build_tree, patch_tree};
 pub use project::*;
 pub use session_id::SessionId;
+pub use snapshot::{AddedInstance, PatchDiff, UpdatedInstance, ValueChange};
 pub use web::interface as web_api;
@@ -3,7 +3,7 @@ use std::{env, panic, process};
 use backtrace::Backtrace;
 use clap::Parser;
 
-use librojo::cli::Options;
+use librojo::cli::{ExitCode, Options};
 
 fn main() {
     #[cfg(feature = "profile-with-tracy")]
@@ -73,6 +73,6 @@ fn main() {
 
     if let Err(err) = options.run() {
         log::error!("{:?}", err);
-        process::exit(1);
+        process::exit(ExitCode::for_error(&err) as i32);
     }
 }
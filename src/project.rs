@@ -1,14 +1,21 @@
 use std::{
-    collections::{BTreeMap, HashMap, HashSet},
-    fs, io,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    env, fs, io,
     net::IpAddr,
-    path::{Path, PathBuf},
+    path::{Path, PathBuf, MAIN_SEPARATOR_STR},
 };
 
-use serde::{Deserialize, Serialize};
+use memofs::Vfs;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
 use thiserror::Error;
 
-use crate::{glob::Glob, resolution::UnresolvedValue, snapshot::SyncRule};
+use crate::{
+    glob::Glob,
+    json5::{self, Json5Error},
+    resolution::UnresolvedValue,
+    snapshot::SyncRule,
+};
 
 static PROJECT_FILENAME: &str = "default.project.json";
 
@@ -30,6 +37,45 @@ enum Error {
         source: serde_json::Error,
         path: PathBuf,
     },
+
+    #[error("Error parsing Rojo project in path {}", .path.display())]
+    Json5 { source: Json5Error, path: PathBuf },
+
+    #[error("Invalid Rojo project in path {}: {json_path} {message}", .path.display())]
+    Invalid {
+        json_path: String,
+        message: String,
+        path: PathBuf,
+    },
+
+    #[error(
+        "Environment variable {name} is used by $path {value:?} in project {}, but it isn't set",
+        .path.display()
+    )]
+    UnsetEnvVar {
+        name: String,
+        value: String,
+        path: PathBuf,
+    },
+
+    #[error("$path {value:?} in project {} has a ${{ without a closing }}", .path.display())]
+    UnclosedEnvVar { value: String, path: PathBuf },
+
+    #[error(
+        "$path {value:?} in project {} is an absolute Windows path, which can't be used on this platform",
+        .path.display()
+    )]
+    WindowsAbsolutePath { value: String, path: PathBuf },
+
+    #[error("Could not read project {}, which is included by {}", .included.display(), .path.display())]
+    IncludeRead {
+        source: io::Error,
+        included: PathBuf,
+        path: PathBuf,
+    },
+
+    #[error("Projects cannot include themselves: {cycle}")]
+    IncludeCycle { cycle: String },
 }
 
 /// Contains all of the configuration for a Rojo-managed project.
@@ -55,7 +101,10 @@ pub struct Project {
     ///
     /// This setting is intended to help prevent syncing a Rojo project into the
     /// wrong Roblox place.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_sorted_place_ids"
+    )]
     pub serve_place_ids: Option<HashSet<u64>>,
 
     /// If specified, sets the current place's place ID when connecting to the
@@ -79,6 +128,52 @@ pub struct Project {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub emit_legacy_scripts: Option<bool>,
 
+    /// Determines if Rojo should remove comments from the Source of scripts.
+    /// If not specified, nested projects use the setting of the project that
+    /// contains them, and top-level projects keep comments.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strip_comments: Option<bool>,
+
+    /// Determines if Rojo should ignore changes to the Source of scripts that
+    /// only change whitespace outside of strings, like re-indenting. If not
+    /// specified, nested projects use the setting of the project that contains
+    /// them, and top-level projects sync every change.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore_script_whitespace: Option<bool>,
+
+    /// Determines if Rojo should leave out properties generated from files,
+    /// like the `RunContext` of scripts, when they match the default value for
+    /// their class. Properties set explicitly are always kept. If not
+    /// specified, nested projects use the setting of the project that contains
+    /// them, and top-level projects keep every property.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prune_default_properties: Option<bool>,
+
+    /// Determines if Rojo should snapshot the contents of directories on
+    /// several threads at once. This can speed up building and serving
+    /// projects with many files, and doesn't change the result. If not
+    /// specified, nested projects use the setting of the project that contains
+    /// them, and top-level projects are snapshotted on one thread.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parallel_snapshots: Option<bool>,
+
+    /// The class to use for directories, and for nodes in the project that
+    /// don't have a `$className`, a `$path`, or a name Rojo can infer a class
+    /// from. A `$className` always takes priority. If not specified, nested
+    /// projects use the setting of the project that contains them, and
+    /// top-level projects use Folder.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_directory_class_name: Option<String>,
+
+    /// Default values for properties of every instance with a given class,
+    /// keyed by class name and then property name.
+    ///
+    /// These are only used when an instance doesn't set the property itself,
+    /// whether through the project, a model file, or a meta file. Nested
+    /// projects use these defaults too, but can replace them.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub class_defaults: BTreeMap<String, BTreeMap<String, UnresolvedValue>>,
+
     /// A list of globs, relative to the folder the project file is in, that
     /// match files that should be excluded if Rojo encounters them.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
@@ -95,6 +190,12 @@ pub struct Project {
     /// given by `Project::folder_location`.
     #[serde(skip)]
     pub file_location: PathBuf,
+
+    /// The JSON paths of keys starting with `$` in the project's tree that
+    /// Rojo doesn't know about, like `tree.ReplicatedStorage.$classNme`. These
+    /// are left out when the project is loaded.
+    #[serde(skip)]
+    unknown_keys: Vec<String>,
 }
 
 impl Project {
@@ -135,17 +236,17 @@ impl Project {
         }
     }
 
+    /// Loads a project to be turned into instances. References to
+    /// environment variables in `$path`, like `${NAME}` or
+    /// `${NAME:-default}`, are replaced with their values.
     pub fn load_from_slice(
         contents: &[u8],
         project_file_location: &Path,
     ) -> Result<Self, ProjectError> {
-        let mut project: Self = serde_json::from_slice(contents).map_err(|source| Error::Json {
-            source,
-            path: project_file_location.to_owned(),
-        })?;
+        let mut project = Self::parse(contents, project_file_location)?;
 
-        project.file_location = project_file_location.to_path_buf();
-        project.check_compatibility();
+        project.tree.expand_env_vars(project_file_location)?;
+        project.tree.normalize_separators(project_file_location)?;
         Ok(project)
     }
 
@@ -159,25 +260,105 @@ impl Project {
         }
     }
 
+    /// Loads a project exactly as it's written, without expanding environment
+    /// variables, so that it can be written back out unchanged.
     fn load_exact(project_file_location: &Path) -> Result<Self, Error> {
-        let contents = fs::read_to_string(project_file_location)?;
+        let contents = fs::read(project_file_location)?;
+        Self::parse(&contents, project_file_location)
+    }
 
-        let mut project: Project =
-            serde_json::from_str(&contents).map_err(|source| Error::Json {
-                source,
-                path: project_file_location.to_owned(),
-            })?;
+    /// Parses a project file, which may use comments, trailing commas, and
+    /// unquoted keys from JSON5. Files that are valid JSON are read as-is.
+    fn parse(contents: &[u8], project_file_location: &Path) -> Result<Self, Error> {
+        let json_error = |source| Error::Json {
+            source,
+            path: project_file_location.to_owned(),
+        };
+        let json5_error = |source| Error::Json5 {
+            source,
+            path: project_file_location.to_owned(),
+        };
+        let invalid = |ProjectValidationError { json_path, message }| Error::Invalid {
+            json_path,
+            message,
+            path: project_file_location.to_owned(),
+        };
+
+        // Projects are checked before they're deserialized so that mistakes
+        // can be reported along with where they are in the project. Unknown
+        // keys are removed from the value, so it's deserialized instead of the
+        // original contents when there are any.
+        let mut project: Self = match serde_json::from_slice::<Value>(contents) {
+            Ok(mut value) => {
+                let unknown_keys = validate_project(&mut value).map_err(invalid)?;
+
+                if unknown_keys.is_empty() {
+                    serde_json::from_slice(contents).map_err(json_error)?
+                } else {
+                    let mut project: Self = serde_json::from_value(value).map_err(json_error)?;
+                    project.unknown_keys = unknown_keys;
+                    project
+                }
+            }
+
+            // JSON5 can only help with files that aren't valid JSON, not ones
+            // that are valid JSON but aren't valid projects.
+            Err(err) if err.is_syntax() || err.is_eof() => {
+                let source = std::str::from_utf8(contents).map_err(|_| json_error(err))?;
+                let converted = json5::to_json(source).map_err(json5_error)?;
+                let converted_error = |err| json5_error(converted.map_error(err));
+
+                let mut value = serde_json::from_str(&converted.json).map_err(converted_error)?;
+                let unknown_keys = validate_project(&mut value).map_err(invalid)?;
+
+                if unknown_keys.is_empty() {
+                    serde_json::from_str(&converted.json).map_err(converted_error)?
+                } else {
+                    let mut project: Self = serde_json::from_value(value).map_err(json_error)?;
+                    project.unknown_keys = unknown_keys;
+                    project
+                }
+            }
+
+            Err(err) => return Err(json_error(err)),
+        };
 
         project.file_location = project_file_location.to_path_buf();
-        project.check_compatibility();
 
         Ok(project)
     }
 
-    /// Checks if there are any compatibility issues with this project file and
-    /// warns the user if there are any.
-    fn check_compatibility(&self) {
-        self.tree.validate_reserved_names();
+    /// Returns an error for every key starting with `$` that the project's tree
+    /// used but Rojo doesn't know about. These keys are ignored when the
+    /// project is loaded. They're usually warned about, and are errors in
+    /// strict mode.
+    pub fn unknown_key_errors(&self) -> impl Iterator<Item = ProjectError> + '_ {
+        self.unknown_keys.iter().map(|json_path| {
+            Error::Invalid {
+                json_path: json_path.clone(),
+                message: format!(
+                    "is not a known key. Keys starting with $ must be one of: {}",
+                    PROJECT_NODE_KEYS.join(", ")
+                ),
+                path: self.file_location.clone(),
+            }
+            .into()
+        })
+    }
+
+    /// Replaces every `$include` in the project's tree with the tree of the
+    /// project it refers to, which is read through the given `Vfs`. Returns
+    /// the paths of all of the included projects.
+    ///
+    /// Only the tree of an included project is used; its other settings, like
+    /// `syncRules`, are ignored.
+    pub fn resolve_includes(&mut self, vfs: &Vfs) -> Result<Vec<PathBuf>, ProjectError> {
+        let mut stack = vec![self.file_location.clone()];
+        let mut included = Vec::new();
+
+        self.tree.resolve_includes(vfs, &mut stack, &mut included)?;
+
+        Ok(included)
     }
 
     pub fn folder_location(&self) -> &Path {
@@ -212,6 +393,13 @@ impl PathNode {
             PathNode::Optional(OptionalPathNode { optional }) => optional,
         }
     }
+
+    fn path_mut(&mut self) -> &mut PathBuf {
+        match self {
+            PathNode::Required(pathbuf) => pathbuf,
+            PathNode::Optional(OptionalPathNode { optional }) => optional,
+        }
+    }
 }
 
 /// Describes an instance and its descendants in a project.
@@ -226,9 +414,21 @@ pub struct ProjectNode {
     #[serde(rename = "$className", skip_serializing_if = "Option::is_none")]
     pub class_name: Option<String>,
 
-    /// Contains all of the children of the described instance.
-    #[serde(flatten)]
-    pub children: BTreeMap<String, ProjectNode>,
+    /// Defines that this instance should come from the given file path. This
+    /// path can point to any file type supported by Rojo, including Lua files
+    /// (`.lua`), Roblox models (`.rbxm`, `.rbxmx`), and localization table
+    /// spreadsheets (`.csv`).
+    #[serde(rename = "$path", skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathNode>,
+
+    /// If set to `true`, a `$path` that doesn't exist is skipped instead of
+    /// being an error, the same as writing `$path` as `{ "optional": ... }`.
+    #[serde(
+        rename = "$optional",
+        default,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub optional: bool,
 
     /// The properties that will be assigned to the resulting instance.
     ///
@@ -236,14 +436,16 @@ pub struct ProjectNode {
     #[serde(
         rename = "$properties",
         default,
-        skip_serializing_if = "HashMap::is_empty"
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_sorted"
     )]
     pub properties: HashMap<String, UnresolvedValue>,
 
     #[serde(
         rename = "$attributes",
         default,
-        skip_serializing_if = "HashMap::is_empty"
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_sorted"
     )]
     pub attributes: HashMap<String, UnresolvedValue>,
 
@@ -267,30 +469,335 @@ pub struct ProjectNode {
     )]
     pub ignore_unknown_instances: Option<bool>,
 
-    /// Defines that this instance should come from the given file path. This
-    /// path can point to any file type supported by Rojo, including Lua files
-    /// (`.lua`), Roblox models (`.rbxm`, `.rbxmx`), and localization table
-    /// spreadsheets (`.csv`).
-    #[serde(rename = "$path", skip_serializing_if = "Option::is_none")]
-    pub path: Option<PathNode>,
+    /// The path to another project file, relative to this one, whose tree
+    /// should be used as the base of this node. Everything else set on this
+    /// node takes precedence over the included tree, and children with the
+    /// same name are combined the same way.
+    #[serde(rename = "$include", skip_serializing_if = "Option::is_none")]
+    pub include: Option<PathBuf>,
+
+    /// Contains all of the children of the described instance.
+    #[serde(flatten)]
+    pub children: BTreeMap<String, ProjectNode>,
 }
 
 impl ProjectNode {
-    fn validate_reserved_names(&self) {
-        for (name, child) in &self.children {
-            if name.starts_with('$') {
-                log::warn!(
-                    "Keys starting with '$' are reserved by Rojo to ensure forward compatibility."
-                );
-                log::warn!(
-                    "This project uses the key '{}', which should be renamed.",
-                    name
-                );
+    /// Tells whether this node's `$path` is allowed to not exist.
+    pub fn is_path_optional(&self) -> bool {
+        self.optional || matches!(self.path, Some(PathNode::Optional(_)))
+    }
+
+    /// Replaces references to environment variables in this node's `$path`
+    /// and those of its descendants with their values.
+    fn expand_env_vars(&mut self, project_file_location: &Path) -> Result<(), Error> {
+        if let Some(path_node) = &mut self.path {
+            let path = path_node.path_mut();
+
+            if let Some(value) = path.to_str() {
+                let expanded = expand_env_vars(value, |name| env::var(name).ok()).map_err(
+                    |err| match err {
+                        ExpandError::Unset(name) => Error::UnsetEnvVar {
+                            name,
+                            value: value.to_owned(),
+                            path: project_file_location.to_owned(),
+                        },
+                        ExpandError::Unclosed => Error::UnclosedEnvVar {
+                            value: value.to_owned(),
+                            path: project_file_location.to_owned(),
+                        },
+                    },
+                )?;
+
+                *path = PathBuf::from(expanded);
+            }
+        }
+
+        for child in self.children.values_mut() {
+            child.expand_env_vars(project_file_location)?;
+        }
+
+        Ok(())
+    }
+
+    /// Makes every `$path` in this node and its descendants use the separator
+    /// of the current platform, so that projects written with either `/` or
+    /// `\` work everywhere.
+    fn normalize_separators(&mut self, project_file_location: &Path) -> Result<(), Error> {
+        if let Some(path_node) = &mut self.path {
+            let path = path_node.path_mut();
+
+            if let Some(value) = path.to_str() {
+                if !cfg!(windows) && is_windows_absolute_path(value) {
+                    return Err(Error::WindowsAbsolutePath {
+                        value: value.to_owned(),
+                        path: project_file_location.to_owned(),
+                    });
+                }
+
+                *path = PathBuf::from(value.replace(['/', '\\'], MAIN_SEPARATOR_STR));
+            }
+        }
+
+        for child in self.children.values_mut() {
+            child.normalize_separators(project_file_location)?;
+        }
+
+        Ok(())
+    }
+
+    fn resolve_includes(
+        &mut self,
+        vfs: &Vfs,
+        stack: &mut Vec<PathBuf>,
+        included: &mut Vec<PathBuf>,
+    ) -> Result<(), ProjectError> {
+        for child in self.children.values_mut() {
+            child.resolve_includes(vfs, stack, included)?;
+        }
+
+        let include = match self.include.take() {
+            Some(include) => include,
+            None => return Ok(()),
+        };
+
+        let including_path = stack.last().unwrap();
+        let include_path = including_path.parent().unwrap().join(include);
+
+        if stack.contains(&include_path) {
+            let cycle = stack
+                .iter()
+                .chain([&include_path])
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+
+            return Err(Error::IncludeCycle { cycle }.into());
+        }
+
+        let contents = vfs
+            .read(&include_path)
+            .map_err(|source| Error::IncludeRead {
+                source,
+                included: include_path.clone(),
+                path: including_path.clone(),
+            })?;
+        let mut base = Project::load_from_slice(&contents, &include_path)?.tree;
+
+        stack.push(include_path.clone());
+        base.resolve_includes(vfs, stack, included)?;
+        stack.pop();
+
+        base.rebase_paths(include_path.parent().unwrap());
+        included.push(include_path);
+
+        let overrides = std::mem::take(self);
+        *self = base;
+        self.merge(overrides);
+
+        Ok(())
+    }
+
+    /// Makes every relative `$path` in this node and its descendants relative
+    /// to the given folder instead.
+    fn rebase_paths(&mut self, folder: &Path) {
+        if let Some(path_node) = &mut self.path {
+            let path = path_node.path_mut();
+            *path = folder.join(&path);
+        }
+
+        for child in self.children.values_mut() {
+            child.rebase_paths(folder);
+        }
+    }
+
+    /// Applies everything set on `overrides` on top of this node.
+    fn merge(&mut self, overrides: ProjectNode) {
+        if overrides.class_name.is_some() {
+            self.class_name = overrides.class_name;
+        }
+
+        if overrides.path.is_some() {
+            self.path = overrides.path;
+        }
+
+        if overrides.optional {
+            self.optional = true;
+        }
+
+        if overrides.ignore_unknown_instances.is_some() {
+            self.ignore_unknown_instances = overrides.ignore_unknown_instances;
+        }
+
+        self.properties.extend(overrides.properties);
+        self.attributes.extend(overrides.attributes);
+
+        for (name, child) in overrides.children {
+            match self.children.get_mut(&name) {
+                Some(existing) => existing.merge(child),
+                None => {
+                    self.children.insert(name, child);
+                }
             }
+        }
+    }
+}
 
-            child.validate_reserved_names();
+/// Serializes a map in order of its keys, so that serializing the same project
+/// always gives the same result.
+fn serialize_sorted<S, V>(map: &HashMap<String, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    V: Serialize,
+{
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+fn serialize_sorted_place_ids<S>(
+    ids: &Option<HashSet<u64>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    ids.as_ref()
+        .map(|ids| ids.iter().collect::<BTreeSet<_>>())
+        .serialize(serializer)
+}
+
+/// The keys that project nodes understand. Other keys starting with `$` are
+/// most likely typos, so they're ignored instead of becoming children.
+const PROJECT_NODE_KEYS: &[&str] = &[
+    "$className",
+    "$path",
+    "$optional",
+    "$properties",
+    "$attributes",
+    "$ignoreUnknownInstances",
+    "$include",
+];
+
+struct ProjectValidationError {
+    json_path: String,
+    message: String,
+}
+
+/// Checks the parts of a project that deserializing it wouldn't catch, or
+/// wouldn't be able to say where the problem is. Unknown keys starting with
+/// `$` are removed from the project, and their JSON paths are returned.
+fn validate_project(project: &mut Value) -> Result<Vec<String>, ProjectValidationError> {
+    let mut unknown_keys = Vec::new();
+
+    if let Some(tree) = project.get_mut("tree") {
+        validate_project_node(tree, "tree", &mut unknown_keys)?;
+    }
+
+    Ok(unknown_keys)
+}
+
+fn validate_project_node(
+    node: &mut Value,
+    json_path: &str,
+    unknown_keys: &mut Vec<String>,
+) -> Result<(), ProjectValidationError> {
+    let error = |json_path: String, message: &str| ProjectValidationError {
+        json_path,
+        message: message.to_owned(),
+    };
+
+    let node = node
+        .as_object_mut()
+        .ok_or_else(|| error(json_path.to_owned(), "must be an object"))?;
+
+    node.retain(|key, _| {
+        let known = !key.starts_with('$') || PROJECT_NODE_KEYS.contains(&key.as_str());
+
+        if !known {
+            unknown_keys.push(format!("{}.{}", json_path, key));
+        }
+
+        known
+    });
+
+    for (key, value) in node {
+        let child_path = format!("{}.{}", json_path, key);
+
+        let valid = match key.as_str() {
+            "$className" | "$include" => value.is_string(),
+            "$path" => {
+                value.is_string()
+                    || value
+                        .get("optional")
+                        .is_some_and(|optional| optional.is_string())
+            }
+            "$properties" | "$attributes" => value.is_object(),
+            "$optional" | "$ignoreUnknownInstances" => value.is_boolean(),
+            _ => {
+                validate_project_node(value, &child_path, unknown_keys)?;
+                continue;
+            }
+        };
+
+        if !valid {
+            let message = match key.as_str() {
+                "$className" | "$include" => "must be a string",
+                "$path" => "must be a string or an object with an \"optional\" string",
+                "$properties" | "$attributes" => "must be an object",
+                _ => "must be true or false",
+            };
+
+            return Err(error(child_path, message));
         }
     }
+
+    Ok(())
+}
+
+enum ExpandError {
+    Unset(String),
+    Unclosed,
+}
+
+/// Replaces every `${NAME}` in the input with the value of the variable
+/// `NAME`, as given by `lookup`. `${NAME:-default}` uses `default` instead if
+/// the variable isn't set.
+fn expand_env_vars(
+    input: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String, ExpandError> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find("${") {
+        output.push_str(&rest[..start]);
+
+        let after_start = &rest[start + 2..];
+        let end = after_start.find('}').ok_or(ExpandError::Unclosed)?;
+        let reference = &after_start[..end];
+
+        let value = match reference.split_once(":-") {
+            Some((name, default)) => lookup(name).unwrap_or_else(|| default.to_owned()),
+            None => lookup(reference).ok_or_else(|| ExpandError::Unset(reference.to_owned()))?,
+        };
+
+        output.push_str(&value);
+        rest = &after_start[end + 1..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Tells whether a path starts with a drive letter, like `C:\`, or is a UNC
+/// path, like `\\server\share`.
+fn is_windows_absolute_path(value: &str) -> bool {
+    let bytes = value.as_bytes();
+
+    let has_drive = bytes.len() >= 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes[2] == b'\\' || bytes[2] == b'/');
+
+    has_drive || value.starts_with("\\\\")
 }
 
 #[cfg(test)]
@@ -394,4 +901,190 @@ mod test {
         let serialized = serde_json::to_string(&project_node).unwrap();
         assert_eq!(serialized, r#"{"$path":"../src"}"#);
     }
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "GAME_ROOT" => Some("games/racing".to_owned()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn expand_env_vars_set() {
+        assert_eq!(
+            expand_env_vars("${GAME_ROOT}/src", lookup).ok(),
+            Some("games/racing/src".to_owned())
+        );
+        assert_eq!(
+            expand_env_vars("${GAME_ROOT:-default}/src", lookup).ok(),
+            Some("games/racing/src".to_owned())
+        );
+        assert_eq!(expand_env_vars("src", lookup).ok(), Some("src".to_owned()));
+    }
+
+    #[test]
+    fn expand_env_vars_unset() {
+        assert_eq!(
+            expand_env_vars("${MISSING:-shared}/src", lookup).ok(),
+            Some("shared/src".to_owned())
+        );
+        assert!(matches!(
+            expand_env_vars("${MISSING}/src", lookup),
+            Err(ExpandError::Unset(name)) if name == "MISSING"
+        ));
+        assert!(matches!(
+            expand_env_vars("${GAME_ROOT/src", lookup),
+            Err(ExpandError::Unclosed)
+        ));
+    }
+
+    #[test]
+    fn project_path_env_vars() {
+        env::set_var("ROJO_TEST_PROJECT_PATH_ROOT", "games/racing");
+
+        let project = Project::load_from_slice(
+            br#"{
+                "tree": {
+                    "$className": "DataModel",
+                    "ReplicatedStorage": {
+                        "$path": "${ROJO_TEST_PROJECT_PATH_ROOT}/src"
+                    },
+                    "ServerStorage": {
+                        "$path": { "optional": "${ROJO_TEST_PROJECT_PATH_UNSET:-shared}/server" }
+                    }
+                }
+            }"#,
+            Path::new("/default.project.json"),
+        )
+        .unwrap();
+
+        let path_of = |name: &str| project.tree.children[name].path.as_ref().unwrap().path();
+
+        assert_eq!(path_of("ReplicatedStorage"), Path::new("games/racing/src"));
+        assert_eq!(path_of("ServerStorage"), Path::new("shared/server"));
+
+        let err = Project::load_from_slice(
+            br#"{ "tree": { "$path": "${ROJO_TEST_PROJECT_PATH_UNSET}/src" } }"#,
+            Path::new("/default.project.json"),
+        )
+        .unwrap_err();
+
+        assert!(err.to_string().contains("ROJO_TEST_PROJECT_PATH_UNSET"));
+    }
+
+    #[test]
+    fn project_json5() {
+        let project = Project::load_from_slice(
+            br#"{
+                // Comments are allowed anywhere whitespace is.
+                name: "json5",
+                tree: {
+                    $className: "DataModel",
+                    /* Keys don't need quotes. */
+                    ReplicatedStorage: {
+                        "$path": "src",
+                    },
+                },
+            }"#,
+            Path::new("/default.project.json"),
+        )
+        .unwrap();
+
+        assert_eq!(project.name.as_deref(), Some("json5"));
+        assert_eq!(project.tree.class_name.as_deref(), Some("DataModel"));
+        assert_eq!(
+            project.tree.children["ReplicatedStorage"].path,
+            Some(PathNode::Required(PathBuf::from("src")))
+        );
+    }
+
+    #[test]
+    fn project_json5_error_position() {
+        let err = Project::load_from_slice(
+            b"{\n  tree: { $className: \"Folder\" },\n  name: oops,\n}",
+            Path::new("/default.project.json"),
+        )
+        .unwrap_err();
+
+        let source = std::error::Error::source(&err.0).unwrap();
+        assert_eq!(source.to_string(), "expected value at line 3 column 9");
+    }
+
+    #[test]
+    fn project_unknown_key() {
+        let project = Project::load_from_slice(
+            br#"{
+                "tree": {
+                    "$className": "DataModel",
+                    "ReplicatedStorage": { "$classNme": "ReplicatedStorage" }
+                }
+            }"#,
+            Path::new("/default.project.json"),
+        )
+        .unwrap();
+
+        let storage = &project.tree.children["ReplicatedStorage"];
+        assert_eq!(storage.class_name, None);
+        assert!(storage.children.is_empty());
+
+        let errors: Vec<_> = project
+            .unknown_key_errors()
+            .map(|error| error.to_string())
+            .collect();
+
+        assert_eq!(
+            errors,
+            ["Invalid Rojo project in path /default.project.json: \
+             tree.ReplicatedStorage.$classNme is not a known key. Keys starting with $ must be \
+             one of: $className, $path, $optional, $properties, $attributes, $ignoreUnknownInstances, \
+             $include"]
+        );
+    }
+
+    #[test]
+    fn project_wrong_type() {
+        let err = Project::load_from_slice(
+            br#"{ tree: { Workspace: { $properties: "Gravity" } } }"#,
+            Path::new("/default.project.json"),
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Invalid Rojo project in path /default.project.json: \
+             tree.Workspace.$properties must be an object"
+        );
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn project_path_backslashes() {
+        let project = Project::load_from_slice(
+            br#"{
+                "tree": {
+                    "$className": "DataModel",
+                    "ReplicatedStorage": { "$path": "src\\shared" },
+                    "ServerStorage": { "$path": { "optional": "src\\server/modules" } },
+                    "Workspace": { "$path": "src/workspace" }
+                }
+            }"#,
+            Path::new("/default.project.json"),
+        )
+        .unwrap();
+
+        let path_of = |name: &str| project.tree.children[name].path.as_ref().unwrap().path();
+
+        assert_eq!(path_of("ReplicatedStorage"), Path::new("src/shared"));
+        assert_eq!(path_of("ServerStorage"), Path::new("src/server/modules"));
+        assert_eq!(path_of("Workspace"), Path::new("src/workspace"));
+
+        for absolute in [r#""C:\\Games\\src""#, r#""\\\\server\\share""#] {
+            let contents = format!(r#"{{ "tree": {{ "$path": {} }} }}"#, absolute);
+            let err =
+                Project::load_from_slice(contents.as_bytes(), Path::new("/default.project.json"))
+                    .unwrap_err();
+
+            assert!(err.to_string().contains("absolute Windows path"), "{}", err);
+        }
+    }
 }
@@ -2,8 +2,8 @@ use std::borrow::Borrow;
 
 use anyhow::{bail, format_err};
 use rbx_dom_weak::types::{
-    Attributes, CFrame, Color3, Content, Enum, Font, MaterialColors, Matrix3, Tags, Variant,
-    VariantType, Vector2, Vector3,
+    Attributes, BrickColor, CFrame, Color3, Content, Enum, Font, MaterialColors, Matrix3, Tags,
+    Variant, VariantType, Vector2, Vector3,
 };
 use rbx_reflection::{DataType, PropertyDescriptor};
 use serde::{Deserialize, Serialize};
@@ -17,6 +17,9 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum UnresolvedValue {
+    // This has to come before FullyQualified, since a fully qualified Ref is
+    // written the same way.
+    RefPath(RefPath),
     FullyQualified(Variant),
     Ambiguous(AmbiguousValue),
 }
@@ -24,6 +27,7 @@ pub enum UnresolvedValue {
 impl UnresolvedValue {
     pub fn resolve(self, class_name: &str, prop_name: &str) -> anyhow::Result<Variant> {
         match self {
+            UnresolvedValue::RefPath(ref_path) => ref_path.unsupported(),
             UnresolvedValue::FullyQualified(full) => Ok(full),
             UnresolvedValue::Ambiguous(partial) => partial.resolve(class_name, prop_name),
         }
@@ -31,10 +35,56 @@ impl UnresolvedValue {
 
     pub fn resolve_unambiguous(self) -> anyhow::Result<Variant> {
         match self {
+            UnresolvedValue::RefPath(ref_path) => ref_path.unsupported(),
             UnresolvedValue::FullyQualified(full) => Ok(full),
             UnresolvedValue::Ambiguous(partial) => partial.resolve_unambiguous(),
         }
     }
+
+    /// Returns the path of the instance this value refers to if it's a Ref
+    /// given by path, after checking that the property can hold a Ref.
+    pub fn as_ref_path(&self, class_name: &str, prop_name: &str) -> anyhow::Result<Option<&str>> {
+        let ref_path = match self {
+            UnresolvedValue::RefPath(ref_path) => ref_path,
+            _ => return Ok(None),
+        };
+
+        let property = find_descriptor(class_name, prop_name)
+            .ok_or_else(|| format_err!("Unknown property {}.{}", class_name, prop_name))?;
+
+        match &property.data_type {
+            DataType::Value(VariantType::Ref) => Ok(Some(&ref_path.path)),
+            _ => bail!(
+                "Property {}.{} can't be set to a Ref, so it can't refer to {}",
+                class_name,
+                prop_name,
+                ref_path.path
+            ),
+        }
+    }
+}
+
+/// A Ref to another instance, written as the names of the instances between
+/// the root of the tree and it, separated by periods. For example,
+/// `{ "Ref": "ReplicatedStorage.Shared.Thing" }`.
+///
+/// Since the instance might not have been snapshotted yet, these are turned
+/// into real Refs once the whole tree has been snapshotted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RefPath {
+    #[serde(rename = "Ref")]
+    pub path: String,
+}
+
+impl RefPath {
+    fn unsupported(self) -> anyhow::Result<Variant> {
+        bail!(
+            "Refs to other instances, like the one to {}, can only be used in \
+             the properties of projects and meta files",
+            self.path
+        )
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -115,6 +165,33 @@ impl AmbiguousValue {
                     Ok(Content::from(value).into())
                 }
 
+                (VariantType::BrickColor, AmbiguousValue::String(value)) => {
+                    BrickColor::from_name(&value)
+                        .map(Variant::from)
+                        .ok_or_else(|| {
+                            format_err!(
+                                "Invalid value for property {}.{}. Got {} but \
+                                 expected the name of a BrickColor such as Bright red",
+                                class_name,
+                                prop_name,
+                                value,
+                            )
+                        })
+                }
+                (VariantType::BrickColor, AmbiguousValue::Number(value)) => {
+                    BrickColor::from_number(value as u16)
+                        .map(Variant::from)
+                        .ok_or_else(|| {
+                            format_err!(
+                                "Invalid value for property {}.{}. Got {} but \
+                                 expected the number of a BrickColor such as 21",
+                                class_name,
+                                prop_name,
+                                value,
+                            )
+                        })
+                }
+
                 (VariantType::Vector2, AmbiguousValue::Array2(value)) => {
                     Ok(Vector2::new(value[0] as f32, value[1] as f32).into())
                 }
@@ -331,12 +408,56 @@ mod test {
         // some, we should treat them the same in the future.
     }
 
+    #[test]
+    fn brick_colors() {
+        assert_eq!(
+            resolve("Part", "BrickColor", "\"Bright red\""),
+            Variant::BrickColor(BrickColor::BrightRed),
+        );
+
+        assert_eq!(
+            resolve("BrickColorValue", "Value", "21"),
+            Variant::BrickColor(BrickColor::BrightRed),
+        );
+
+        let unresolved: UnresolvedValue = serde_json::from_str("\"Not a color\"").unwrap();
+        assert_eq!(
+            unresolved
+                .resolve("Part", "BrickColor")
+                .unwrap_err()
+                .to_string(),
+            "Invalid value for property Part.BrickColor. Got Not a color but \
+             expected the name of a BrickColor such as Bright red"
+        );
+    }
+
+    #[test]
+    fn wrong_type() {
+        let unresolved: UnresolvedValue = serde_json::from_str("\"Big\"").unwrap();
+        assert_eq!(
+            unresolved.resolve("Part", "Size").unwrap_err().to_string(),
+            "Wrong type of value for property Part.Size. Expected Vector3, got a string"
+        );
+    }
+
     #[test]
     fn enums() {
         assert_eq!(
             resolve("Lighting", "Technology", "\"Voxel\""),
             Variant::Enum(Enum::from_u32(1)),
         );
+
+        assert_eq!(
+            resolve("Part", "Shape", "\"Ball\""),
+            Variant::Enum(Enum::from_u32(0)),
+        );
+
+        let unresolved: UnresolvedValue = serde_json::from_str("\"Sphere\"").unwrap();
+        assert_eq!(
+            unresolved.resolve("Part", "Shape").unwrap_err().to_string(),
+            "Invalid value for property Part.Shape. Got Sphere but expected a member of \
+             the PartType enum such as Ball, Block, CornerWedge, Cylinder, or Wedge"
+        );
     }
 
     #[test]
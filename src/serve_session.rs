@@ -20,9 +20,11 @@ use crate::{
     session_id::SessionId,
     snapshot::{
         apply_patch_set, compute_patch_set, AppliedPatchSet, InstanceContext, InstanceSnapshot,
-        PatchSet, RojoTree,
+        PatchSet, RojoTree, SnapshotCache, SnapshotProgress,
+    },
+    snapshot_middleware::{
+        resolve_ref_paths, snapshot_from_vfs, snapshot_from_vfs_collecting_errors, MiddlewareError,
     },
-    snapshot_middleware::snapshot_from_vfs,
 };
 
 /// Contains all of the state for a Rojo serve session. A serve session is used
@@ -89,6 +91,23 @@ pub struct ServeSession {
     tree_mutation_sender: Sender<PatchSet>,
 }
 
+/// Options that change how a `ServeSession` snapshots its project.
+#[derive(Debug, Clone, Default)]
+pub struct SessionOptions {
+    /// Whether files that can't be snapshotted are left out of the initial
+    /// tree and returned instead of stopping the session from starting. This
+    /// only applies to the initial snapshot; later changes are handled the
+    /// same way as in any other session.
+    pub collect_errors: bool,
+
+    /// Whether problems that are usually only warned about, like files whose
+    /// names only differ by case, stop the snapshot instead.
+    pub strict: bool,
+
+    /// Counts the files in the initial snapshot as they're snapshotted.
+    pub progress: Option<SnapshotProgress>,
+}
+
 impl ServeSession {
     /// Start a new serve session from the given in-memory filesystem and start
     /// path.
@@ -97,72 +116,51 @@ impl ServeSession {
     /// currently loaded from the filesystem directly instead of through the
     /// in-memory filesystem layer.
     pub fn new<P: AsRef<Path>>(vfs: Vfs, start_path: P) -> Result<Self, ServeSessionError> {
+        let (session, _) = Self::with_options(vfs, start_path, SessionOptions::default())?;
+        Ok(session)
+    }
+
+    /// Like `new`, but with options that change how the project is
+    /// snapshotted. Errors are only returned when `collect_errors` is set.
+    pub fn with_options<P: AsRef<Path>>(
+        vfs: Vfs,
+        start_path: P,
+        options: SessionOptions,
+    ) -> Result<(Self, Vec<MiddlewareError>), ServeSessionError> {
         let start_path = start_path.as_ref();
         let start_time = Instant::now();
 
         log::trace!("Starting new ServeSession at path {}", start_path.display());
 
-        let project_path = if Project::is_project_file(start_path) {
-            Cow::Borrowed(start_path)
-        } else {
-            Cow::Owned(start_path.join("default.project.json"))
-        };
-
-        log::debug!("Loading project file from {}", project_path.display());
-
-        let mut root_project = match vfs.read(&project_path).with_not_found()? {
-            Some(contents) => Project::load_from_slice(&contents, &project_path)?,
-            None => {
-                return Err(ServeSessionError::NoProjectFound {
-                    path: project_path.to_path_buf(),
-                });
-            }
-        };
-        if root_project.name.is_none() {
-            if let Some(file_name) = project_path.file_name().and_then(|s| s.to_str()) {
-                if file_name == "default.project.json" {
-                    let folder_name = project_path
-                        .parent()
-                        .and_then(Path::file_name)
-                        .and_then(|s| s.to_str());
-                    if let Some(folder_name) = folder_name {
-                        root_project.name = Some(folder_name.to_string());
-                    } else {
-                        return Err(ServeSessionError::FolderNameInvalid {
-                            path: project_path.to_path_buf(),
-                        });
-                    }
-                } else if let Some(trimmed) = file_name.strip_suffix(".project.json") {
-                    root_project.name = Some(trimmed.to_string());
-                } else {
-                    return Err(ServeSessionError::ProjectNameInvalid {
-                        path: project_path.to_path_buf(),
-                    });
-                }
-            } else {
-                return Err(ServeSessionError::ProjectNameInvalid {
-                    path: project_path.to_path_buf(),
-                });
-            }
-        }
-        // Rebind it to make it no longer mutable
-        let root_project = root_project;
-
-        let mut tree = RojoTree::new(InstanceSnapshot::new());
+        let root_project = load_root_project(&vfs, start_path)?;
 
-        let root_id = tree.get_root_id();
+        // Files that haven't changed since they were last snapshotted don't
+        // need to be read again when the tree is updated.
+        let snapshot_cache = SnapshotCache::new();
 
-        let instance_context =
+        let mut instance_context =
             InstanceContext::with_emit_legacy_scripts(root_project.emit_legacy_scripts);
+        instance_context.set_snapshot_cache(snapshot_cache.clone());
+        instance_context.set_strict(options.strict);
+        if let Some(progress) = options.progress {
+            instance_context.set_progress(progress);
+        }
 
         log::trace!("Generating snapshot of instances from VFS");
-        let snapshot = snapshot_from_vfs(&instance_context, &vfs, start_path)?;
+        let (mut snapshot, errors) = if options.collect_errors {
+            snapshot_from_vfs_collecting_errors(&instance_context, &vfs, start_path)?
+        } else {
+            (
+                snapshot_from_vfs(&instance_context, &vfs, start_path)?,
+                Vec::new(),
+            )
+        };
 
-        log::trace!("Computing initial patch set");
-        let patch_set = compute_patch_set(snapshot, &tree, root_id);
+        if let Some(snapshot) = &mut snapshot {
+            resolve_ref_paths(snapshot)?;
+        }
 
-        log::trace!("Applying initial patch set");
-        apply_patch_set(&mut tree, patch_set);
+        let tree = tree_from_snapshot(snapshot);
 
         let session_id = SessionId::new();
         let message_queue = MessageQueue::new();
@@ -179,9 +177,10 @@ impl ServeSession {
             Arc::clone(&vfs),
             Arc::clone(&message_queue),
             tree_mutation_receiver,
+            snapshot_cache,
         );
 
-        Ok(Self {
+        let session = Self {
             change_processor,
             start_time,
             session_id,
@@ -190,7 +189,9 @@ impl ServeSession {
             message_queue,
             tree_mutation_sender,
             vfs,
-        })
+        };
+
+        Ok((session, errors))
     }
 
     pub fn tree_handle(&self) -> Arc<Mutex<RojoTree>> {
@@ -254,6 +255,73 @@ impl ServeSession {
     }
 }
 
+/// Loads the project at the given path, or the `default.project.json` inside
+/// of it if it's a folder, making sure it has a name.
+pub(crate) fn load_root_project(
+    vfs: &Vfs,
+    start_path: &Path,
+) -> Result<Project, ServeSessionError> {
+    let project_path = if Project::is_project_file(start_path) {
+        Cow::Borrowed(start_path)
+    } else {
+        Cow::Owned(start_path.join("default.project.json"))
+    };
+
+    log::debug!("Loading project file from {}", project_path.display());
+
+    let mut root_project = match vfs.read(&project_path).with_not_found()? {
+        Some(contents) => Project::load_from_slice(&contents, &project_path)?,
+        None => {
+            return Err(ServeSessionError::NoProjectFound {
+                path: project_path.to_path_buf(),
+            });
+        }
+    };
+    if root_project.name.is_none() {
+        if let Some(file_name) = project_path.file_name().and_then(|s| s.to_str()) {
+            if file_name == "default.project.json" {
+                let folder_name = project_path
+                    .parent()
+                    .and_then(Path::file_name)
+                    .and_then(|s| s.to_str());
+                if let Some(folder_name) = folder_name {
+                    root_project.name = Some(folder_name.to_string());
+                } else {
+                    return Err(ServeSessionError::FolderNameInvalid {
+                        path: project_path.to_path_buf(),
+                    });
+                }
+            } else if let Some(trimmed) = file_name.strip_suffix(".project.json") {
+                root_project.name = Some(trimmed.to_string());
+            } else {
+                return Err(ServeSessionError::ProjectNameInvalid {
+                    path: project_path.to_path_buf(),
+                });
+            }
+        } else {
+            return Err(ServeSessionError::ProjectNameInvalid {
+                path: project_path.to_path_buf(),
+            });
+        }
+    }
+
+    Ok(root_project)
+}
+
+/// Creates a tree containing the instances from a snapshot of a project.
+pub(crate) fn tree_from_snapshot(snapshot: Option<InstanceSnapshot>) -> RojoTree {
+    let mut tree = RojoTree::new(InstanceSnapshot::new());
+    let root_id = tree.get_root_id();
+
+    log::trace!("Computing initial patch set");
+    let patch_set = compute_patch_set(snapshot, &tree, root_id);
+
+    log::trace!("Applying initial patch set");
+    apply_patch_set(&mut tree, patch_set);
+
+    tree
+}
+
 #[derive(Debug, Error)]
 pub enum ServeSessionError {
     #[error(
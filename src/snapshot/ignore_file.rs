@@ -0,0 +1,214 @@
+//! Parsing and matching for `.rojoignore` files, which use a subset of the
+//! syntax of `.gitignore` files to exclude the contents of a directory.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use globset::{GlobBuilder, GlobMatcher};
+use serde::{Deserialize, Serialize};
+
+use crate::path_serializer;
+
+/// The name of the file read from each directory to exclude its children.
+pub const IGNORE_FILE_NAME: &str = ".rojoignore";
+
+/// The rules from a single `.rojoignore` file.
+///
+/// Patterns follow the rules of `.gitignore` files: blank lines and lines
+/// starting with `#` are skipped, a leading `!` re-includes paths excluded by
+/// earlier rules, a trailing `/` only matches directories, and patterns
+/// containing a `/` are relative to the directory holding the file instead of
+/// matching at any depth.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "IgnoreFileSource", into = "IgnoreFileSource")]
+pub struct IgnoreFile {
+    base_path: PathBuf,
+    rules: Vec<IgnoreRule>,
+}
+
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    line: String,
+    matcher: GlobMatcher,
+    negated: bool,
+    dir_only: bool,
+}
+
+impl IgnoreFile {
+    /// Parses the contents of a `.rojoignore` file located in `base_path`.
+    pub fn parse(base_path: PathBuf, contents: &str) -> anyhow::Result<Self> {
+        let mut rules = Vec::new();
+
+        for line in contents.lines() {
+            let rule = IgnoreRule::parse(line).with_context(|| {
+                format!("Invalid pattern {:?} in {}", line, base_path.display())
+            })?;
+
+            rules.extend(rule);
+        }
+
+        Ok(Self { base_path, rules })
+    }
+
+    /// Tells whether this file ignores the given path. Returns `None` if none
+    /// of its rules match the path.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative_path = path.strip_prefix(&self.base_path).ok()?;
+
+        // Like with .gitignore files, the last rule to match a path wins.
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| (is_dir || !rule.dir_only) && rule.matcher.is_match(relative_path))
+            .map(|rule| !rule.negated)
+    }
+}
+
+impl PartialEq for IgnoreFile {
+    fn eq(&self, other: &Self) -> bool {
+        self.base_path == other.base_path
+            && self
+                .rules
+                .iter()
+                .map(|rule| &rule.line)
+                .eq(other.rules.iter().map(|rule| &rule.line))
+    }
+}
+
+impl IgnoreRule {
+    fn parse(line: &str) -> anyhow::Result<Option<Self>> {
+        let line = line.trim_end();
+
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+
+        let (negated, pattern) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+
+        let (dir_only, pattern) = match pattern.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+
+        // Patterns without a separator can match at any depth, while the
+        // rest are anchored to the directory containing the ignore file.
+        let glob = match pattern.strip_prefix('/') {
+            Some(anchored) => anchored.to_owned(),
+            None if pattern.contains('/') => pattern.to_owned(),
+            None => format!("**/{}", pattern),
+        };
+
+        let matcher = GlobBuilder::new(&glob)
+            .literal_separator(true)
+            .build()?
+            .compile_matcher();
+
+        Ok(Some(Self {
+            line: line.to_owned(),
+            matcher,
+            negated,
+            dir_only,
+        }))
+    }
+}
+
+/// The serialized form of an `IgnoreFile`, which keeps the original patterns
+/// rather than their compiled matchers.
+#[derive(Serialize, Deserialize)]
+struct IgnoreFileSource {
+    #[serde(serialize_with = "path_serializer::serialize_absolute")]
+    base_path: PathBuf,
+    patterns: Vec<String>,
+}
+
+impl From<IgnoreFile> for IgnoreFileSource {
+    fn from(file: IgnoreFile) -> Self {
+        Self {
+            base_path: file.base_path,
+            patterns: file.rules.into_iter().map(|rule| rule.line).collect(),
+        }
+    }
+}
+
+impl TryFrom<IgnoreFileSource> for IgnoreFile {
+    type Error = anyhow::Error;
+
+    fn try_from(source: IgnoreFileSource) -> Result<Self, Self::Error> {
+        IgnoreFile::parse(source.base_path, &source.patterns.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ignore_file(contents: &str) -> IgnoreFile {
+        IgnoreFile::parse(PathBuf::from("/root"), contents).unwrap()
+    }
+
+    #[test]
+    fn unanchored_patterns() {
+        let file = ignore_file("# build output\n*.log\n\nnode_modules/\n");
+
+        assert_eq!(file.is_ignored(Path::new("/root/a.log"), false), Some(true));
+        assert_eq!(
+            file.is_ignored(Path::new("/root/deep/b.log"), false),
+            Some(true)
+        );
+        assert_eq!(
+            file.is_ignored(Path::new("/root/deep/node_modules"), true),
+            Some(true)
+        );
+        assert_eq!(
+            file.is_ignored(Path::new("/root/node_modules"), false),
+            None
+        );
+        assert_eq!(file.is_ignored(Path::new("/root/a.lua"), false), None);
+        assert_eq!(file.is_ignored(Path::new("/elsewhere/a.log"), false), None);
+    }
+
+    #[test]
+    fn anchored_patterns() {
+        let file = ignore_file("/top.lua\nsrc/*.lua\n");
+
+        assert_eq!(
+            file.is_ignored(Path::new("/root/top.lua"), false),
+            Some(true)
+        );
+        assert_eq!(
+            file.is_ignored(Path::new("/root/nested/top.lua"), false),
+            None
+        );
+        assert_eq!(
+            file.is_ignored(Path::new("/root/src/a.lua"), false),
+            Some(true)
+        );
+        assert_eq!(
+            file.is_ignored(Path::new("/root/src/nested/a.lua"), false),
+            None
+        );
+    }
+
+    #[test]
+    fn last_match_wins() {
+        let file = ignore_file("*.lua\n!keep.lua\n");
+
+        assert_eq!(file.is_ignored(Path::new("/root/a.lua"), false), Some(true));
+        assert_eq!(
+            file.is_ignored(Path::new("/root/keep.lua"), false),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let file = ignore_file("*.lua\n!keep.lua\n");
+        let encoded = serde_json::to_string(&file).unwrap();
+        let decoded: IgnoreFile = serde_json::from_str(&encoded).unwrap();
+
+        assert_eq!(file, decoded);
+    }
+}
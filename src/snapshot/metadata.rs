@@ -1,17 +1,24 @@
 use std::{
+    collections::BTreeMap,
     fmt,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
 use anyhow::Context;
+use rbx_dom_weak::types::Variant;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
+use super::{IgnoreFile, SnapshotCache, SnapshotProgress};
 use crate::{
     glob::Glob,
     path_serializer,
     project::ProjectNode,
-    snapshot_middleware::{emit_legacy_scripts_default, Middleware},
+    snapshot_middleware::{emit_legacy_scripts_default, ErrorCollector, Middleware, PathMatcher},
 };
 
 /// Rojo-specific metadata that can be associated with an instance or a snapshot
@@ -51,6 +58,12 @@ pub struct InstanceMetadata {
     #[serde(serialize_with = "path_serializer::serialize_vec_absolute")]
     pub relevant_paths: Vec<PathBuf>,
 
+    /// Ref properties that point to other instances by path, keyed by property
+    /// name. These are set on the instance by `resolve_ref_paths` once the
+    /// whole tree has been snapshotted.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub ref_paths: BTreeMap<String, String>,
+
     /// Contains information about this instance that should persist between
     /// snapshot invocations and is generally inherited.
     ///
@@ -66,6 +79,7 @@ impl InstanceMetadata {
             ignore_unknown_instances: false,
             instigating_source: None,
             relevant_paths: Vec::new(),
+            ref_paths: BTreeMap::new(),
             context: InstanceContext::default(),
         }
     }
@@ -107,19 +121,79 @@ impl Default for InstanceMetadata {
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct InstanceContext {
+    /// The `globIgnorePaths` of the projects this instance is in. Paths that
+    /// match any of them are skipped.
     #[serde(skip_serializing_if = "Vec::is_empty")]
-    pub path_ignore_rules: Arc<Vec<PathIgnoreRule>>,
+    pub path_ignore_rules: Arc<Vec<PathMatcher>>,
+    /// The `.rojoignore` files that apply to this instance, ordered from the
+    /// outermost directory to the innermost one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore_files: Arc<Vec<IgnoreFile>>,
     pub emit_legacy_scripts: bool,
+    /// Whether comments should be removed from the Source of scripts.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strip_comments: bool,
+    /// Whether changes to the Source of scripts that only move whitespace
+    /// around should be ignored when comparing snapshots to the tree.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ignore_script_whitespace: bool,
+    /// Whether properties generated by middleware should be dropped when they
+    /// match the default value for their class. Properties set explicitly,
+    /// like those from meta files or models, are always kept.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub prune_default_properties: bool,
+    /// Whether the children of directories should be snapshotted in parallel.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub parallel_snapshots: bool,
+    /// Whether problems that are usually only warned about or allowed, like
+    /// files whose names only differ by case or unknown properties in XML
+    /// models, should stop the snapshot instead.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict: bool,
+    /// The class given to directories, and to project nodes that don't have a
+    /// class of their own. Folder is used when this isn't set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_directory_class_name: Option<String>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub sync_rules: Vec<SyncRule>,
+    /// Properties given to every instance of a class that doesn't set them
+    /// itself, keyed by class name and then property name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub class_defaults: Arc<BTreeMap<String, BTreeMap<String, Variant>>>,
+    /// Lets snapshotting be stopped partway through. Snapshots that were
+    /// started without a token can't be cancelled.
+    #[serde(skip)]
+    pub cancellation: Option<CancellationToken>,
+    /// Snapshots of files that can be reused if the files haven't changed.
+    #[serde(skip)]
+    pub snapshot_cache: Option<SnapshotCache>,
+    /// Where errors from middleware go when they shouldn't stop snapshotting.
+    /// Without one, the first error stops the snapshot.
+    #[serde(skip)]
+    pub error_collector: Option<ErrorCollector>,
+    /// Counts the files that have been snapshotted, for reporting progress.
+    #[serde(skip)]
+    pub progress: Option<SnapshotProgress>,
 }
 
 impl InstanceContext {
     pub fn new() -> Self {
         Self {
             path_ignore_rules: Arc::new(Vec::new()),
+            ignore_files: Arc::new(Vec::new()),
             emit_legacy_scripts: emit_legacy_scripts_default().unwrap(),
+            strip_comments: false,
+            ignore_script_whitespace: false,
+            prune_default_properties: false,
+            parallel_snapshots: false,
+            strict: false,
+            default_directory_class_name: None,
             sync_rules: Vec::new(),
+            class_defaults: Arc::new(BTreeMap::new()),
+            cancellation: None,
+            snapshot_cache: None,
+            error_collector: None,
+            progress: None,
         }
     }
 
@@ -135,7 +209,7 @@ impl InstanceContext {
     /// Extend the list of ignore rules in the context with the given new rules.
     pub fn add_path_ignore_rules<I>(&mut self, new_rules: I)
     where
-        I: IntoIterator<Item = PathIgnoreRule>,
+        I: IntoIterator<Item = PathMatcher>,
         I::IntoIter: ExactSizeIterator,
     {
         let new_rules = new_rules.into_iter();
@@ -150,6 +224,22 @@ impl InstanceContext {
         rules.extend(new_rules);
     }
 
+    /// Add an ignore file that takes precedence over the ones already in
+    /// the context.
+    pub fn add_ignore_file(&mut self, ignore_file: IgnoreFile) {
+        Arc::make_mut(&mut self.ignore_files).push(ignore_file);
+    }
+
+    /// Tells whether any ignore file in the context excludes the given path.
+    /// Innermost ignore files take precedence.
+    pub fn is_ignored_by_files(&self, path: &Path, is_dir: bool) -> bool {
+        self.ignore_files
+            .iter()
+            .rev()
+            .find_map(|ignore_file| ignore_file.is_ignored(path, is_dir))
+            .unwrap_or(false)
+    }
+
     /// Extend the list of syncing rules in the context with the given new rules.
     pub fn add_sync_rules<I>(&mut self, new_rules: I)
     where
@@ -167,6 +257,74 @@ impl InstanceContext {
         self.emit_legacy_scripts = emit_legacy_scripts;
     }
 
+    pub fn set_strip_comments(&mut self, strip_comments: bool) {
+        self.strip_comments = strip_comments;
+    }
+
+    pub fn set_ignore_script_whitespace(&mut self, ignore_script_whitespace: bool) {
+        self.ignore_script_whitespace = ignore_script_whitespace;
+    }
+
+    pub fn set_prune_default_properties(&mut self, prune_default_properties: bool) {
+        self.prune_default_properties = prune_default_properties;
+    }
+
+    pub fn set_parallel_snapshots(&mut self, parallel_snapshots: bool) {
+        self.parallel_snapshots = parallel_snapshots;
+    }
+
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    pub fn set_default_directory_class_name(&mut self, class_name: String) {
+        self.default_directory_class_name = Some(class_name);
+    }
+
+    /// The class that directories turn into.
+    pub fn directory_class_name(&self) -> &str {
+        self.default_directory_class_name
+            .as_deref()
+            .unwrap_or("Folder")
+    }
+
+    /// Add default property values for a class, replacing any defaults already
+    /// in the context for the same properties.
+    pub fn add_class_defaults(&mut self, class_name: &str, defaults: BTreeMap<String, Variant>) {
+        if defaults.is_empty() {
+            return;
+        }
+
+        Arc::make_mut(&mut self.class_defaults)
+            .entry(class_name.to_owned())
+            .or_default()
+            .extend(defaults);
+    }
+
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) {
+        self.cancellation = Some(token);
+    }
+
+    pub fn set_snapshot_cache(&mut self, cache: SnapshotCache) {
+        self.snapshot_cache = Some(cache);
+    }
+
+    pub fn set_error_collector(&mut self, collector: ErrorCollector) {
+        self.error_collector = Some(collector);
+    }
+
+    pub fn set_progress(&mut self, progress: SnapshotProgress) {
+        self.progress = Some(progress);
+    }
+
+    /// Returns an error if snapshotting with this context has been cancelled.
+    pub fn check_cancelled(&self) -> Result<(), Cancelled> {
+        match &self.cancellation {
+            Some(token) if token.is_cancelled() => Err(Cancelled),
+            _ => Ok(()),
+        }
+    }
+
     /// Returns the middleware specified by the first sync rule that
     /// matches the provided path. This does not handle default syncing rules.
     pub fn get_user_sync_rule(&self, path: &Path) -> Option<&SyncRule> {
@@ -180,29 +338,39 @@ impl Default for InstanceContext {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct PathIgnoreRule {
-    /// The path that this glob is relative to. Since ignore globs are defined
-    /// in project files, this will generally be the folder containing the
-    /// project file that defined this glob.
-    #[serde(serialize_with = "path_serializer::serialize_absolute")]
-    pub base_path: PathBuf,
+/// A flag shared between a snapshot in progress and whoever started it, which
+/// can be set from any thread to stop the snapshot early.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
 
-    /// The actual glob that can be matched against the input path.
-    pub glob: Glob,
-}
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
 
-impl PathIgnoreRule {
-    pub fn passes<P: AsRef<Path>>(&self, path: P) -> bool {
-        let path = path.as_ref();
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
 
-        match path.strip_prefix(&self.base_path) {
-            Ok(suffix) => !self.glob.is_match(suffix),
-            Err(_) => true,
-        }
+// Tokens don't change what a snapshot contains, so they shouldn't make two
+// contexts compare differently.
+impl PartialEq for CancellationToken {
+    fn eq(&self, _other: &Self) -> bool {
+        true
     }
 }
 
+/// The error returned when a snapshot is stopped by its `CancellationToken`.
+#[derive(Debug, Error)]
+#[error("Snapshot was cancelled")]
+pub struct Cancelled;
+
+#[allow(clippy::large_enum_variant)]
 #[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub enum InstigatingSource {
     Path(#[serde(serialize_with = "path_serializer::serialize_absolute")] PathBuf),
@@ -259,6 +427,10 @@ pub struct SyncRule {
     /// If not specified, the file extension is the only thing cut off.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub suffix: Option<String>,
+    /// The class of instances created by this SyncRule, for middleware that
+    /// can create more than one class. Meta files can still override it.
+    #[serde(rename = "className", skip_serializing_if = "Option::is_none")]
+    pub class_name: Option<String>,
     /// The 'base' of the glob above, allowing it to be used
     /// relative to a path instead of absolute.
     #[serde(skip)]
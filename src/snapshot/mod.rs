@@ -48,18 +48,28 @@
 
 #![allow(dead_code)]
 
+mod ignore_file;
 mod instance_snapshot;
 mod metadata;
 mod patch;
 mod patch_apply;
 mod patch_compute;
+mod patch_diff;
+mod progress;
+mod script_classes;
+mod snapshot_cache;
 mod tree;
 
+pub use ignore_file::{IgnoreFile, IGNORE_FILE_NAME};
 pub use instance_snapshot::InstanceSnapshot;
 pub use metadata::*;
 pub use patch::*;
 pub use patch_apply::apply_patch_set;
 pub use patch_compute::compute_patch_set;
+pub use patch_diff::*;
+pub use progress::SnapshotProgress;
+pub use script_classes::{normalize_dom_script_classes, normalize_snapshot_script_classes};
+pub use snapshot_cache::SnapshotCache;
 pub use tree::*;
 
 #[cfg(test)]
@@ -8,11 +8,17 @@ use std::{
 
 use rbx_dom_weak::types::{Ref, Variant};
 
+use crate::snapshot_middleware::normalize_whitespace;
+
 use super::{
     patch::{PatchAdd, PatchSet, PatchUpdate},
     InstanceSnapshot, InstanceWithMeta, RojoTree,
 };
 
+/// An attribute that, when it's set on both an instance and a snapshot, pairs
+/// them up even if their names or positions don't match.
+const IDENTITY_ATTRIBUTE: &str = "Rojo_Id";
+
 #[profiling::function]
 pub fn compute_patch_set(snapshot: Option<InstanceSnapshot>, tree: &RojoTree, id: Ref) -> PatchSet {
     let mut patch_set = PatchSet::new();
@@ -87,8 +93,18 @@ fn compute_patch_set_internal(
         .get_instance(id)
         .expect("Instance did not exist in tree");
 
+    // Computing property patches can take the snapshot's metadata.
+    let ignore_unknown_instances = snapshot.metadata.ignore_unknown_instances;
+
     compute_property_patches(&mut snapshot, &instance, patch_set);
-    compute_children_patches(context, &mut snapshot, tree, id, patch_set);
+    compute_children_patches(
+        context,
+        &mut snapshot,
+        ignore_unknown_instances,
+        tree,
+        id,
+        patch_set,
+    );
 }
 
 fn compute_property_patches(
@@ -99,6 +115,8 @@ fn compute_property_patches(
     let mut visited_properties = HashSet::new();
     let mut changed_properties = HashMap::new();
 
+    let ignore_script_whitespace = snapshot.metadata.context.ignore_script_whitespace;
+
     let changed_name = if snapshot.name == instance.name() {
         None
     } else {
@@ -122,7 +140,13 @@ fn compute_property_patches(
 
         match instance.properties().get(&name) {
             Some(instance_value) => {
-                if &snapshot_value != instance_value {
+                let unchanged = if ignore_script_whitespace && name == "Source" {
+                    same_ignoring_whitespace(&snapshot_value, instance_value)
+                } else {
+                    &snapshot_value == instance_value
+                };
+
+                if !unchanged {
                     changed_properties.insert(name, Some(snapshot_value));
                 }
             }
@@ -160,6 +184,7 @@ fn compute_property_patches(
 fn compute_children_patches(
     context: &mut ComputePatchContext,
     snapshot: &mut InstanceSnapshot,
+    ignore_unknown_instances: bool,
     tree: &RojoTree,
     id: Ref,
     patch_set: &mut PatchSet,
@@ -169,40 +194,47 @@ fn compute_children_patches(
         .expect("Instance did not exist in tree");
 
     let instance_children = instance.children();
+    let snapshot_children = take(&mut snapshot.children);
 
     let mut paired_instances = vec![false; instance_children.len()];
-
-    for snapshot_child in take(&mut snapshot.children) {
-        let matching_instance =
-            instance_children
-                .iter()
-                .enumerate()
-                .find(|(instance_index, instance_child_id)| {
-                    if paired_instances[*instance_index] {
-                        return false;
-                    }
-
-                    let instance_child = tree
-                        .get_instance(**instance_child_id)
-                        .expect("Instance did not exist in tree");
-
-                    if snapshot_child.name == instance_child.name()
-                        && snapshot_child.class_name == instance_child.class_name()
-                    {
-                        paired_instances[*instance_index] = true;
-                        return true;
-                    }
-
-                    false
-                });
-
-        match matching_instance {
-            Some((_, instance_child_id)) => {
+    let mut matching_instances = vec![None; snapshot_children.len()];
+
+    // Children that have the same identity attribute are paired first, so
+    // that renaming or reordering them doesn't replace them. Everything else
+    // is paired by name and class.
+    pair_children(
+        &snapshot_children,
+        instance_children,
+        tree,
+        &mut paired_instances,
+        &mut matching_instances,
+        |snapshot_child, instance_child| {
+            let snapshot_identity = identity(&snapshot_child.properties);
+            snapshot_identity.is_some()
+                && snapshot_identity == identity(instance_child.properties())
+        },
+    );
+
+    pair_children(
+        &snapshot_children,
+        instance_children,
+        tree,
+        &mut paired_instances,
+        &mut matching_instances,
+        |snapshot_child, instance_child| {
+            snapshot_child.name == instance_child.name()
+                && snapshot_child.class_name == instance_child.class_name()
+        },
+    );
+
+    for (snapshot_child, matching_index) in snapshot_children.into_iter().zip(matching_instances) {
+        match matching_index {
+            Some(instance_index) => {
                 compute_patch_set_internal(
                     context,
                     snapshot_child,
                     tree,
-                    *instance_child_id,
+                    instance_children[instance_index],
                     patch_set,
                 );
             }
@@ -215,15 +247,89 @@ fn compute_children_patches(
         }
     }
 
+    // Instances that no snapshot describes are kept if the snapshot ignores
+    // unknown instances, like ones loaded from a place. Instances Rojo created
+    // from a file or project node are still removed once that source no longer
+    // describes them, so that deleting a file deletes its instance.
     for (instance_index, instance_child_id) in instance_children.iter().enumerate() {
         if paired_instances[instance_index] {
             continue;
         }
 
+        if ignore_unknown_instances {
+            let instance_child = tree
+                .get_instance(*instance_child_id)
+                .expect("Instance did not exist in tree");
+
+            if instance_child.metadata().instigating_source.is_none() {
+                continue;
+            }
+        }
+
         patch_set.removed_instances.push(*instance_child_id);
     }
 }
 
+/// Pairs each snapshot child that isn't paired yet with the first unpaired
+/// instance child that `matches` accepts.
+fn pair_children(
+    snapshot_children: &[InstanceSnapshot],
+    instance_children: &[Ref],
+    tree: &RojoTree,
+    paired_instances: &mut [bool],
+    matching_instances: &mut [Option<usize>],
+    matches: impl Fn(&InstanceSnapshot, &InstanceWithMeta) -> bool,
+) {
+    for (snapshot_child, matching_index) in snapshot_children.iter().zip(matching_instances) {
+        if matching_index.is_some() {
+            continue;
+        }
+
+        *matching_index =
+            instance_children
+                .iter()
+                .enumerate()
+                .position(|(instance_index, instance_child_id)| {
+                    if paired_instances[instance_index] {
+                        return false;
+                    }
+
+                    let instance_child = tree
+                        .get_instance(*instance_child_id)
+                        .expect("Instance did not exist in tree");
+
+                    matches(snapshot_child, &instance_child)
+                });
+
+        if let Some(instance_index) = *matching_index {
+            paired_instances[instance_index] = true;
+        }
+    }
+}
+
+/// Compares two script sources, treating them as the same if they only differ
+/// in whitespace that doesn't change what the script does.
+fn same_ignoring_whitespace(snapshot_value: &Variant, instance_value: &Variant) -> bool {
+    match (snapshot_value, instance_value) {
+        (Variant::String(snapshot_source), Variant::String(instance_source)) => {
+            snapshot_source == instance_source
+                || normalize_whitespace(snapshot_source) == normalize_whitespace(instance_source)
+        }
+        _ => snapshot_value == instance_value,
+    }
+}
+
+/// Finds the value of the identity attribute in a set of properties.
+fn identity(properties: &HashMap<String, Variant>) -> Option<&str> {
+    match properties.get("Attributes") {
+        Some(Variant::Attributes(attributes)) => match attributes.get(IDENTITY_ATTRIBUTE) {
+            Some(Variant::String(identity)) => Some(identity.as_str()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -231,6 +337,9 @@ mod test {
     use std::borrow::Cow;
 
     use maplit::hashmap;
+    use rbx_dom_weak::types::Attributes;
+
+    use crate::snapshot::{InstanceContext, InstanceMetadata};
 
     /// This test makes sure that rewriting refs in instance update patches to
     /// instances that already exists works. We should be able to correlate the
@@ -246,7 +355,7 @@ mod test {
         // addition of a prop named Self, which is a self-referential Ref.
         let snapshot_id = Ref::new();
         let snapshot = InstanceSnapshot {
-            snapshot_id: snapshot_id,
+            snapshot_id,
             properties: hashmap! {
                 "Self".to_owned() => Variant::Ref(snapshot_id),
             },
@@ -288,7 +397,7 @@ mod test {
         // This patch describes the existing instance with a new child added.
         let snapshot_id = Ref::new();
         let snapshot = InstanceSnapshot {
-            snapshot_id: snapshot_id,
+            snapshot_id,
             children: vec![InstanceSnapshot {
                 properties: hashmap! {
                     "Self".to_owned() => Variant::Ref(snapshot_id),
@@ -329,4 +438,84 @@ mod test {
 
         assert_eq!(patch_set, expected_patch_set);
     }
+
+    /// Children with an identity attribute should be matched up by it, even
+    /// when they've been reordered and renamed.
+    #[test]
+    fn identity_attribute_pairs_children() {
+        fn child(name: &'static str, identity: &str) -> InstanceSnapshot {
+            let mut attributes = Attributes::new();
+            attributes.insert(IDENTITY_ATTRIBUTE.to_owned(), identity.into());
+
+            InstanceSnapshot::new()
+                .name(name)
+                .class_name("Folder")
+                .property("Attributes", attributes)
+        }
+
+        let tree = RojoTree::new(
+            InstanceSnapshot::new()
+                .name("root")
+                .class_name("Folder")
+                .children(vec![child("A", "a"), child("B", "b")]),
+        );
+
+        let root_id = tree.get_root_id();
+        let root = tree.get_instance(root_id).unwrap();
+        let b_id = root.children()[1];
+
+        let snapshot = InstanceSnapshot::new()
+            .name("root")
+            .class_name("Folder")
+            .children(vec![child("Renamed", "b"), child("A", "a")]);
+
+        let patch_set = compute_patch_set(Some(snapshot), &tree, root_id);
+
+        let expected_patch_set = PatchSet {
+            updated_instances: vec![PatchUpdate {
+                id: b_id,
+                changed_name: Some("Renamed".to_owned()),
+                changed_class_name: None,
+                changed_properties: HashMap::new(),
+                changed_metadata: None,
+            }],
+            added_instances: Vec::new(),
+            removed_instances: Vec::new(),
+        };
+
+        assert_eq!(patch_set, expected_patch_set);
+    }
+
+    /// Re-indenting a script shouldn't change it when the context says to
+    /// ignore whitespace, but should otherwise.
+    #[test]
+    fn ignore_script_whitespace() {
+        let script = |source: &str, ignore_script_whitespace: bool| {
+            let mut context = InstanceContext::new();
+            context.set_ignore_script_whitespace(ignore_script_whitespace);
+
+            InstanceSnapshot::new()
+                .name("Script")
+                .class_name("ModuleScript")
+                .property("Source", source)
+                .metadata(InstanceMetadata::new().context(&context))
+        };
+
+        let tree = RojoTree::new(script("return function()\n\tprint(\"a  b\")\nend\n", true));
+        let root_id = tree.get_root_id();
+
+        let reindented = "return function()\n    print(\"a  b\")\nend";
+        let patch_set = compute_patch_set(Some(script(reindented, true)), &tree, root_id);
+        assert_eq!(patch_set, PatchSet::new());
+
+        let string_changed = "return function()\n\tprint(\"a b\")\nend\n";
+        let patch_set = compute_patch_set(Some(script(string_changed, true)), &tree, root_id);
+        assert_eq!(patch_set.updated_instances.len(), 1);
+
+        // The metadata changes too, but so should the Source.
+        let patch_set = compute_patch_set(Some(script(reindented, false)), &tree, root_id);
+        assert!(patch_set.updated_instances[0]
+            .changed_properties
+            .contains_key("Source"));
+    }
 }
@@ -0,0 +1,160 @@
+//! Defines a description of a patch set that can be serialized for tools
+//! outside of Rojo.
+
+use std::collections::BTreeMap;
+
+use rbx_dom_weak::types::{Ref, Variant};
+use serde::{Deserialize, Serialize};
+
+use super::{InstanceSnapshot, PatchSet, RojoTree};
+
+/// A structured description of the changes a `PatchSet` would make to a tree.
+///
+/// Unlike `PatchSet`, this includes the previous values of everything that
+/// changes and leaves out Rojo-specific metadata, so it's suitable for
+/// showing to users or handing to other programs. Instance IDs are written as
+/// the same strings Rojo uses for them everywhere else.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchDiff {
+    pub added: Vec<AddedInstance>,
+    pub removed: Vec<String>,
+    pub updated: Vec<UpdatedInstance>,
+}
+
+impl PatchDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.updated.is_empty()
+    }
+}
+
+/// An instance that would be added, along with all of its descendants.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddedInstance {
+    /// The existing instance this one would be added to. This is only set on
+    /// the topmost added instance.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    pub name: String,
+    pub class_name: String,
+    pub properties: BTreeMap<String, Variant>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<AddedInstance>,
+}
+
+/// An existing instance whose name, class, or properties would change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatedInstance {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changed_name: Option<ValueChange<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changed_class_name: Option<ValueChange<String>>,
+
+    /// Contains all changed properties. A property that doesn't exist before
+    /// or after the change has a value of `None` on that side.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub changed_properties: BTreeMap<String, ValueChange<Option<Variant>>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValueChange<T> {
+    pub old: T,
+    pub new: T,
+}
+
+/// Describes the changes that applying `patch_set` to `tree` would make. This
+/// must be called before the patch set is applied, since the previous values
+/// are read from the tree.
+pub fn diff_patch_set(tree: &RojoTree, patch_set: &PatchSet) -> PatchDiff {
+    let added = patch_set
+        .added_instances
+        .iter()
+        .map(|add| describe_added(Some(add.parent_id), &add.instance))
+        .collect();
+
+    let removed = patch_set
+        .removed_instances
+        .iter()
+        .map(|id| id.to_string())
+        .collect();
+
+    let mut updated = Vec::new();
+
+    for update in &patch_set.updated_instances {
+        let instance = match tree.get_instance(update.id) {
+            Some(instance) => instance,
+            None => {
+                log::warn!(
+                    "Patch set updates instance {}, which is not in the tree",
+                    update.id
+                );
+                continue;
+            }
+        };
+
+        let changed_name = update.changed_name.as_ref().map(|name| ValueChange {
+            old: instance.name().to_owned(),
+            new: name.clone(),
+        });
+
+        let changed_class_name = update
+            .changed_class_name
+            .as_ref()
+            .map(|class_name| ValueChange {
+                old: instance.class_name().to_owned(),
+                new: class_name.clone(),
+            });
+
+        let changed_properties: BTreeMap<_, _> = update
+            .changed_properties
+            .iter()
+            .map(|(key, value)| {
+                let change = ValueChange {
+                    old: instance.properties().get(key).cloned(),
+                    new: value.clone(),
+                };
+
+                (key.clone(), change)
+            })
+            .collect();
+
+        // Updates that only touch metadata aren't visible to anyone else.
+        if changed_name.is_none() && changed_class_name.is_none() && changed_properties.is_empty() {
+            continue;
+        }
+
+        updated.push(UpdatedInstance {
+            id: update.id.to_string(),
+            changed_name,
+            changed_class_name,
+            changed_properties,
+        });
+    }
+
+    PatchDiff {
+        added,
+        removed,
+        updated,
+    }
+}
+
+fn describe_added(parent: Option<Ref>, snapshot: &InstanceSnapshot) -> AddedInstance {
+    AddedInstance {
+        parent: parent.map(|id| id.to_string()),
+        name: snapshot.name.to_string(),
+        class_name: snapshot.class_name.to_string(),
+        properties: snapshot
+            .properties
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect(),
+        children: snapshot
+            .children
+            .iter()
+            .map(|child| describe_added(None, child))
+            .collect(),
+    }
+}
@@ -0,0 +1,84 @@
+use std::sync::{
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+    Arc,
+};
+
+/// Counts the files that have been snapshotted so far, so that something else
+/// can report how far along a long snapshot is.
+///
+/// Like `SnapshotCache`, every copy of the counter shares the same counts.
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotProgress {
+    files: Arc<AtomicUsize>,
+    bytes: Arc<AtomicU64>,
+}
+
+impl SnapshotProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that a file of the given length has been snapshotted.
+    pub fn record_file(&self, len: u64) {
+        self.files.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(len, Ordering::Relaxed);
+    }
+
+    /// The number of files snapshotted so far.
+    pub fn files(&self) -> usize {
+        self.files.load(Ordering::Relaxed)
+    }
+
+    /// The combined length of every file snapshotted so far.
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+}
+
+// Reporting progress doesn't change what a snapshot contains.
+impl PartialEq for SnapshotProgress {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path::Path;
+
+    use memofs::{InMemoryFs, Vfs, VfsSnapshot};
+
+    use crate::{snapshot::InstanceContext, snapshot_middleware::snapshot_from_vfs};
+
+    #[test]
+    fn counts_files() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/root",
+            VfsSnapshot::dir([
+                ("a.lua", VfsSnapshot::file("return 'a'")),
+                (
+                    "nested",
+                    VfsSnapshot::dir([
+                        ("b.txt", VfsSnapshot::file("hello")),
+                        ("c.txt", VfsSnapshot::file("world!")),
+                    ]),
+                ),
+            ]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let progress = SnapshotProgress::new();
+        let mut context = InstanceContext::default();
+        context.set_progress(progress.clone());
+
+        snapshot_from_vfs(&context, &vfs, Path::new("/root")).unwrap();
+
+        assert_eq!(progress.files(), 3);
+        assert_eq!(progress.bytes(), 21);
+    }
+}
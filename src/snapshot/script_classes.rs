@@ -0,0 +1,86 @@
+//! Maps scripts between legacy classes, where client scripts are
+//! `LocalScript`s, and the classes that use `RunContext` instead, so that trees
+//! made with either setting of `emitLegacyScripts` can be compared.
+
+use std::collections::HashMap;
+
+use rbx_dom_weak::{
+    types::{Enum, Variant},
+    WeakDom,
+};
+
+use super::{InstanceContext, InstanceSnapshot};
+
+/// Changes every script in the tree to the class and `RunContext` that Rojo
+/// would create for it with the given context.
+pub fn normalize_dom_script_classes(context: &InstanceContext, dom: &mut WeakDom) {
+    let mut to_visit = vec![dom.root_ref()];
+
+    while let Some(id) = to_visit.pop() {
+        let instance = dom.get_by_ref_mut(id).unwrap();
+
+        if let Some(class_name) = normalize(context, &instance.class, &mut instance.properties) {
+            instance.class = class_name.to_owned();
+        }
+
+        to_visit.extend_from_slice(instance.children());
+    }
+}
+
+/// Changes every script in the snapshot to the class and `RunContext` that Rojo
+/// would create for it with the given context. Snapshots usually only need
+/// this when they contain projects with a different `emitLegacyScripts`.
+pub fn normalize_snapshot_script_classes(
+    context: &InstanceContext,
+    snapshot: &mut InstanceSnapshot,
+) {
+    if let Some(class_name) = normalize(context, &snapshot.class_name, &mut snapshot.properties) {
+        snapshot.class_name = class_name.into();
+    }
+
+    for child in &mut snapshot.children {
+        normalize_snapshot_script_classes(context, child);
+    }
+}
+
+/// Updates the properties of a script and returns its new class name, if it
+/// needs to change.
+fn normalize(
+    context: &InstanceContext,
+    class_name: &str,
+    properties: &mut HashMap<String, Variant>,
+) -> Option<&'static str> {
+    let run_context_enums = &rbx_reflection_database::get()
+        .enums
+        .get("RunContext")
+        .expect("Unable to get RunContext enums!")
+        .items;
+    let run_context = |name: &str| Variant::Enum(Enum::from_u32(run_context_enums[name]));
+
+    match (context.emit_legacy_scripts, class_name) {
+        (false, "LocalScript") => {
+            properties.insert("RunContext".to_owned(), run_context("Client"));
+            Some("Script")
+        }
+        (false, "Script") => {
+            if properties.get("RunContext") == Some(&run_context("Legacy")) {
+                properties.insert("RunContext".to_owned(), run_context("Server"));
+            }
+            None
+        }
+        (true, "Script") => {
+            let current = properties.get("RunContext");
+
+            if current == Some(&run_context("Client")) {
+                properties.remove("RunContext");
+                Some("LocalScript")
+            } else {
+                if current == Some(&run_context("Server")) {
+                    properties.insert("RunContext".to_owned(), run_context("Legacy"));
+                }
+                None
+            }
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,190 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use memofs::Metadata;
+
+use super::{InstanceContext, InstanceSnapshot};
+
+/// Remembers the snapshots of files so that files that haven't changed don't
+/// need to be read and parsed again.
+///
+/// Entries are keyed by path and are only reused while the file's modified
+/// time and length stay the same. Files whose backend doesn't report a
+/// modified time are never cached. Because a file's snapshot can depend on
+/// other files, like adjacent meta files, entries should also be invalidated
+/// whenever something changes on the filesystem.
+///
+/// The cache is internally reference counted, so every copy of it shares the
+/// same entries.
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotCache {
+    entries: Arc<Mutex<HashMap<PathBuf, CacheEntry>>>,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    modified: SystemTime,
+    len: u64,
+    context: InstanceContext,
+    snapshot: Option<InstanceSnapshot>,
+}
+
+impl SnapshotCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the snapshot cached for the given path, as long as the file
+    /// hasn't changed since and it was created with the same context.
+    pub fn get(
+        &self,
+        path: &Path,
+        meta: &Metadata,
+        context: &InstanceContext,
+    ) -> Option<Option<InstanceSnapshot>> {
+        let modified = meta.modified()?;
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.get(path)?;
+
+        if entry.modified == modified && entry.len == meta.len() && entry.context == *context {
+            Some(entry.snapshot.clone())
+        } else {
+            None
+        }
+    }
+
+    pub fn insert(
+        &self,
+        path: &Path,
+        meta: &Metadata,
+        context: &InstanceContext,
+        snapshot: Option<InstanceSnapshot>,
+    ) {
+        let modified = match meta.modified() {
+            Some(modified) => modified,
+            None => return,
+        };
+
+        self.entries.lock().unwrap().insert(
+            path.to_path_buf(),
+            CacheEntry {
+                modified,
+                len: meta.len(),
+                context: context.clone(),
+                snapshot,
+            },
+        );
+    }
+
+    /// Removes every entry that could be affected by a change to the given
+    /// path: the path itself, anything inside of it, and any snapshot that
+    /// lists it as a relevant path.
+    pub fn invalidate(&self, changed_path: &Path) {
+        self.entries.lock().unwrap().retain(|path, entry| {
+            if path.starts_with(changed_path) {
+                return false;
+            }
+
+            match &entry.snapshot {
+                Some(snapshot) => !snapshot
+                    .metadata
+                    .relevant_paths
+                    .iter()
+                    .any(|relevant| relevant == changed_path),
+                None => true,
+            }
+        });
+    }
+}
+
+// Like `CancellationToken`, the cache doesn't change what a snapshot contains.
+impl PartialEq for SnapshotCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use memofs::{InMemoryFs, Vfs, VfsSnapshot};
+
+    use crate::snapshot_middleware::snapshot_from_vfs;
+
+    fn tree() -> VfsSnapshot {
+        VfsSnapshot::dir([
+            ("a.lua", VfsSnapshot::file("return 'a'")),
+            ("b.txt", VfsSnapshot::file("b")),
+            (
+                "nested",
+                VfsSnapshot::dir([
+                    ("c.lua", VfsSnapshot::file("return 'c'")),
+                    ("c.meta.json", VfsSnapshot::file("{}")),
+                ]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn unchanged_files_are_not_read() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/root", tree()).unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let mut context = InstanceContext::default();
+        context.set_snapshot_cache(SnapshotCache::new());
+
+        let first = snapshot_from_vfs(&context, &vfs, Path::new("/root")).unwrap();
+        let reads = imfs.read_count();
+        assert!(reads > 0);
+
+        let second = snapshot_from_vfs(&context, &vfs, Path::new("/root")).unwrap();
+        assert_eq!(imfs.read_count(), reads);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn changed_files_are_read() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/root", tree()).unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let cache = SnapshotCache::new();
+        let mut context = InstanceContext::default();
+        context.set_snapshot_cache(cache.clone());
+
+        snapshot_from_vfs(&context, &vfs, Path::new("/root")).unwrap();
+
+        // Writing a file gives it a new modified time.
+        vfs.write("/root/a.lua", "return 'changed'").unwrap();
+        let reads = imfs.read_count();
+        let snapshot = snapshot_from_vfs(&context, &vfs, Path::new("/root"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(imfs.read_count(), reads + 1);
+        assert_eq!(
+            snapshot.children[0].properties["Source"],
+            "return 'changed'".into()
+        );
+
+        // Meta files are a relevant path of the file they apply to.
+        cache.invalidate(Path::new("/root/nested/c.meta.json"));
+        let reads = imfs.read_count();
+        snapshot_from_vfs(&context, &vfs, Path::new("/root")).unwrap();
+        assert_eq!(imfs.read_count(), reads + 2);
+
+        // Snapshots from other contexts aren't reused.
+        let mut other_context = context.clone();
+        other_context.set_strip_comments(true);
+        let reads = imfs.read_count();
+        snapshot_from_vfs(&other_context, &vfs, Path::new("/root")).unwrap();
+        assert_eq!(imfs.read_count(), reads + 4);
+    }
+}
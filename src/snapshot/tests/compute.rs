@@ -1,12 +1,18 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, path::Path};
 
 use insta::assert_yaml_snapshot;
 use maplit::hashmap;
 
-use rbx_dom_weak::types::Ref;
+use rbx_dom_weak::{
+    types::{Enum, Ref},
+    InstanceBuilder, WeakDom,
+};
 use rojo_insta_ext::RedactionMap;
 
-use crate::snapshot::{compute_patch_set, InstanceSnapshot, RojoTree};
+use crate::snapshot::{
+    compute_patch_set, normalize_dom_script_classes, normalize_snapshot_script_classes,
+    InstanceContext, InstanceMetadata, InstanceSnapshot, RojoTree,
+};
 
 #[test]
 fn set_name_and_class_name() {
@@ -146,6 +152,127 @@ fn remove_child() {
     assert_yaml_snapshot!(patch_value);
 }
 
+#[test]
+fn ignore_unknown_instances_per_node() {
+    let mut tree = empty_tree();
+    let root_id = tree.get_root_id();
+
+    let kept_parent = tree.insert_instance(
+        root_id,
+        InstanceSnapshot::new().name("Kept").class_name("Folder"),
+    );
+    let kept_child = tree.insert_instance(kept_parent, InstanceSnapshot::new().name("Unknown"));
+
+    let removed_parent = tree.insert_instance(
+        root_id,
+        InstanceSnapshot::new().name("Removed").class_name("Folder"),
+    );
+    let removed_child =
+        tree.insert_instance(removed_parent, InstanceSnapshot::new().name("Unknown"));
+
+    let folder = |name: &'static str, ignore_unknown_instances: bool| InstanceSnapshot {
+        snapshot_id: Ref::none(),
+        metadata: InstanceMetadata::new().ignore_unknown_instances(ignore_unknown_instances),
+        name: Cow::Borrowed(name),
+        class_name: Cow::Borrowed("Folder"),
+        properties: Default::default(),
+        children: Vec::new(),
+    };
+
+    let snapshot = InstanceSnapshot {
+        snapshot_id: Ref::none(),
+        metadata: Default::default(),
+        name: Cow::Borrowed("ROOT"),
+        class_name: Cow::Borrowed("ROOT"),
+        properties: Default::default(),
+        children: vec![folder("Kept", true), folder("Removed", false)],
+    };
+
+    let patch_set = compute_patch_set(Some(snapshot), &tree, root_id);
+
+    assert!(!patch_set.removed_instances.contains(&kept_child));
+    assert_eq!(patch_set.removed_instances, vec![removed_child]);
+}
+
+#[test]
+fn ignore_unknown_instances_removes_sourced_instances() {
+    let mut tree = empty_tree();
+    let root_id = tree.get_root_id();
+    let child_id = tree.insert_instance(
+        root_id,
+        InstanceSnapshot::new()
+            .name("Deleted")
+            .metadata(InstanceMetadata::new().instigating_source(Path::new("/deleted.lua"))),
+    );
+
+    let snapshot = InstanceSnapshot {
+        snapshot_id: Ref::none(),
+        metadata: InstanceMetadata::new().ignore_unknown_instances(true),
+        name: Cow::Borrowed("ROOT"),
+        class_name: Cow::Borrowed("ROOT"),
+        properties: Default::default(),
+        children: Vec::new(),
+    };
+
+    let patch_set = compute_patch_set(Some(snapshot), &tree, root_id);
+
+    assert_eq!(patch_set.removed_instances, vec![child_id]);
+}
+
+/// A LocalScript from a place file should line up with a client script from a
+/// project that doesn't emit legacy scripts, and the other way around.
+#[test]
+fn legacy_script_classes() {
+    let client_script = |class_name: &'static str, run_context: Option<u32>| {
+        let mut snapshot = InstanceSnapshot::new()
+            .name("Client")
+            .class_name(class_name)
+            .property("Source", "print(1)");
+
+        if let Some(run_context) = run_context {
+            snapshot = snapshot.property("RunContext", Enum::from_u32(run_context));
+        }
+
+        snapshot
+    };
+
+    // RunContext.Client is 2.
+    let cases = [
+        (false, ("LocalScript", None), ("Script", Some(2))),
+        (true, ("Script", Some(2)), ("Script", Some(2))),
+    ];
+
+    for (emit_legacy_scripts, (input_class, input_context), (project_class, project_context)) in
+        cases
+    {
+        let context = InstanceContext::with_emit_legacy_scripts(Some(emit_legacy_scripts));
+
+        let input = client_script(input_class, input_context);
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel").with_name("ROOT"));
+        let root = dom.root_ref();
+        dom.insert(
+            root,
+            InstanceBuilder::new(input.class_name.as_ref())
+                .with_name("Client")
+                .with_properties(input.properties),
+        );
+        normalize_dom_script_classes(&context, &mut dom);
+        let tree = RojoTree::from_dom(dom);
+
+        let mut snapshot = InstanceSnapshot::new()
+            .name("ROOT")
+            .class_name("DataModel")
+            .children(vec![client_script(project_class, project_context)]);
+        normalize_snapshot_script_classes(&context, &mut snapshot);
+
+        let patch_set = compute_patch_set(Some(snapshot), &tree, tree.get_root_id());
+
+        assert!(patch_set.added_instances.is_empty());
+        assert!(patch_set.removed_instances.is_empty());
+        assert!(patch_set.updated_instances.is_empty());
+    }
+}
+
 fn empty_tree() -> RojoTree {
     RojoTree::new(InstanceSnapshot::new().name("ROOT").class_name("ROOT"))
 }
@@ -0,0 +1,57 @@
+use insta::assert_yaml_snapshot;
+
+use rojo_insta_ext::RedactionMap;
+
+use crate::snapshot::{compute_patch_set, diff_patch_set, InstanceSnapshot, RojoTree};
+
+#[test]
+fn describe_changes() {
+    let mut redactions = RedactionMap::new();
+
+    let mut tree = RojoTree::new(
+        InstanceSnapshot::new()
+            .name("ROOT")
+            .class_name("Folder")
+            .property("Kept", "Same")
+            .property("Changed", "Before")
+            .property("Removed", "Gone soon"),
+    );
+    let root_id = tree.get_root_id();
+    redactions.intern(root_id);
+
+    let removed_id = tree.insert_instance(root_id, InstanceSnapshot::new().name("Removed"));
+    redactions.intern(removed_id);
+
+    let snapshot = InstanceSnapshot::new()
+        .name("Renamed")
+        .class_name("Folder")
+        .property("Kept", "Same")
+        .property("Changed", "After")
+        .property("Added", 5.0)
+        .children(vec![InstanceSnapshot::new()
+            .name("New")
+            .class_name("Folder")
+            .children(vec![InstanceSnapshot::new()
+                .name("Nested")
+                .class_name("StringValue")
+                .property("Value", "Hello")])]);
+
+    let patch_set = compute_patch_set(Some(snapshot), &tree, root_id);
+    let diff = diff_patch_set(&tree, &patch_set);
+    let diff_value = redactions.redacted_yaml(diff);
+
+    assert_yaml_snapshot!(diff_value);
+}
+
+#[test]
+fn skip_metadata_only_updates() {
+    let tree = RojoTree::new(InstanceSnapshot::new().name("ROOT").class_name("Folder"));
+
+    let mut snapshot = InstanceSnapshot::new().name("ROOT").class_name("Folder");
+    snapshot.metadata.ignore_unknown_instances = true;
+
+    let patch_set = compute_patch_set(Some(snapshot), &tree, tree.get_root_id());
+    assert_eq!(patch_set.updated_instances.len(), 1);
+
+    assert!(diff_patch_set(&tree, &patch_set).is_empty());
+}
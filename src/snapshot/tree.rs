@@ -58,15 +58,44 @@ impl RojoTree {
         tree
     }
 
+    /// Creates a tree from instances that didn't come from Rojo, like those
+    /// loaded from a place file. Every instance keeps its referent and is
+    /// given empty metadata.
+    pub fn from_dom(dom: WeakDom) -> RojoTree {
+        let mut ids = Vec::new();
+        let mut to_visit = vec![dom.root_ref()];
+
+        while let Some(id) = to_visit.pop() {
+            ids.push(id);
+            to_visit.extend_from_slice(dom.get_by_ref(id).unwrap().children());
+        }
+
+        let mut tree = RojoTree {
+            inner: dom,
+            metadata_map: HashMap::new(),
+            path_to_ids: MultiMap::new(),
+        };
+
+        for id in ids {
+            tree.insert_metadata(id, InstanceMetadata::new());
+        }
+
+        tree
+    }
+
     pub fn inner(&self) -> &WeakDom {
         &self.inner
     }
 
+    pub fn into_inner(self) -> WeakDom {
+        self.inner
+    }
+
     pub fn get_root_id(&self) -> Ref {
         self.inner.root_ref()
     }
 
-    pub fn get_instance(&self, id: Ref) -> Option<InstanceWithMeta> {
+    pub fn get_instance(&self, id: Ref) -> Option<InstanceWithMeta<'_>> {
         if let Some(instance) = self.inner.get_by_ref(id) {
             let metadata = self.metadata_map.get(&id).unwrap();
 
@@ -76,7 +105,7 @@ impl RojoTree {
         }
     }
 
-    pub fn get_instance_mut(&mut self, id: Ref) -> Option<InstanceWithMetaMut> {
+    pub fn get_instance_mut(&mut self, id: Ref) -> Option<InstanceWithMetaMut<'_>> {
         if let Some(instance) = self.inner.get_by_ref_mut(id) {
             let metadata = self.metadata_map.get_mut(&id).unwrap();
 
@@ -153,6 +182,23 @@ impl RojoTree {
         RojoDescendants { queue, tree: self }
     }
 
+    /// Finds an instance by the names of its ancestors, separated by periods
+    /// and starting from the children of the root, like
+    /// `ReplicatedStorage.Shared`.
+    pub fn find_by_path(&self, path: &str) -> Option<Ref> {
+        let mut current = self.get_root_id();
+
+        for name in path.split('.') {
+            let instance = self.get_instance(current)?;
+            current =
+                instance.children().iter().copied().find(|&child| {
+                    self.get_instance(child).map(|child| child.name()) == Some(name)
+                })?;
+        }
+
+        Some(current)
+    }
+
     pub fn get_ids_at_path(&self, path: &Path) -> &[Ref] {
         self.path_to_ids.get(path)
     }
@@ -0,0 +1,197 @@
+use std::path::{Component, Path};
+
+use anyhow::bail;
+use memofs::{IoResultExt, Vfs};
+use rbx_dom_weak::types::{Attributes, Variant};
+
+use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
+
+use super::meta_file::AdjacentMetadata;
+
+/// The attribute that holds the path of the file an asset instance came from.
+const ASSET_PATH_ATTRIBUTE: &str = "Rojo_AssetPath";
+
+/// Turns a binary asset, like an image or a sound, into a placeholder
+/// instance that records where the asset lives.
+///
+/// The instance is a Folder unless its adjacent meta file or its sync rule sets
+/// `className`.
+/// Its path, relative to the project that declared the sync rule for it, is
+/// stored in the `Rojo_AssetPath` attribute so that later steps can swap it
+/// out for a real asset ID.
+pub fn snapshot_asset(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    // Asset files are never read, but they still need to exist.
+    vfs.metadata(path)?;
+
+    let meta_path = path.with_file_name(format!("{}.meta.json", name));
+
+    let mut metadata = match vfs.read(&meta_path).with_not_found()? {
+        Some(meta_contents) => Some(AdjacentMetadata::from_slice(
+            &meta_contents,
+            meta_path.clone(),
+        )?),
+        None => None,
+    };
+
+    let rule = context.get_user_sync_rule(path);
+
+    let class_name = metadata
+        .as_mut()
+        .and_then(|meta| meta.class_name.take())
+        .or_else(|| rule.and_then(|rule| rule.class_name.clone()))
+        .unwrap_or_else(|| "Folder".to_owned());
+
+    if !rbx_reflection_database::get()
+        .classes
+        .contains_key(class_name.as_str())
+    {
+        bail!(
+            "className is set to {} for {}, which is not a known class",
+            class_name,
+            path.display()
+        );
+    }
+
+    let base_path = rule
+        .map(|rule| rule.base_path.as_path())
+        .unwrap_or_else(|| Path::new(""));
+
+    let relative_path = path.strip_prefix(base_path).unwrap_or(path);
+
+    let mut attributes = Attributes::new();
+    attributes.insert(
+        ASSET_PATH_ATTRIBUTE.to_owned(),
+        Variant::String(to_slash_path(relative_path)),
+    );
+
+    let mut snapshot = InstanceSnapshot::new()
+        .name(name)
+        .class_name(class_name)
+        .property("Attributes", attributes)
+        .metadata(
+            InstanceMetadata::new()
+                .instigating_source(path)
+                .relevant_paths(vec![path.to_path_buf(), meta_path])
+                .context(context),
+        );
+
+    if let Some(mut metadata) = metadata {
+        metadata.apply_all(&mut snapshot)?;
+    }
+
+    Ok(Some(snapshot))
+}
+
+/// Formats a path with forward slashes so that it's the same on every
+/// platform.
+fn to_slash_path(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use memofs::{InMemoryFs, VfsSnapshot};
+
+    use crate::{glob::Glob, snapshot::SyncRule, snapshot_middleware::Middleware};
+
+    fn asset_context() -> InstanceContext {
+        let mut context = InstanceContext::default();
+        context.add_sync_rules([SyncRule {
+            include: Glob::new("**/*.png").unwrap(),
+            exclude: None,
+            middleware: Middleware::Asset,
+            suffix: None,
+            class_name: None,
+            base_path: PathBuf::from("/root"),
+        }]);
+        context
+    }
+
+    #[test]
+    fn instance_from_vfs() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/root/assets/icon.png",
+            VfsSnapshot::file(b"\x89PNG".to_vec()),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_asset(
+            &asset_context(),
+            &vfs,
+            Path::new("/root/assets/icon.png"),
+            "icon",
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::assert_yaml_snapshot!(instance_snapshot);
+    }
+
+    #[test]
+    fn class_name_from_meta() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/root/icon.png", VfsSnapshot::file(b"\x89PNG".to_vec()))
+            .unwrap();
+        imfs.load_snapshot(
+            "/root/icon.meta.json",
+            VfsSnapshot::file(r#"{ "className": "ImageLabel" }"#),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot =
+            snapshot_asset(&asset_context(), &vfs, Path::new("/root/icon.png"), "icon")
+                .unwrap()
+                .unwrap();
+
+        assert_eq!(instance_snapshot.class_name, "ImageLabel");
+        assert_eq!(
+            instance_snapshot.properties.get("Attributes"),
+            Some(&Variant::Attributes({
+                let mut attributes = Attributes::new();
+                attributes.insert(
+                    ASSET_PATH_ATTRIBUTE.to_owned(),
+                    Variant::String("icon.png".to_owned()),
+                );
+                attributes
+            }))
+        );
+    }
+
+    #[test]
+    fn unknown_class_name() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/root/icon.png", VfsSnapshot::file(b"\x89PNG".to_vec()))
+            .unwrap();
+        imfs.load_snapshot(
+            "/root/icon.meta.json",
+            VfsSnapshot::file(r#"{ "className": "NotAClass" }"#),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        assert!(
+            snapshot_asset(&asset_context(), &vfs, Path::new("/root/icon.png"), "icon",).is_err()
+        );
+    }
+}
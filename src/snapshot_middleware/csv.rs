@@ -61,7 +61,7 @@ pub fn snapshot_csv_init(
     let folder_path = init_path.parent().unwrap();
     let dir_snapshot = snapshot_dir_no_meta(context, vfs, folder_path)?.unwrap();
 
-    if dir_snapshot.class_name != "Folder" {
+    if dir_snapshot.class_name != context.directory_class_name() {
         anyhow::bail!(
             "init.csv can only be used if the instance produced by \
              the containing directory would be a Folder.\n\
@@ -78,6 +78,7 @@ pub fn snapshot_csv_init(
     init_snapshot.metadata = dir_snapshot.metadata;
 
     if let Some(mut meta) = dir_meta(vfs, folder_path)? {
+        meta.check_no_class_name(init_path)?;
         meta.apply_all(&mut init_snapshot)?;
     }
 
@@ -132,7 +133,7 @@ fn convert_localization_csv(contents: &[u8]) -> Result<String, csv::Error> {
     for record in &records {
         let mut entry = LocalizationEntry::default();
 
-        for (header, value) in headers.iter().zip(record.into_iter()) {
+        for (header, value) in headers.iter().zip(record) {
             if header.is_empty() || value.is_empty() {
                 continue;
             }
@@ -180,11 +181,11 @@ Ack,Ack!,,An exclamation of despair,¡Ay!"#,
         )
         .unwrap();
 
-        let mut vfs = Vfs::new(imfs);
+        let vfs = Vfs::new(imfs);
 
         let instance_snapshot = snapshot_csv(
             &InstanceContext::default(),
-            &mut vfs,
+            &vfs,
             Path::new("/foo.csv"),
             "foo",
         )
@@ -212,11 +213,11 @@ Ack,Ack!,,An exclamation of despair,¡Ay!"#,
         )
         .unwrap();
 
-        let mut vfs = Vfs::new(imfs);
+        let vfs = Vfs::new(imfs);
 
         let instance_snapshot = snapshot_csv(
             &InstanceContext::default(),
-            &mut vfs,
+            &vfs,
             Path::new("/foo.csv"),
             "foo",
         )
@@ -225,4 +226,20 @@ Ack,Ack!,,An exclamation of despair,¡Ay!"#,
 
         insta::assert_yaml_snapshot!(instance_snapshot);
     }
+
+    #[test]
+    fn empty_cells_are_omitted() {
+        let contents = convert_localization_csv(
+            br#"Key,Source,Context,en,fr
+Greeting,Hello,Lobby,Hello,Bonjour
+Farewell,Goodbye,,Goodbye,
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            contents,
+            r#"[{"key":"Greeting","context":"Lobby","source":"Hello","values":{"en":"Hello","fr":"Bonjour"}},{"key":"Farewell","source":"Goodbye","values":{"en":"Goodbye"}}]"#
+        );
+    }
 }
@@ -1,8 +1,11 @@
-use std::path::Path;
+use std::{borrow::Cow, collections::HashMap, path::Path};
 
 use memofs::{DirEntry, IoResultExt, Vfs};
+use rayon::prelude::*;
 
-use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
+use crate::snapshot::{
+    IgnoreFile, InstanceContext, InstanceMetadata, InstanceSnapshot, IGNORE_FILE_NAME,
+};
 
 use super::{meta_file::DirectoryMetadata, snapshot_from_vfs};
 
@@ -45,27 +48,63 @@ pub fn snapshot_dir_no_meta(
     vfs: &Vfs,
     path: &Path,
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
-    let passes_filter_rules = |child: &DirEntry| {
-        context
+    let ignore_file_path = path.join(IGNORE_FILE_NAME);
+
+    let child_context = match vfs.read_to_string(&ignore_file_path).with_not_found()? {
+        Some(contents) => {
+            let mut child_context = context.clone();
+            child_context.add_ignore_file(IgnoreFile::parse(path.to_path_buf(), &contents)?);
+            Cow::Owned(child_context)
+        }
+        None => Cow::Borrowed(context),
+    };
+
+    let passes_filter_rules = |child: &DirEntry| -> anyhow::Result<bool> {
+        let passes_ignore_rules = context
             .path_ignore_rules
             .iter()
-            .all(|rule| rule.passes(child.path()))
+            .all(|matcher| !matcher.is_match(child.path()));
+
+        if !passes_ignore_rules {
+            return Ok(false);
+        }
+
+        if child_context.ignore_files.is_empty() {
+            return Ok(true);
+        }
+
+        let is_dir = vfs.metadata(child.path())?.is_dir();
+        Ok(!child_context.is_ignored_by_files(child.path(), is_dir))
     };
 
-    let mut snapshot_children = Vec::new();
+    let mut entries = Vec::new();
 
     for entry in vfs.read_dir(path)? {
         let entry = entry?;
 
-        if !passes_filter_rules(&entry) {
-            continue;
-        }
-
-        if let Some(child_snapshot) = snapshot_from_vfs(context, vfs, entry.path())? {
-            snapshot_children.push(child_snapshot);
+        if passes_filter_rules(&entry)? {
+            entries.push(entry);
         }
     }
 
+    check_case_collisions(context, &entries)?;
+
+    // Children are collected in the same order either way, so snapshotting
+    // them in parallel doesn't change the result.
+    let child_snapshots: Vec<_> = if context.parallel_snapshots {
+        entries
+            .par_iter()
+            .map(|entry| snapshot_from_vfs(&child_context, vfs, entry.path()))
+            .collect::<anyhow::Result<_>>()?
+    } else {
+        entries
+            .iter()
+            .map(|entry| snapshot_from_vfs(&child_context, vfs, entry.path()))
+            .collect::<anyhow::Result<_>>()?
+    };
+
+    let snapshot_children: Vec<_> = child_snapshots.into_iter().flatten().collect();
+
     let instance_name = path
         .file_name()
         .expect("Could not extract file name")
@@ -78,6 +117,7 @@ pub fn snapshot_dir_no_meta(
     let relevant_paths = vec![
         path.to_path_buf(),
         meta_path,
+        ignore_file_path,
         // TODO: We shouldn't need to know about Lua existing in this
         // middleware. Should we figure out a way for that function to add
         // relevant paths to this middleware?
@@ -92,7 +132,7 @@ pub fn snapshot_dir_no_meta(
 
     let snapshot = InstanceSnapshot::new()
         .name(instance_name)
-        .class_name("Folder")
+        .class_name(context.directory_class_name())
         .children(snapshot_children)
         .metadata(
             InstanceMetadata::new()
@@ -104,25 +144,65 @@ pub fn snapshot_dir_no_meta(
     Ok(Some(snapshot))
 }
 
+/// Checks for entries whose names only differ by case, which would overwrite
+/// each other when the project is checked out on a case-insensitive
+/// filesystem. These are errors in strict mode and warnings otherwise.
+fn check_case_collisions(context: &InstanceContext, entries: &[DirEntry]) -> anyhow::Result<()> {
+    let mut seen: HashMap<String, &Path> = HashMap::new();
+
+    for entry in entries {
+        let name = match entry.path().file_name().and_then(|name| name.to_str()) {
+            Some(name) => name.to_lowercase(),
+            None => continue,
+        };
+
+        if let Some(other) = seen.insert(name, entry.path()) {
+            let message = format!(
+                "{} and {} have names that only differ by case, \
+                 so one will overwrite the other on case-insensitive filesystems",
+                other.display(),
+                entry.path().display()
+            );
+
+            if context.strict {
+                anyhow::bail!(message);
+            }
+
+            log::warn!("{}", message);
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
     use maplit::hashmap;
     use memofs::{InMemoryFs, VfsSnapshot};
 
+    use crate::{
+        snapshot::{CancellationToken, Cancelled},
+        snapshot_middleware::snapshot_from_vfs_collecting_errors,
+    };
+
     #[test]
     fn empty_folder() {
         let mut imfs = InMemoryFs::new();
         imfs.load_snapshot("/foo", VfsSnapshot::empty_dir())
             .unwrap();
 
-        let mut vfs = Vfs::new(imfs);
+        let vfs = Vfs::new(imfs);
 
-        let instance_snapshot =
-            snapshot_dir(&InstanceContext::default(), &mut vfs, Path::new("/foo"))
-                .unwrap()
-                .unwrap();
+        let instance_snapshot = snapshot_dir(&InstanceContext::default(), &vfs, Path::new("/foo"))
+            .unwrap()
+            .unwrap();
 
         insta::assert_yaml_snapshot!(instance_snapshot);
     }
@@ -138,13 +218,274 @@ mod test {
         )
         .unwrap();
 
-        let mut vfs = Vfs::new(imfs);
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_dir(&InstanceContext::default(), &vfs, Path::new("/foo"))
+            .unwrap()
+            .unwrap();
+
+        insta::assert_yaml_snapshot!(instance_snapshot);
+    }
+
+    #[test]
+    fn nested_ignore_files() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir(hashmap! {
+                ".rojoignore" => VfsSnapshot::file("*.lua\nbuild/\n"),
+                "ignored.lua" => VfsSnapshot::file("return 1"),
+                "build" => VfsSnapshot::dir(hashmap! {
+                    "output.txt" => VfsSnapshot::file("ignored"),
+                }),
+                "nested" => VfsSnapshot::dir(hashmap! {
+                    ".rojoignore" => VfsSnapshot::file("!keep.lua\n"),
+                    "keep.lua" => VfsSnapshot::file("return 2"),
+                    "other.lua" => VfsSnapshot::file("return 3"),
+                }),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_dir(&InstanceContext::default(), &vfs, Path::new("/foo"))
+            .unwrap()
+            .unwrap();
+
+        let child_names: Vec<_> = instance_snapshot
+            .children
+            .iter()
+            .map(|child| {
+                let grandchildren: Vec<_> = child
+                    .children
+                    .iter()
+                    .map(|grandchild| grandchild.name.as_ref())
+                    .collect();
+
+                (child.name.as_ref(), grandchildren)
+            })
+            .collect();
+
+        assert_eq!(child_names, vec![("nested", vec!["keep"])]);
+    }
+
+    fn large_tree(dirs: usize, files: usize) -> VfsSnapshot {
+        VfsSnapshot::dir((0..dirs).map(|i| {
+            let children = (0..files).map(|j| {
+                (
+                    format!("file{}.lua", j),
+                    VfsSnapshot::file(format!("return {}", j)),
+                )
+            });
+
+            (format!("dir{}", i), VfsSnapshot::dir(children))
+        }))
+    }
+
+    #[test]
+    fn cancelled_before_start() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo", large_tree(10, 10)).unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let mut context = InstanceContext::default();
+        context.set_cancellation_token(token);
+
+        let err = snapshot_from_vfs(&context, &vfs, Path::new("/foo")).unwrap_err();
+        assert!(err.downcast_ref::<Cancelled>().is_some());
+    }
+
+    #[test]
+    fn cancelled_mid_walk() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo", large_tree(2000, 20)).unwrap();
+
+        let vfs = Vfs::new(imfs);
 
-        let instance_snapshot =
-            snapshot_dir(&InstanceContext::default(), &mut vfs, Path::new("/foo"))
+        let token = CancellationToken::new();
+        let mut context = InstanceContext::default();
+        context.set_cancellation_token(token.clone());
+
+        let walk = thread::spawn(move || {
+            let result = snapshot_from_vfs(&context, &vfs, Path::new("/foo"));
+            (result, Instant::now())
+        });
+
+        thread::sleep(Duration::from_millis(10));
+        let cancelled_at = Instant::now();
+        token.cancel();
+
+        let (result, returned_at) = walk.join().unwrap();
+        let err = result.unwrap_err();
+        assert!(err.downcast_ref::<Cancelled>().is_some());
+
+        // The walk should stop at the next directory rather than finishing.
+        assert!(returned_at.saturating_duration_since(cancelled_at) < Duration::from_secs(1));
+    }
+
+    fn deep_tree(depth: usize, breadth: usize) -> VfsSnapshot {
+        let files = (0..breadth).map(|i| {
+            (
+                format!("module{}.lua", i),
+                VfsSnapshot::file(format!("return {}", i)),
+            )
+        });
+
+        if depth == 0 {
+            return VfsSnapshot::dir(files);
+        }
+
+        let dirs = (0..breadth).map(|i| (format!("dir{}", i), deep_tree(depth - 1, breadth)));
+
+        VfsSnapshot::dir(files.chain(dirs))
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo", deep_tree(4, 5)).unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let sequential_start = Instant::now();
+        let sequential = snapshot_dir(&InstanceContext::default(), &vfs, Path::new("/foo"))
+            .unwrap()
+            .unwrap();
+        let sequential_time = sequential_start.elapsed();
+
+        let mut context = InstanceContext::default();
+        context.set_parallel_snapshots(true);
+
+        // Thread timing differs between runs, so snapshot a few times to give
+        // any ordering problems a chance to show up.
+        for _ in 0..3 {
+            let parallel_start = Instant::now();
+            let parallel = snapshot_dir(&context, &vfs, Path::new("/foo"))
                 .unwrap()
                 .unwrap();
+            let parallel_time = parallel_start.elapsed();
 
-        insta::assert_yaml_snapshot!(instance_snapshot);
+            log::info!(
+                "Sequential: {:?}, parallel: {:?}",
+                sequential_time,
+                parallel_time
+            );
+
+            // Metadata includes the context, which differs between the two.
+            assert_eq!(
+                without_metadata(parallel),
+                without_metadata(sequential.clone())
+            );
+        }
+    }
+
+    fn without_metadata(mut snapshot: InstanceSnapshot) -> InstanceSnapshot {
+        snapshot.metadata = InstanceMetadata::new();
+        snapshot.children = snapshot
+            .children
+            .into_iter()
+            .map(without_metadata)
+            .collect();
+        snapshot
+    }
+
+    #[test]
+    fn case_collisions() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir(hashmap! {
+                "Foo.lua" => VfsSnapshot::file("return 1"),
+                "foo.lua" => VfsSnapshot::file("return 2"),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        // Collisions are only warned about unless strict mode is on.
+        snapshot_dir(&InstanceContext::default(), &vfs, Path::new("/foo"))
+            .unwrap()
+            .unwrap();
+
+        let mut context = InstanceContext::default();
+        context.set_strict(true);
+
+        let err = snapshot_dir(&context, &vfs, Path::new("/foo")).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("/foo/Foo.lua"), "{}", message);
+        assert!(message.contains("/foo/foo.lua"), "{}", message);
+    }
+
+    #[test]
+    fn collects_errors() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir(hashmap! {
+                "good.lua" => VfsSnapshot::file("return 1"),
+                "broken.json" => VfsSnapshot::file("{"),
+                "nested" => VfsSnapshot::dir(hashmap! {
+                    "broken.toml" => VfsSnapshot::file("= 1"),
+                }),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let (snapshot, errors) = snapshot_from_vfs_collecting_errors(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo"),
+        )
+        .unwrap();
+
+        let mut error_paths: Vec<_> = errors.iter().map(|err| err.path.as_path()).collect();
+        error_paths.sort();
+        assert_eq!(
+            error_paths,
+            vec![
+                Path::new("/foo/broken.json"),
+                Path::new("/foo/nested/broken.toml")
+            ]
+        );
+
+        let child_names: Vec<_> = snapshot
+            .unwrap()
+            .children
+            .iter()
+            .map(|child| child.name.to_string())
+            .collect();
+        assert_eq!(child_names, vec!["good", "nested"]);
+
+        // Without collecting, the first error stops the snapshot.
+        assert!(snapshot_from_vfs(&InstanceContext::default(), &vfs, Path::new("/foo")).is_err());
+    }
+
+    #[test]
+    fn init_meta_class_name_with_init_script() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir(hashmap! {
+                "init.lua" => VfsSnapshot::file("return {}"),
+                "init.meta.json" => VfsSnapshot::file(r#"{ "className": "ScreenGui" }"#),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let err = snapshot_from_vfs(&InstanceContext::default(), &vfs, Path::new("/foo"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("sets className to ScreenGui"), "{}", err);
+        assert!(err.contains("/foo/init.lua"), "{}", err);
     }
 }
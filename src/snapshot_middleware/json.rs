@@ -19,8 +19,8 @@ pub fn snapshot_json(
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
     let contents = vfs.read(path)?;
 
-    let value: serde_json::Value = serde_json::from_slice(&contents)
-        .with_context(|| format!("File contains malformed JSON: {}", path.display()))?;
+    let value: serde_json::Value =
+        serde_json::from_slice(&contents).context("File contains malformed JSON")?;
 
     let as_lua = json_to_lua(value).to_string();
 
@@ -49,7 +49,7 @@ pub fn snapshot_json(
     Ok(Some(snapshot))
 }
 
-fn json_to_lua(value: serde_json::Value) -> Statement {
+pub(super) fn json_to_lua(value: serde_json::Value) -> Statement {
     Statement::Return(json_to_lua_value(value))
 }
 
@@ -79,6 +79,8 @@ mod test {
 
     use memofs::{InMemoryFs, VfsSnapshot};
 
+    use crate::snapshot_middleware::{snapshot_from_vfs, Middleware, MiddlewareError};
+
     #[test]
     fn instance_from_vfs() {
         let mut imfs = InMemoryFs::new();
@@ -101,11 +103,11 @@ mod test {
         )
         .unwrap();
 
-        let mut vfs = Vfs::new(imfs.clone());
+        let vfs = Vfs::new(imfs.clone());
 
         let instance_snapshot = snapshot_json(
             &InstanceContext::default(),
-            &mut vfs,
+            &vfs,
             Path::new("/foo.json"),
             "foo",
         )
@@ -114,4 +116,28 @@ mod test {
 
         insta::assert_yaml_snapshot!(instance_snapshot);
     }
+
+    #[test]
+    fn malformed() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/src",
+            VfsSnapshot::dir([("config.json", VfsSnapshot::file("{ \"a\": "))]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let err =
+            snapshot_from_vfs(&InstanceContext::default(), &vfs, Path::new("/src")).unwrap_err();
+
+        let middleware_err = err.downcast_ref::<MiddlewareError>().unwrap();
+        assert_eq!(middleware_err.path, Path::new("/src/config.json"));
+        assert_eq!(middleware_err.middleware, Middleware::Json);
+        let expected = format!(
+            "error in {}: File contains malformed JSON: ",
+            middleware_err.path.display()
+        );
+        assert!(err.to_string().starts_with(&expected));
+    }
 }
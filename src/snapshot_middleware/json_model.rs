@@ -1,4 +1,8 @@
-use std::{borrow::Cow, collections::HashMap, path::Path, str};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use anyhow::Context;
 use memofs::Vfs;
@@ -6,7 +10,7 @@ use rbx_dom_weak::types::{Attributes, Ref};
 use serde::Deserialize;
 
 use crate::{
-    resolution::UnresolvedValue,
+    resolution::{AmbiguousValue, UnresolvedValue},
     snapshot::{InstanceContext, InstanceSnapshot},
 };
 
@@ -16,16 +20,14 @@ pub fn snapshot_json_model(
     path: &Path,
     name: &str,
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
-    let contents = vfs.read(path)?;
-    let contents_str = str::from_utf8(&contents)
-        .with_context(|| format!("File was not valid UTF-8: {}", path.display()))?;
+    let contents = vfs.read_to_string(path)?;
 
-    if contents_str.trim().is_empty() {
+    if contents.trim().is_empty() {
         return Ok(None);
     }
 
-    let mut instance: JsonModel = serde_json::from_str(contents_str)
-        .with_context(|| format!("File is not a valid JSON model: {}", path.display()))?;
+    let mut instance: JsonModel =
+        serde_json::from_str(&contents).context("File is not a valid JSON model")?;
 
     if let Some(top_level_name) = &instance.name {
         let new_name = format!("{}.model.json", top_level_name);
@@ -41,14 +43,19 @@ pub fn snapshot_json_model(
 
     instance.name = Some(name.to_owned());
 
+    // Files that properties are read from are relevant too, so that the model
+    // is read again when they change.
+    let mut relevant_paths = vec![path.to_path_buf()];
+    let base_path = path.parent().unwrap_or_else(|| Path::new(""));
+
     let mut snapshot = instance
-        .into_snapshot()
-        .with_context(|| format!("Could not load JSON model: {}", path.display()))?;
+        .into_snapshot(vfs, base_path, &mut relevant_paths)
+        .context("Could not load JSON model")?;
 
     snapshot.metadata = snapshot
         .metadata
         .instigating_source(path)
-        .relevant_paths(vec![path.to_path_buf()])
+        .relevant_paths(relevant_paths)
         .context(context);
 
     Ok(Some(snapshot))
@@ -75,24 +82,66 @@ struct JsonModel {
         default = "HashMap::new",
         skip_serializing_if = "HashMap::is_empty"
     )]
-    properties: HashMap<String, UnresolvedValue>,
+    properties: HashMap<String, JsonModelProperty>,
 
     #[serde(default = "HashMap::new", skip_serializing_if = "HashMap::is_empty")]
     attributes: HashMap<String, UnresolvedValue>,
 }
 
+/// A property of an instance in a JSON model, which can either be given
+/// directly or read from another file.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum JsonModelProperty {
+    File(FileProperty),
+    Value(UnresolvedValue),
+}
+
+/// A string property whose value is the contents of a file, like
+/// `"Source": { "$file": "Module.lua" }`. The path is relative to the directory
+/// containing the model.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileProperty {
+    #[serde(rename = "$file")]
+    file: PathBuf,
+}
+
 impl JsonModel {
-    fn into_snapshot(self) -> anyhow::Result<InstanceSnapshot> {
+    fn into_snapshot(
+        self,
+        vfs: &Vfs,
+        base_path: &Path,
+        relevant_paths: &mut Vec<PathBuf>,
+    ) -> anyhow::Result<InstanceSnapshot> {
         let name = self.name.unwrap_or_else(|| self.class_name.clone());
         let class_name = self.class_name;
 
         let mut children = Vec::with_capacity(self.children.len());
         for child in self.children {
-            children.push(child.into_snapshot()?);
+            children.push(child.into_snapshot(vfs, base_path, relevant_paths)?);
         }
 
         let mut properties = HashMap::with_capacity(self.properties.len());
-        for (key, unresolved) in self.properties {
+        for (key, property) in self.properties {
+            let unresolved = match property {
+                JsonModelProperty::Value(unresolved) => unresolved,
+                JsonModelProperty::File(FileProperty { file }) => {
+                    let path = base_path.join(file);
+                    let contents = vfs.read_to_string_lf_normalized(&path).with_context(|| {
+                        format!(
+                            "Could not read property {} of {} from {}",
+                            key,
+                            name,
+                            path.display()
+                        )
+                    })?;
+
+                    relevant_paths.push(path);
+                    UnresolvedValue::Ambiguous(AmbiguousValue::String(contents.to_string()))
+                }
+            };
+
             let value = unresolved.resolve(&class_name, &key)?;
             properties.insert(key, value);
         }
@@ -124,6 +173,7 @@ mod test {
     use super::*;
 
     use memofs::{InMemoryFs, VfsSnapshot};
+    use rbx_dom_weak::types::Variant;
 
     #[test]
     fn model_from_vfs() {
@@ -163,6 +213,96 @@ mod test {
         insta::assert_yaml_snapshot!(instance_snapshot);
     }
 
+    #[test]
+    fn source_from_file() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/models",
+            VfsSnapshot::dir([
+                (
+                    "tool.model.json",
+                    VfsSnapshot::file(
+                        r#"
+                            {
+                              "className": "Tool",
+                              "children": [
+                                {
+                                  "name": "Main",
+                                  "className": "Script",
+                                  "properties": {
+                                    "Source": { "$file": "main.lua" }
+                                  }
+                                }
+                              ]
+                            }
+                        "#,
+                    ),
+                ),
+                ("main.lua", VfsSnapshot::file("print(\"Hello\")\r\n")),
+            ]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_json_model(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/models/tool.model.json"),
+            "tool",
+        )
+        .unwrap()
+        .unwrap();
+
+        let main = &instance_snapshot.children[0];
+        assert_eq!(
+            main.properties["Source"],
+            Variant::String("print(\"Hello\")\n".to_owned())
+        );
+        assert_eq!(
+            instance_snapshot.metadata.relevant_paths,
+            vec![
+                PathBuf::from("/models/tool.model.json"),
+                PathBuf::from("/models/main.lua"),
+            ]
+        );
+    }
+
+    #[test]
+    fn source_from_missing_file() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.model.json",
+            VfsSnapshot::file(
+                r#"
+                    {
+                      "className": "ModuleScript",
+                      "properties": {
+                        "Source": { "$file": "missing.lua" }
+                      }
+                    }
+                "#,
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let err = snapshot_json_model(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.model.json"),
+            "foo",
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            format!("{:#}", err),
+            "Could not load JSON model: Could not read property Source of foo \
+             from /missing.lua: path /missing.lua not found"
+        );
+    }
+
     #[test]
     fn model_from_vfs_legacy() {
         let mut imfs = InMemoryFs::new();
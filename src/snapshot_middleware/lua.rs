@@ -1,4 +1,4 @@
-use std::{collections::HashMap, path::Path, str};
+use std::{collections::HashMap, path::Path, str, sync::Arc};
 
 use memofs::{IoResultExt, Vfs};
 use rbx_dom_weak::types::Enum;
@@ -7,7 +7,9 @@ use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
 
 use super::{
     dir::{dir_meta, snapshot_dir_no_meta},
+    lua_comments::strip_comments,
     meta_file::AdjacentMetadata,
+    util::prune_default_properties,
 };
 
 #[derive(Debug)]
@@ -39,7 +41,12 @@ pub fn snapshot_lua(
         (_, ScriptType::Module) => ("ModuleScript", None),
     };
 
-    let contents = vfs.read_to_string_lf_normalized(path)?;
+    let mut contents = vfs.read_to_string_lf_normalized(path)?;
+
+    if context.strip_comments {
+        contents = Arc::new(strip_comments(&contents));
+    }
+
     let contents_str = contents.as_str();
 
     let mut properties = HashMap::with_capacity(2);
@@ -65,8 +72,11 @@ pub fn snapshot_lua(
                 .context(context),
         );
 
+    prune_default_properties(context, &mut snapshot);
+
     if let Some(meta_contents) = vfs.read(&meta_path).with_not_found()? {
         let mut metadata = AdjacentMetadata::from_slice(&meta_contents, meta_path)?;
+        ignore_source_property(&mut metadata.properties, &metadata.path);
         metadata.apply_all(&mut snapshot)?;
     }
 
@@ -76,8 +86,8 @@ pub fn snapshot_lua(
 /// Attempts to snapshot an 'init' Lua script contained inside of a folder with
 /// the given name.
 ///
-/// Scripts named `init.lua`, `init.server.lua`, or `init.client.lua` usurp
-/// their parents, which acts similarly to `__init__.py` from the Python world.
+/// Scripts named `init.lua`, `init.server.lua`, or `init.client.lua` (or their
+/// `.luau` equivalents) usurp their parents, which acts similarly to `__init__.py` from the Python world.
 pub fn snapshot_lua_init(
     context: &InstanceContext,
     vfs: &Vfs,
@@ -87,10 +97,10 @@ pub fn snapshot_lua_init(
     let folder_path = init_path.parent().unwrap();
     let dir_snapshot = snapshot_dir_no_meta(context, vfs, folder_path)?.unwrap();
 
-    if dir_snapshot.class_name != "Folder" {
+    if dir_snapshot.class_name != context.directory_class_name() {
         anyhow::bail!(
-            "init.lua, init.server.lua, and init.client.lua can \
-             only be used if the instance produced by the containing \
+            "init.lua, init.server.lua, init.client.lua, and their \
+             .luau equivalents can only be used if the instance produced by the containing \
              directory would be a Folder.\n\
              \n\
              The directory {} turned into an instance of class {}.",
@@ -106,12 +116,26 @@ pub fn snapshot_lua_init(
     init_snapshot.metadata = dir_snapshot.metadata;
 
     if let Some(mut meta) = dir_meta(vfs, folder_path)? {
+        meta.check_no_class_name(init_path)?;
+        ignore_source_property(&mut meta.properties, &meta.path);
         meta.apply_all(&mut init_snapshot)?;
     }
 
     Ok(Some(init_snapshot))
 }
 
+/// A script's `Source` always comes from its file, so meta files aren't
+/// allowed to override it.
+fn ignore_source_property<V>(properties: &mut HashMap<String, V>, meta_path: &Path) {
+    if properties.remove("Source").is_some() {
+        log::warn!(
+            "Source is set in {}, but a script's Source always comes from its \
+             file. It will be ignored.",
+            meta_path.display()
+        );
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -125,11 +149,11 @@ mod test {
         imfs.load_snapshot("/foo.lua", VfsSnapshot::file("Hello there!"))
             .unwrap();
 
-        let mut vfs = Vfs::new(imfs);
+        let vfs = Vfs::new(imfs);
 
         let instance_snapshot = snapshot_lua(
             &InstanceContext::with_emit_legacy_scripts(Some(true)),
-            &mut vfs,
+            &vfs,
             Path::new("/foo.lua"),
             "foo",
             ScriptType::Module,
@@ -148,11 +172,11 @@ mod test {
         imfs.load_snapshot("/foo.lua", VfsSnapshot::file("Hello there!"))
             .unwrap();
 
-        let mut vfs = Vfs::new(imfs);
+        let vfs = Vfs::new(imfs);
 
         let instance_snapshot = snapshot_lua(
             &InstanceContext::with_emit_legacy_scripts(Some(false)),
-            &mut vfs,
+            &vfs,
             Path::new("/foo.lua"),
             "foo",
             ScriptType::Module,
@@ -171,11 +195,11 @@ mod test {
         imfs.load_snapshot("/foo.server.lua", VfsSnapshot::file("Hello there!"))
             .unwrap();
 
-        let mut vfs = Vfs::new(imfs);
+        let vfs = Vfs::new(imfs);
 
         let instance_snapshot = snapshot_lua(
             &InstanceContext::with_emit_legacy_scripts(Some(true)),
-            &mut vfs,
+            &vfs,
             Path::new("/foo.server.lua"),
             "foo",
             ScriptType::Server,
@@ -194,11 +218,11 @@ mod test {
         imfs.load_snapshot("/foo.server.lua", VfsSnapshot::file("Hello there!"))
             .unwrap();
 
-        let mut vfs = Vfs::new(imfs);
+        let vfs = Vfs::new(imfs);
 
         let instance_snapshot = snapshot_lua(
             &InstanceContext::with_emit_legacy_scripts(Some(false)),
-            &mut vfs,
+            &vfs,
             Path::new("/foo.server.lua"),
             "foo",
             ScriptType::Server,
@@ -217,11 +241,11 @@ mod test {
         imfs.load_snapshot("/foo.client.lua", VfsSnapshot::file("Hello there!"))
             .unwrap();
 
-        let mut vfs = Vfs::new(imfs);
+        let vfs = Vfs::new(imfs);
 
         let instance_snapshot = snapshot_lua(
             &InstanceContext::with_emit_legacy_scripts(Some(true)),
-            &mut vfs,
+            &vfs,
             Path::new("/foo.client.lua"),
             "foo",
             ScriptType::Client,
@@ -240,11 +264,11 @@ mod test {
         imfs.load_snapshot("/foo.client.lua", VfsSnapshot::file("Hello there!"))
             .unwrap();
 
-        let mut vfs = Vfs::new(imfs);
+        let vfs = Vfs::new(imfs);
 
         let instance_snapshot = snapshot_lua(
             &InstanceContext::with_emit_legacy_scripts(Some(false)),
-            &mut vfs,
+            &vfs,
             Path::new("/foo.client.lua"),
             "foo",
             ScriptType::Client,
@@ -269,11 +293,11 @@ mod test {
         )
         .unwrap();
 
-        let mut vfs = Vfs::new(imfs);
+        let vfs = Vfs::new(imfs);
 
         let instance_snapshot = snapshot_lua(
             &InstanceContext::with_emit_legacy_scripts(Some(true)),
-            &mut vfs,
+            &vfs,
             Path::new("/root"),
             "root",
             ScriptType::Module,
@@ -303,11 +327,11 @@ mod test {
         )
         .unwrap();
 
-        let mut vfs = Vfs::new(imfs);
+        let vfs = Vfs::new(imfs);
 
         let instance_snapshot = snapshot_lua(
             &InstanceContext::with_emit_legacy_scripts(Some(true)),
-            &mut vfs,
+            &vfs,
             Path::new("/foo.lua"),
             "foo",
             ScriptType::Module,
@@ -337,11 +361,11 @@ mod test {
         )
         .unwrap();
 
-        let mut vfs = Vfs::new(imfs);
+        let vfs = Vfs::new(imfs);
 
         let instance_snapshot = snapshot_lua(
             &InstanceContext::with_emit_legacy_scripts(Some(false)),
-            &mut vfs,
+            &vfs,
             Path::new("/foo.lua"),
             "foo",
             ScriptType::Module,
@@ -371,11 +395,11 @@ mod test {
         )
         .unwrap();
 
-        let mut vfs = Vfs::new(imfs);
+        let vfs = Vfs::new(imfs);
 
         let instance_snapshot = snapshot_lua(
             &InstanceContext::with_emit_legacy_scripts(Some(true)),
-            &mut vfs,
+            &vfs,
             Path::new("/foo.server.lua"),
             "foo",
             ScriptType::Server,
@@ -405,11 +429,11 @@ mod test {
         )
         .unwrap();
 
-        let mut vfs = Vfs::new(imfs);
+        let vfs = Vfs::new(imfs);
 
         let instance_snapshot = snapshot_lua(
             &InstanceContext::with_emit_legacy_scripts(Some(false)),
-            &mut vfs,
+            &vfs,
             Path::new("/foo.server.lua"),
             "foo",
             ScriptType::Server,
@@ -441,11 +465,11 @@ mod test {
         )
         .unwrap();
 
-        let mut vfs = Vfs::new(imfs);
+        let vfs = Vfs::new(imfs);
 
         let instance_snapshot = snapshot_lua(
             &InstanceContext::with_emit_legacy_scripts(Some(true)),
-            &mut vfs,
+            &vfs,
             Path::new("/bar.server.lua"),
             "bar",
             ScriptType::Server,
@@ -477,11 +501,11 @@ mod test {
         )
         .unwrap();
 
-        let mut vfs = Vfs::new(imfs);
+        let vfs = Vfs::new(imfs);
 
         let instance_snapshot = snapshot_lua(
             &InstanceContext::with_emit_legacy_scripts(Some(false)),
-            &mut vfs,
+            &vfs,
             Path::new("/bar.server.lua"),
             "bar",
             ScriptType::Server,
@@ -493,4 +517,128 @@ mod test {
             insta::assert_yaml_snapshot!(instance_snapshot);
         });
     }
+
+    #[test]
+    fn module_with_attributes() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo.lua", VfsSnapshot::file("Hello there!"))
+            .unwrap();
+        imfs.load_snapshot(
+            "/foo.meta.json",
+            VfsSnapshot::file(
+                r#"
+                    {
+                        "attributes": {
+                            "Version": 3,
+                            "Tag": "shared"
+                        }
+                    }
+                "#,
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_lua(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.lua"),
+            "foo",
+            ScriptType::Module,
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::with_settings!({ sort_maps => true }, {
+            insta::assert_yaml_snapshot!(instance_snapshot);
+        });
+    }
+
+    #[test]
+    fn meta_cannot_override_source() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo.server.lua", VfsSnapshot::file("Hello there!"))
+            .unwrap();
+        imfs.load_snapshot(
+            "/foo.meta.json",
+            VfsSnapshot::file(
+                r#"
+                    {
+                        "properties": {
+                            "Source": "Overridden!",
+                            "Disabled": true
+                        }
+                    }
+                "#,
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_lua(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.server.lua"),
+            "foo",
+            ScriptType::Server,
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            instance_snapshot.properties.get("Source"),
+            Some(&"Hello there!".into())
+        );
+        assert_eq!(
+            instance_snapshot.properties.get("Disabled"),
+            Some(&true.into())
+        );
+    }
+
+    #[test]
+    fn prune_default_properties_keeps_meta() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo.server.lua", VfsSnapshot::file("Hello there!"))
+            .unwrap();
+        imfs.load_snapshot(
+            "/foo.meta.json",
+            VfsSnapshot::file(
+                r#"
+                    {
+                        "properties": {
+                            "Disabled": false
+                        }
+                    }
+                "#,
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let mut context = InstanceContext::with_emit_legacy_scripts(Some(true));
+        context.set_prune_default_properties(true);
+
+        let instance_snapshot = snapshot_lua(
+            &context,
+            &vfs,
+            Path::new("/foo.server.lua"),
+            "foo",
+            ScriptType::Server,
+        )
+        .unwrap()
+        .unwrap();
+
+        assert!(!instance_snapshot.properties.contains_key("RunContext"));
+        assert_eq!(
+            instance_snapshot.properties.get("Disabled"),
+            Some(&false.into())
+        );
+        assert_eq!(
+            instance_snapshot.properties.get("Source"),
+            Some(&"Hello there!".into())
+        );
+    }
 }
@@ -0,0 +1,277 @@
+//! A small Luau lexer that knows just enough about strings to remove comments
+//! from scripts, or to compare their whitespace, without touching anything
+//! inside of a string.
+
+/// Removes every comment from the given Luau source.
+///
+/// Line numbers are preserved: single line comments leave their newline
+/// behind, and block comments are replaced with the newlines they contained,
+/// or a single space if they were on one line.
+pub fn strip_comments(source: &str) -> String {
+    let bytes = source.as_bytes();
+    let mut output = String::with_capacity(source.len());
+    let mut copied_up_to = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' | b'\'' | b'`' => i = skip_quoted_string(bytes, i),
+            b'[' => match long_bracket(bytes, i) {
+                Some((level, contents_start)) => {
+                    i = skip_long_bracket(bytes, contents_start, level)
+                }
+                None => i += 1,
+            },
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                output.push_str(&source[copied_up_to..i]);
+
+                match long_bracket(bytes, i + 2) {
+                    Some((level, contents_start)) => {
+                        let end = skip_long_bracket(bytes, contents_start, level);
+                        let newlines = source[i..end].matches('\n').count();
+
+                        if newlines == 0 {
+                            output.push(' ');
+                        } else {
+                            output.extend(std::iter::repeat('\n').take(newlines));
+                        }
+
+                        i = end;
+                    }
+                    None => {
+                        let trimmed_len = output.trim_end_matches([' ', '\t']).len();
+                        output.truncate(trimmed_len);
+
+                        i = bytes[i..]
+                            .iter()
+                            .position(|&byte| byte == b'\n')
+                            .map_or(bytes.len(), |offset| i + offset);
+                    }
+                }
+
+                copied_up_to = i;
+            }
+            _ => i += 1,
+        }
+    }
+
+    output.push_str(&source[copied_up_to..]);
+    output
+}
+
+/// Rewrites the given Luau source so that any two sources that only differ in
+/// whitespace outside of strings and comments come out the same.
+///
+/// Runs of whitespace are kept as a single space between two words or two
+/// symbols, since removing them could join two tokens into one, and are
+/// dropped everywhere else. Line comments keep the newline that ends them.
+pub fn normalize_whitespace(source: &str) -> String {
+    let bytes = source.as_bytes();
+    let mut output: Vec<u8> = Vec::with_capacity(source.len());
+    let mut after_whitespace = false;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let start = i;
+
+        match bytes[i] {
+            byte if byte.is_ascii_whitespace() => {
+                after_whitespace = true;
+                i += 1;
+                continue;
+            }
+            b'"' | b'\'' | b'`' => i = skip_quoted_string(bytes, i),
+            b'[' => match long_bracket(bytes, i) {
+                Some((level, contents_start)) => {
+                    i = skip_long_bracket(bytes, contents_start, level)
+                }
+                None => i += 1,
+            },
+            b'-' if bytes.get(i + 1) == Some(&b'-') => match long_bracket(bytes, i + 2) {
+                Some((level, contents_start)) => {
+                    i = skip_long_bracket(bytes, contents_start, level)
+                }
+                None => {
+                    i = bytes[i..]
+                        .iter()
+                        .position(|&byte| byte == b'\n')
+                        .map_or(bytes.len(), |offset| i + offset + 1)
+                }
+            },
+            _ => i += 1,
+        }
+
+        if after_whitespace {
+            if let Some(&previous) = output.last() {
+                if is_word_byte(previous) == is_word_byte(bytes[start]) && previous != b'\n' {
+                    output.push(b' ');
+                }
+            }
+
+            after_whitespace = false;
+        }
+
+        output.extend_from_slice(&bytes[start..i]);
+    }
+
+    // Only ASCII whitespace is ever left out, so the output is still UTF-8.
+    String::from_utf8(output).expect("normalized source was not UTF-8")
+}
+
+/// Whether the byte can be part of a name, keyword, or number.
+fn is_word_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || !byte.is_ascii()
+}
+
+/// Given the index of an opening quote, returns the index just past the end
+/// of the string it starts.
+fn skip_quoted_string(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut i = start + 1;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            // Only interpolated strings can span lines without an escape.
+            b'\n' if quote != b'`' => return i,
+            byte if byte == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+
+    bytes.len()
+}
+
+/// If a long bracket like `[[` or `[==[` starts at the given index, returns its
+/// level and the index of the first byte after it.
+fn long_bracket(bytes: &[u8], start: usize) -> Option<(usize, usize)> {
+    if bytes.get(start) != Some(&b'[') {
+        return None;
+    }
+
+    let level = bytes[start + 1..]
+        .iter()
+        .take_while(|&&byte| byte == b'=')
+        .count();
+    let end = start + 1 + level;
+
+    if bytes.get(end) == Some(&b'[') {
+        Some((level, end + 1))
+    } else {
+        None
+    }
+}
+
+/// Returns the index just past the closing long bracket of the given level.
+fn skip_long_bracket(bytes: &[u8], start: usize, level: usize) -> usize {
+    let mut i = start;
+
+    while i < bytes.len() {
+        if bytes[i] == b']' {
+            let equals = bytes[i + 1..]
+                .iter()
+                .take_while(|&&byte| byte == b'=')
+                .count();
+            let end = i + 1 + equals;
+
+            if equals == level && bytes.get(end) == Some(&b']') {
+                return end + 1;
+            }
+        }
+
+        i += 1;
+    }
+
+    bytes.len()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn line_comments() {
+        assert_eq!(
+            strip_comments("-- header\nlocal x = 1 -- trailing\nreturn x\n"),
+            "\nlocal x = 1\nreturn x\n"
+        );
+    }
+
+    #[test]
+    fn block_comments() {
+        assert_eq!(
+            strip_comments("local x = --[[ inline ]]1\n--[==[\nmany\nlines\n]==]\nreturn x"),
+            "local x =  1\n\n\n\n\nreturn x"
+        );
+    }
+
+    #[test]
+    fn comments_in_strings() {
+        let source = "print(\"-- not a comment\", '--[[ nor this ]]', `-- {x} --`)";
+
+        assert_eq!(strip_comments(source), source);
+        assert_eq!(
+            strip_comments("print(\"escaped \\\" -- quote\") -- comment"),
+            "print(\"escaped \\\" -- quote\")"
+        );
+    }
+
+    #[test]
+    fn comments_in_long_strings() {
+        let source = "local s = [==[\n-- kept\n]] still in the string\n]==]";
+
+        assert_eq!(strip_comments(source), source);
+        assert_eq!(
+            strip_comments("local s = [[ -- kept ]] -- removed"),
+            "local s = [[ -- kept ]]"
+        );
+    }
+
+    #[test]
+    fn unterminated() {
+        assert_eq!(
+            strip_comments("local s = \"open\n-- gone"),
+            "local s = \"open\n"
+        );
+        assert_eq!(strip_comments("--[[ never closed"), " ");
+    }
+    #[test]
+    fn whitespace_between_tokens() {
+        assert_eq!(
+            normalize_whitespace("local function f(a, b)\n\treturn a  +  b\nend\n"),
+            normalize_whitespace("local function f(a,b)\n    return a+b\nend"),
+        );
+
+        // Removing these spaces would turn the two dashes into a comment.
+        assert_ne!(
+            normalize_whitespace("x = 1 - -1"),
+            normalize_whitespace("x = 1 --1")
+        );
+        assert_ne!(
+            normalize_whitespace("local x"),
+            normalize_whitespace("localx")
+        );
+    }
+
+    #[test]
+    fn whitespace_in_strings_and_comments() {
+        assert_ne!(
+            normalize_whitespace("print(\"a  b\")"),
+            normalize_whitespace("print(\"a b\")")
+        );
+        assert_ne!(
+            normalize_whitespace("local s = [[\n  indented\n]]"),
+            normalize_whitespace("local s = [[\nindented\n]]")
+        );
+
+        // The newline after a line comment is what ends it.
+        assert_ne!(
+            normalize_whitespace("-- comment\nreturn x"),
+            normalize_whitespace("-- comment return x")
+        );
+        assert_eq!(
+            normalize_whitespace("  -- comment\n  return x"),
+            normalize_whitespace("-- comment\nreturn x")
+        );
+    }
+}
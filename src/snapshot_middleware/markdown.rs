@@ -0,0 +1,214 @@
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use maplit::hashmap;
+use memofs::{IoResultExt, Vfs};
+use rbx_dom_weak::types::{Attributes, Variant};
+
+use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
+
+use super::{meta_file::AdjacentMetadata, util::prune_default_properties};
+
+pub fn snapshot_markdown(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let contents = vfs.read_to_string_lf_normalized(path)?;
+
+    let (front_matter, body) = match split_front_matter(&contents) {
+        Some((front_matter, body)) => (Some(front_matter), body),
+        None => (None, contents.as_str()),
+    };
+
+    let mut properties = hashmap! {
+        "Value".to_owned() => body.into(),
+    };
+
+    if let Some(front_matter) = front_matter {
+        let attributes = parse_front_matter(front_matter).context("Invalid front matter")?;
+
+        if !attributes.is_empty() {
+            properties.insert("Attributes".to_owned(), attributes.into());
+        }
+    }
+
+    let meta_path = path.with_file_name(format!("{}.meta.json", name));
+
+    let mut snapshot = InstanceSnapshot::new()
+        .name(name)
+        .class_name("StringValue")
+        .properties(properties)
+        .metadata(
+            InstanceMetadata::new()
+                .instigating_source(path)
+                .relevant_paths(vec![path.to_path_buf(), meta_path.clone()])
+                .context(context),
+        );
+
+    prune_default_properties(context, &mut snapshot);
+
+    if let Some(meta_contents) = vfs.read(&meta_path).with_not_found()? {
+        let mut metadata = AdjacentMetadata::from_slice(&meta_contents, meta_path)?;
+        metadata.apply_all(&mut snapshot)?;
+    }
+
+    Ok(Some(snapshot))
+}
+
+/// Splits a leading block delimited by `---` lines off of the given text,
+/// returning the contents of that block and the rest of the text.
+fn split_front_matter(contents: &str) -> Option<(&str, &str)> {
+    let rest = contents.strip_prefix("---\n")?;
+
+    if let Some(body) = rest.strip_prefix("---\n") {
+        return Some(("", body));
+    }
+
+    let end = rest
+        .find("\n---\n")
+        .map(|index| (index, index + 5))
+        .or_else(|| {
+            rest.strip_suffix("\n---")
+                .map(|front_matter| (front_matter.len(), rest.len()))
+        })?;
+
+    Some((&rest[..end.0], &rest[end.1..]))
+}
+
+/// Turns YAML front matter into attributes. Front matter must be a map whose
+/// values are all strings, numbers, or booleans.
+fn parse_front_matter(front_matter: &str) -> anyhow::Result<Attributes> {
+    use serde_yaml::Value;
+
+    let mut attributes = Attributes::new();
+
+    let mapping = match serde_yaml::from_str(front_matter)? {
+        Value::Null => return Ok(attributes),
+        Value::Mapping(mapping) => mapping,
+        _ => bail!("front matter must be a map of keys to values"),
+    };
+
+    for (key, value) in mapping {
+        let key = match key {
+            Value::String(key) => key,
+            other => bail!("front matter keys must be strings, but found {:?}", other),
+        };
+
+        let value = match value {
+            Value::Bool(value) => Variant::Bool(value),
+            Value::Number(value) => match value.as_f64() {
+                Some(value) => Variant::Float64(value),
+                None => bail!("the number in key {} cannot be represented", key),
+            },
+            Value::String(value) => Variant::String(value),
+            _ => bail!(
+                "front matter must be a flat map of keys to strings, numbers, \
+                 or booleans, but key {} has a different type of value",
+                key
+            ),
+        };
+
+        attributes.insert(key, value);
+    }
+
+    Ok(attributes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use memofs::{InMemoryFs, VfsSnapshot};
+
+    use crate::snapshot_middleware::snapshot_from_vfs;
+
+    fn snapshot_file(contents: &str) -> anyhow::Result<Option<InstanceSnapshot>> {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo.md", VfsSnapshot::file(contents))
+            .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        snapshot_markdown(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.md"),
+            "foo",
+        )
+    }
+
+    #[test]
+    fn instance_from_vfs() {
+        let instance_snapshot = snapshot_file("# Hello\n\nThere!\n").unwrap().unwrap();
+
+        insta::assert_yaml_snapshot!(instance_snapshot);
+    }
+
+    #[test]
+    fn with_front_matter() {
+        let instance_snapshot = snapshot_file(
+            "---\ntitle: Getting Started\norder: 2\ndraft: false\n---\n# Hello\n\nThere!\n",
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::with_settings!({ sort_maps => true }, {
+            insta::assert_yaml_snapshot!(instance_snapshot);
+        });
+    }
+
+    #[test]
+    fn nested_front_matter() {
+        let error = snapshot_file("---\nauthor:\n  name: Someone\n---\n# Hello\n").unwrap_err();
+
+        assert!(format!("{:#}", error).contains("key author"));
+    }
+
+    #[test]
+    fn front_matter_splitting() {
+        assert_eq!(
+            split_front_matter("---\na: 1\n---\nbody"),
+            Some(("a: 1", "body"))
+        );
+        assert_eq!(split_front_matter("---\n---\nbody"), Some(("", "body")));
+        assert_eq!(split_front_matter("---\na: 1\n---"), Some(("a: 1", "")));
+        assert_eq!(split_front_matter("---\na: 1\nbody"), None);
+        assert_eq!(split_front_matter("body\n---\n"), None);
+    }
+
+    #[test]
+    fn needs_sync_rule() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir(hashmap! {
+                "README.md" => VfsSnapshot::file("# Hello\n"),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        // Markdown files are left alone unless a project asks for them, since
+        // most of them are documentation like READMEs.
+        let snapshot = snapshot_from_vfs(&InstanceContext::default(), &vfs, Path::new("/foo"))
+            .unwrap()
+            .unwrap();
+        assert!(snapshot.children.is_empty());
+
+        let mut context = InstanceContext::default();
+        context
+            .add_sync_rules([
+                serde_json::from_str(r#"{ "pattern": "*.md", "use": "markdown" }"#).unwrap(),
+            ]);
+
+        let snapshot = snapshot_from_vfs(&context, &vfs, Path::new("/foo"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(snapshot.children.len(), 1);
+        assert_eq!(snapshot.children[0].name, "README");
+        assert_eq!(snapshot.children[0].class_name, "StringValue");
+    }
+}
@@ -1,6 +1,10 @@
-use std::{borrow::Cow, collections::HashMap, path::PathBuf};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
-use anyhow::{format_err, Context};
+use anyhow::{bail, format_err, Context};
 use rbx_dom_weak::types::Attributes;
 use serde::{Deserialize, Serialize};
 
@@ -22,6 +26,12 @@ pub struct AdjacentMetadata {
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub attributes: HashMap<String, UnresolvedValue>,
 
+    /// Changes the class of the resulting instance. Only middleware that can
+    /// produce more than one class, like plain text files, support this and
+    /// take it before the rest of the metadata is applied.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class_name: Option<String>,
+
     #[serde(skip)]
     pub path: PathBuf,
 }
@@ -49,6 +59,15 @@ impl AdjacentMetadata {
         let path = &self.path;
 
         for (key, unresolved) in self.properties.drain() {
+            let ref_path = unresolved
+                .as_ref_path(&snapshot.class_name, &key)
+                .with_context(|| format!("error applying meta file {}", path.display()))?;
+
+            if let Some(ref_path) = ref_path {
+                snapshot.metadata.ref_paths.insert(key, ref_path.to_owned());
+                continue;
+            }
+
             let value = unresolved
                 .resolve(&snapshot.class_name, &key)
                 .with_context(|| format!("error applying meta file {}", path.display()))?;
@@ -73,6 +92,15 @@ impl AdjacentMetadata {
     }
 
     pub fn apply_all(&mut self, snapshot: &mut InstanceSnapshot) -> anyhow::Result<()> {
+        if let Some(class_name) = self.class_name.take() {
+            log::warn!(
+                "className is set to {} in {}, but the file it describes cannot \
+                 change its class. It will be ignored.",
+                class_name,
+                self.path.display()
+            );
+        }
+
         self.apply_ignore_unknown_instances(snapshot);
         self.apply_properties(snapshot)?;
         Ok(())
@@ -118,6 +146,22 @@ impl DirectoryMetadata {
         Ok(meta)
     }
 
+    /// Returns an error if the meta file sets a class name, since the init
+    /// file at `init_path` already decides what class the directory becomes.
+    pub fn check_no_class_name(&self, init_path: &Path) -> anyhow::Result<()> {
+        if let Some(class_name) = &self.class_name {
+            bail!(
+                "{} sets className to {}, but the directory also contains {},                  which decides the class of the directory's instance.\n\
+                 Remove className from the meta file or remove the init file.",
+                self.path.display(),
+                class_name,
+                init_path.display()
+            );
+        }
+
+        Ok(())
+    }
+
     pub fn apply_all(&mut self, snapshot: &mut InstanceSnapshot) -> anyhow::Result<()> {
         self.apply_ignore_unknown_instances(snapshot);
         self.apply_class_name(snapshot)?;
@@ -128,7 +172,7 @@ impl DirectoryMetadata {
 
     fn apply_class_name(&mut self, snapshot: &mut InstanceSnapshot) -> anyhow::Result<()> {
         if let Some(class_name) = self.class_name.take() {
-            if snapshot.class_name != "Folder" {
+            if snapshot.class_name != snapshot.metadata.context.directory_class_name() {
                 // TODO: Turn into error type
                 return Err(format_err!(
                     "className in init.meta.json can only be specified if the \
@@ -152,6 +196,15 @@ impl DirectoryMetadata {
         let path = &self.path;
 
         for (key, unresolved) in self.properties.drain() {
+            let ref_path = unresolved
+                .as_ref_path(&snapshot.class_name, &key)
+                .with_context(|| format!("error applying meta file {}", path.display()))?;
+
+            if let Some(ref_path) = ref_path {
+                snapshot.metadata.ref_paths.insert(key, ref_path.to_owned());
+                continue;
+            }
+
             let value = unresolved
                 .resolve(&snapshot.class_name, &key)
                 .with_context(|| format!("error applying meta file {}", path.display()))?;
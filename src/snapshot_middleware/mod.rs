@@ -5,45 +5,63 @@
 
 #![allow(dead_code)]
 
+mod asset;
 mod csv;
 mod dir;
 mod json;
 mod json_model;
 mod lua;
+mod lua_comments;
+mod markdown;
 mod meta_file;
+mod path_matcher;
 mod project;
 mod rbxm;
 mod rbxmx;
+mod ref_paths;
 mod toml;
 mod txt;
 mod util;
+mod yaml;
 
 use std::{
     path::{Path, PathBuf},
-    sync::OnceLock,
+    sync::{Arc, Mutex, OnceLock},
 };
 
 use anyhow::Context;
 use memofs::{IoResultExt, Vfs};
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use crate::glob::Glob;
+use crate::project::Project;
 use crate::snapshot::{InstanceContext, InstanceSnapshot, SyncRule};
 
 use self::{
+    asset::snapshot_asset,
     csv::{snapshot_csv, snapshot_csv_init},
     dir::snapshot_dir,
     json::snapshot_json,
     json_model::snapshot_json_model,
     lua::{snapshot_lua, snapshot_lua_init, ScriptType},
+    markdown::snapshot_markdown,
     project::snapshot_project,
     rbxm::snapshot_rbxm,
     rbxmx::snapshot_rbxmx,
     toml::snapshot_toml,
     txt::snapshot_txt,
+    util::apply_class_defaults,
+    yaml::snapshot_yaml,
 };
 
-pub use self::{project::snapshot_project_node, util::emit_legacy_scripts_default};
+pub use self::{
+    lua_comments::normalize_whitespace,
+    path_matcher::PathMatcher,
+    project::snapshot_project_node,
+    ref_paths::{resolve_ref_paths, resolve_ref_paths_in_tree},
+    util::emit_legacy_scripts_default,
+};
 
 /// Returns an `InstanceSnapshot` for the provided path.
 /// This will inspect the path and find the appropriate middleware for it,
@@ -61,53 +79,143 @@ pub fn snapshot_from_vfs(
     };
 
     if meta.is_dir() {
-        if let Some(init_path) = get_init_path(vfs, path)? {
-            // TODO: support user-defined init paths
-            for rule in default_sync_rules() {
-                if rule.matches(&init_path) {
-                    return match rule.middleware {
-                        Middleware::Project => {
-                            let name = init_path
-                                .parent()
-                                .and_then(Path::file_name)
-                                .and_then(|s| s.to_str()).expect("default.project.json should be inside a folder with a unicode name");
-                            snapshot_project(context, vfs, &init_path, name)
-                        }
-
-                        Middleware::ModuleScript => {
-                            snapshot_lua_init(context, vfs, &init_path, ScriptType::Module)
-                        }
-                        Middleware::ServerScript => {
-                            snapshot_lua_init(context, vfs, &init_path, ScriptType::Server)
-                        }
-                        Middleware::ClientScript => {
-                            snapshot_lua_init(context, vfs, &init_path, ScriptType::Client)
-                        }
-
-                        Middleware::Csv => snapshot_csv_init(context, vfs, &init_path),
-
-                        _ => snapshot_dir(context, vfs, path),
-                    };
-                }
+        context.check_cancelled()?;
+        let snapshot = match snapshot_dir_or_init(context, vfs, path) {
+            Ok(snapshot) => snapshot,
+            Err(err) => return collect_error(context, err),
+        };
+        return Ok(with_class_defaults(context, snapshot));
+    }
+
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .with_context(|| format!("file name of {} is invalid", path.display()))?;
+
+    // TODO: Is this even necessary anymore?
+    match file_name {
+        "init.server.luau" | "init.server.lua" | "init.client.luau" | "init.client.lua"
+        | "init.luau" | "init.lua" | "init.csv" => return Ok(None),
+        _ => {}
+    }
+
+    if let Some(progress) = &context.progress {
+        progress.record_file(meta.len());
+    }
+
+    // Projects pull in everything they point to, which the cache can't tell
+    // has changed by looking at the project file alone.
+    let cache = context
+        .snapshot_cache
+        .as_ref()
+        .filter(|_| !Project::is_project_file(path));
+
+    if let Some(snapshot) = cache.and_then(|cache| cache.get(path, &meta, context)) {
+        return Ok(snapshot);
+    }
+
+    let snapshot = match snapshot_from_path(context, vfs, path) {
+        Ok(snapshot) => with_class_defaults(context, snapshot),
+        Err(err) => return collect_error(context, err),
+    };
+
+    if let Some(cache) = cache {
+        cache.insert(path, &meta, context, snapshot.clone());
+    }
+
+    Ok(snapshot)
+}
+
+/// Like `snapshot_from_vfs`, but files that can't be snapshotted are left out
+/// of the tree instead of stopping the whole snapshot. Returns what could be
+/// snapshotted along with an error for every file that was left out.
+///
+/// Errors that don't come from a middleware, like being unable to read a
+/// directory, still stop the snapshot.
+pub fn snapshot_from_vfs_collecting_errors(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+) -> anyhow::Result<(Option<InstanceSnapshot>, Vec<MiddlewareError>)> {
+    let errors = ErrorCollector::new();
+
+    let mut context = context.clone();
+    context.set_error_collector(errors.clone());
+
+    let snapshot = snapshot_from_vfs(&context, vfs, path)?;
+
+    Ok((snapshot, errors.take()))
+}
+
+/// Records an error from a middleware if the context is collecting them, so
+/// that snapshotting can continue without the file it came from.
+fn collect_error(
+    context: &InstanceContext,
+    err: anyhow::Error,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let collector = match &context.error_collector {
+        Some(collector) => collector,
+        None => return Err(err),
+    };
+
+    let err = err.downcast::<MiddlewareError>()?;
+    log::debug!("Leaving out {}: {:#}", err.path.display(), err.error);
+    collector.push(err);
+
+    Ok(None)
+}
+
+fn with_class_defaults(
+    context: &InstanceContext,
+    snapshot: Option<InstanceSnapshot>,
+) -> Option<InstanceSnapshot> {
+    snapshot.map(|mut snapshot| {
+        apply_class_defaults(context, &mut snapshot);
+        snapshot
+    })
+}
+
+/// Snapshots a directory, using its init file if it has one.
+fn snapshot_dir_or_init(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    if let Some(init_path) = get_init_path(vfs, path)? {
+        // TODO: support user-defined init paths
+        for rule in default_sync_rules() {
+            if rule.matches(&init_path) {
+                let result = match rule.middleware {
+                    Middleware::Project => {
+                        let name = init_path
+                            .parent()
+                            .and_then(Path::file_name)
+                            .and_then(|s| s.to_str()).expect("default.project.json should be inside a folder with a unicode name");
+                        snapshot_project(context, vfs, &init_path, name)
+                    }
+
+                    Middleware::ModuleScript => {
+                        snapshot_lua_init(context, vfs, &init_path, ScriptType::Module)
+                    }
+                    Middleware::ServerScript => {
+                        snapshot_lua_init(context, vfs, &init_path, ScriptType::Server)
+                    }
+                    Middleware::ClientScript => {
+                        snapshot_lua_init(context, vfs, &init_path, ScriptType::Client)
+                    }
+
+                    Middleware::Csv => snapshot_csv_init(context, vfs, &init_path),
+
+                    _ => return snapshot_dir(context, vfs, path),
+                };
+
+                return result
+                    .map_err(|err| MiddlewareError::wrap(err, rule.middleware, &init_path));
             }
-            snapshot_dir(context, vfs, path)
-        } else {
-            snapshot_dir(context, vfs, path)
         }
+        snapshot_dir(context, vfs, path)
     } else {
-        let file_name = path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .with_context(|| format!("file name of {} is invalid", path.display()))?;
-
-        // TODO: Is this even necessary anymore?
-        match file_name {
-            "init.server.luau" | "init.server.lua" | "init.client.luau" | "init.client.lua"
-            | "init.luau" | "init.lua" | "init.csv" => return Ok(None),
-            _ => {}
-        }
-
-        snapshot_from_path(context, vfs, path)
+        snapshot_dir(context, vfs, path)
     }
 }
 
@@ -118,42 +226,42 @@ fn get_init_path<P: AsRef<Path>>(vfs: &Vfs, dir: P) -> anyhow::Result<Option<Pat
     let path = dir.as_ref();
 
     let project_path = path.join("default.project.json");
-    if vfs.metadata(&project_path).with_not_found()?.is_some() {
+    if vfs.exists(&project_path)? {
         return Ok(Some(project_path));
     }
 
     let init_path = path.join("init.luau");
-    if vfs.metadata(&init_path).with_not_found()?.is_some() {
+    if vfs.exists(&init_path)? {
         return Ok(Some(init_path));
     }
 
     let init_path = path.join("init.lua");
-    if vfs.metadata(&init_path).with_not_found()?.is_some() {
+    if vfs.exists(&init_path)? {
         return Ok(Some(init_path));
     }
 
     let init_path = path.join("init.server.luau");
-    if vfs.metadata(&init_path).with_not_found()?.is_some() {
+    if vfs.exists(&init_path)? {
         return Ok(Some(init_path));
     }
 
     let init_path = path.join("init.server.lua");
-    if vfs.metadata(&init_path).with_not_found()?.is_some() {
+    if vfs.exists(&init_path)? {
         return Ok(Some(init_path));
     }
 
     let init_path = path.join("init.client.luau");
-    if vfs.metadata(&init_path).with_not_found()?.is_some() {
+    if vfs.exists(&init_path)? {
         return Ok(Some(init_path));
     }
 
     let init_path = path.join("init.client.lua");
-    if vfs.metadata(&init_path).with_not_found()?.is_some() {
+    if vfs.exists(&init_path)? {
         return Ok(Some(init_path));
     }
 
     let init_path = path.join("init.csv");
-    if vfs.metadata(&init_path).with_not_found()?.is_some() {
+    if vfs.exists(&init_path)? {
         return Ok(Some(init_path));
     }
 
@@ -204,6 +312,9 @@ pub enum Middleware {
     Rbxmx,
     Toml,
     Text,
+    Markdown,
+    Yaml,
+    Asset,
     Ignore,
 }
 
@@ -217,7 +328,7 @@ impl Middleware {
         path: &Path,
         name: &str,
     ) -> anyhow::Result<Option<InstanceSnapshot>> {
-        match self {
+        let result = match self {
             Self::Csv => snapshot_csv(context, vfs, path, name),
             Self::JsonModel => snapshot_json_model(context, vfs, path, name),
             Self::Json => snapshot_json(context, vfs, path, name),
@@ -229,8 +340,75 @@ impl Middleware {
             Self::Rbxmx => snapshot_rbxmx(context, vfs, path, name),
             Self::Toml => snapshot_toml(context, vfs, path, name),
             Self::Text => snapshot_txt(context, vfs, path, name),
+            Self::Markdown => snapshot_markdown(context, vfs, path, name),
+            Self::Yaml => snapshot_yaml(context, vfs, path, name),
+            Self::Asset => snapshot_asset(context, vfs, path, name),
             Self::Ignore => Ok(None),
+        };
+
+        result.map_err(|err| MiddlewareError::wrap(err, *self, path))
+    }
+}
+
+/// An error from a middleware that couldn't snapshot a file, along with the
+/// file and the middleware that was used for it.
+#[derive(Debug, Error)]
+#[error("error in {}: {error:#}", path.display())]
+pub struct MiddlewareError {
+    pub path: PathBuf,
+    pub middleware: Middleware,
+    error: anyhow::Error,
+}
+
+impl MiddlewareError {
+    /// Attaches the file and middleware to an error, unless the error already
+    /// came from another middleware, like for a broken file that's included
+    /// by a project.
+    fn wrap(error: anyhow::Error, middleware: Middleware, path: &Path) -> anyhow::Error {
+        if error.chain().any(|cause| cause.is::<MiddlewareError>()) {
+            return error;
+        }
+
+        MiddlewareError {
+            path: path.to_path_buf(),
+            middleware,
+            error,
         }
+        .into()
+    }
+
+    /// The underlying error, without the file it came from.
+    pub fn error(&self) -> &anyhow::Error {
+        &self.error
+    }
+}
+
+/// Errors collected while snapshotting, shared between every context that a
+/// snapshot passes through.
+#[derive(Debug, Clone, Default)]
+pub struct ErrorCollector {
+    errors: Arc<Mutex<Vec<MiddlewareError>>>,
+}
+
+impl ErrorCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&self, error: MiddlewareError) {
+        self.errors.lock().unwrap().push(error);
+    }
+
+    /// Removes and returns every error collected so far.
+    pub fn take(&self) -> Vec<MiddlewareError> {
+        std::mem::take(&mut *self.errors.lock().unwrap())
+    }
+}
+
+// Collecting errors doesn't change what a snapshot contains.
+impl PartialEq for ErrorCollector {
+    fn eq(&self, _other: &Self) -> bool {
+        true
     }
 }
 
@@ -248,6 +426,7 @@ macro_rules! sync_rule {
             include: Glob::new($pattern).unwrap(),
             exclude: None,
             suffix: None,
+            class_name: None,
             base_path: PathBuf::new(),
         }
     };
@@ -257,6 +436,7 @@ macro_rules! sync_rule {
             include: Glob::new($pattern).unwrap(),
             exclude: None,
             suffix: Some($suffix.into()),
+            class_name: None,
             base_path: PathBuf::new(),
         }
     };
@@ -266,6 +446,7 @@ macro_rules! sync_rule {
             include: Glob::new($pattern).unwrap(),
             exclude: Some(Glob::new($exclude).unwrap()),
             suffix: Some($suffix.into()),
+            class_name: None,
             base_path: PathBuf::new(),
         }
     };
@@ -274,7 +455,7 @@ macro_rules! sync_rule {
 /// Defines the 'default' syncing rules that Rojo uses.
 /// These do not broadly overlap, but the order matters for some in the case of
 /// e.g. JSON models.
-fn default_sync_rules() -> &'static [SyncRule] {
+pub fn default_sync_rules() -> &'static [SyncRule] {
     static DEFAULT_SYNC_RULES: OnceLock<Vec<SyncRule>> = OnceLock::new();
 
     DEFAULT_SYNC_RULES.get_or_init(|| {
@@ -0,0 +1,149 @@
+//! Matching of paths inside a project against a set of globs.
+
+use std::path::{Component, Path, PathBuf};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::path_serializer;
+
+/// A set of globs that are matched against paths relative to a base path,
+/// which is usually the folder containing a project file.
+///
+/// Globs support `*`, `?`, `**`, which matches any number of folders, and
+/// `{a,b}` alternation. Like the globs in sync rules, `*` and `?` can match a
+/// `/`, so `src/*.lua` matches files in folders inside of `src` too. Paths are
+/// converted to use forward slashes before being matched so that globs behave
+/// the same way on every platform.
+///
+/// Matchers should be created once per project and reused while traversing
+/// it, since compiling globs is much more expensive than matching them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "SerializedPathMatcher", try_from = "SerializedPathMatcher")]
+pub struct PathMatcher {
+    base_path: PathBuf,
+    patterns: Vec<String>,
+    set: GlobSet,
+}
+
+impl PathMatcher {
+    pub fn new<I, S>(base_path: impl Into<PathBuf>, patterns: I) -> Result<Self, globset::Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let patterns: Vec<String> = patterns
+            .into_iter()
+            .map(|pattern| pattern.as_ref().to_owned())
+            .collect();
+        let mut builder = GlobSetBuilder::new();
+
+        for pattern in &patterns {
+            builder.add(Glob::new(pattern)?);
+        }
+
+        Ok(Self {
+            base_path: base_path.into(),
+            patterns,
+            set: builder.build()?,
+        })
+    }
+
+    /// Tells whether the given path matches any of the globs. Paths outside of
+    /// the base path never match.
+    pub fn is_match(&self, path: &Path) -> bool {
+        match normalize(&self.base_path, path) {
+            Some(relative) => self.set.is_match(relative),
+            None => false,
+        }
+    }
+}
+
+impl PartialEq for PathMatcher {
+    fn eq(&self, other: &Self) -> bool {
+        self.base_path == other.base_path && self.patterns == other.patterns
+    }
+}
+
+/// How a `PathMatcher` is serialized, since the compiled globs can't be.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedPathMatcher {
+    #[serde(serialize_with = "path_serializer::serialize_absolute")]
+    base_path: PathBuf,
+    globs: Vec<String>,
+}
+
+impl From<PathMatcher> for SerializedPathMatcher {
+    fn from(matcher: PathMatcher) -> Self {
+        Self {
+            base_path: matcher.base_path,
+            globs: matcher.patterns,
+        }
+    }
+}
+
+impl TryFrom<SerializedPathMatcher> for PathMatcher {
+    type Error = globset::Error;
+
+    fn try_from(serialized: SerializedPathMatcher) -> Result<Self, Self::Error> {
+        Self::new(serialized.base_path, serialized.globs)
+    }
+}
+
+/// Turns a path into one relative to `base_path` that only uses forward
+/// slashes, or `None` if it isn't inside of `base_path`.
+fn normalize(base_path: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(base_path).ok()?;
+    let mut parts = Vec::new();
+
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_str()?),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+
+    Some(parts.join("/"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn recursive_wildcard() {
+        let matcher = PathMatcher::new("/project", ["**/*.spec.lua"]).unwrap();
+
+        assert!(matcher.is_match(Path::new("/project/init.spec.lua")));
+        assert!(matcher.is_match(Path::new("/project/src/a/b/thing.spec.lua")));
+        assert!(!matcher.is_match(Path::new("/project/src/thing.lua")));
+        assert!(!matcher.is_match(Path::new("/elsewhere/thing.spec.lua")));
+    }
+
+    #[test]
+    fn single_wildcards() {
+        let matcher = PathMatcher::new("/project", ["src/*.lua", "file?.txt"]).unwrap();
+
+        assert!(matcher.is_match(Path::new("/project/src/main.lua")));
+        assert!(matcher.is_match(Path::new("/project/src/nested/main.lua")));
+        assert!(!matcher.is_match(Path::new("/project/other/main.lua")));
+        assert!(matcher.is_match(Path::new("/project/file1.txt")));
+        assert!(!matcher.is_match(Path::new("/project/file10.txt")));
+    }
+
+    #[test]
+    fn brace_expansion() {
+        let matcher = PathMatcher::new("/project", ["src/**/*.{client,server}.lua"]).unwrap();
+
+        assert!(matcher.is_match(Path::new("/project/src/a.client.lua")));
+        assert!(matcher.is_match(Path::new("/project/src/nested/b.server.lua")));
+        assert!(!matcher.is_match(Path::new("/project/src/c.lua")));
+    }
+
+    #[test]
+    fn invalid_glob() {
+        assert!(PathMatcher::new("/project", ["src/{a,b"]).is_err());
+    }
+}
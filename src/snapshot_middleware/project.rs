@@ -1,4 +1,8 @@
-use std::{borrow::Cow, collections::HashMap, path::Path};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
 
 use anyhow::{bail, Context};
 use memofs::Vfs;
@@ -6,14 +10,15 @@ use rbx_dom_weak::types::{Attributes, Ref};
 use rbx_reflection::ClassTag;
 
 use crate::{
-    project::{PathNode, Project, ProjectNode},
-    snapshot::{
-        InstanceContext, InstanceMetadata, InstanceSnapshot, InstigatingSource, PathIgnoreRule,
-        SyncRule,
-    },
+    glob::Glob,
+    project::{Project, ProjectNode},
+    snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot, InstigatingSource, SyncRule},
 };
 
-use super::{emit_legacy_scripts_default, snapshot_from_vfs};
+use super::{
+    emit_legacy_scripts_default, path_matcher::PathMatcher, snapshot_from_vfs,
+    util::apply_class_defaults,
+};
 
 pub fn snapshot_project(
     context: &InstanceContext,
@@ -21,17 +26,30 @@ pub fn snapshot_project(
     path: &Path,
     name: &str,
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
-    let project = Project::load_from_slice(&vfs.read(path)?, path)
+    let mut project = Project::load_from_slice(&vfs.read(path)?, path)
         .with_context(|| format!("File was not a valid Rojo project: {}", path.display()))?;
+
+    // Unknown keys are only warned about unless strict mode is on.
+    for error in project.unknown_key_errors() {
+        if context.strict {
+            return Err(error.into());
+        }
+
+        log::warn!("{}. This key will be ignored.", error);
+    }
+
+    let included_paths = project.resolve_includes(vfs)?;
     let project_name = project.name.as_deref().unwrap_or(name);
 
     let mut context = context.clone();
     context.clear_sync_rules();
 
-    let rules = project.glob_ignore_paths.iter().map(|glob| PathIgnoreRule {
-        glob: glob.clone(),
-        base_path: project.folder_location().to_path_buf(),
-    });
+    // A project's ignore globs are compiled together once, then checked
+    // against every path found under it.
+    let ignore_globs = project.glob_ignore_paths.iter().map(Glob::as_str);
+    let ignore_matcher = PathMatcher::new(project.folder_location(), ignore_globs)
+        .with_context(|| format!("Invalid globIgnorePaths in project {}", path.display()))?;
+    let rules = Some(ignore_matcher).filter(|_| !project.glob_ignore_paths.is_empty());
 
     let sync_rules = project.sync_rules.iter().map(|rule| SyncRule {
         base_path: project.folder_location().to_path_buf(),
@@ -47,6 +65,46 @@ pub fn snapshot_project(
             .unwrap(),
     );
 
+    if let Some(strip_comments) = project.strip_comments {
+        context.set_strip_comments(strip_comments);
+    }
+
+    if let Some(ignore_script_whitespace) = project.ignore_script_whitespace {
+        context.set_ignore_script_whitespace(ignore_script_whitespace);
+    }
+
+    if let Some(prune_default_properties) = project.prune_default_properties {
+        context.set_prune_default_properties(prune_default_properties);
+    }
+
+    if let Some(parallel_snapshots) = project.parallel_snapshots {
+        context.set_parallel_snapshots(parallel_snapshots);
+    }
+
+    if let Some(class_name) = &project.default_directory_class_name {
+        context.set_default_directory_class_name(class_name.clone());
+    }
+
+    for (class_name, unresolved_defaults) in &project.class_defaults {
+        let mut defaults = BTreeMap::new();
+
+        for (key, unresolved) in unresolved_defaults {
+            let value = unresolved
+                .clone()
+                .resolve(class_name, key)
+                .with_context(|| {
+                    format!(
+                        "Unresolvable class default in project at path {}",
+                        path.display()
+                    )
+                })?;
+
+            defaults.insert(key.clone(), value);
+        }
+
+        context.add_class_defaults(class_name, defaults);
+    }
+
     match snapshot_project_node(&context, path, project_name, &project.tree, vfs, None)? {
         Some(found_snapshot) => {
             let mut snapshot = found_snapshot;
@@ -68,6 +126,10 @@ pub fn snapshot_project(
             // file being updated.
             snapshot.metadata.relevant_paths.push(path.to_path_buf());
 
+            // Included projects are part of this project file, so they're
+            // relevant to the same node.
+            snapshot.metadata.relevant_paths.extend(included_paths);
+
             Ok(Some(snapshot))
         }
         None => Ok(None),
@@ -150,7 +212,7 @@ pub fn snapshot_project_node(
         // a class name, let's use the inferred name. If the path we're pointing
         // to isn't a folder, though, that's a user error.
         (None, Some(path), Some(inference), _) => {
-            if path == "Folder" {
+            if is_directory_class(context, &path) {
                 inference
             } else {
                 path
@@ -158,12 +220,12 @@ pub fn snapshot_project_node(
         }
 
         (Some(project), Some(path), _, _) => {
-            if path == "Folder" {
+            if is_directory_class(context, &path) {
                 project
             } else {
                 bail!(
                     "ClassName for Instance \"{}\" was specified in both the project file (as \"{}\") and from the filesystem (as \"{}\").\n\
-                     If $className and $path are both set, $path must refer to a Folder.
+                     If $className and $path are both set, $path must refer to a Folder.\n\
                      \n\
                      Project path: {}\n\
                      Filesystem path: {}\n",
@@ -176,11 +238,11 @@ pub fn snapshot_project_node(
             }
         }
 
-        (None, None, None, Some(PathNode::Optional(_))) => {
+        (None, None, None, Some(_)) if node.is_path_optional() => {
             return Ok(None);
         }
 
-        (_, None, _, Some(PathNode::Required(path))) => {
+        (_, None, _, Some(path_node)) => {
             anyhow::bail!(
                 "Rojo project referred to a file using $path that could not be turned into a Roblox Instance by Rojo.\n\
                 Check that the file exists and is a file type known by Rojo.\n\
@@ -188,10 +250,14 @@ pub fn snapshot_project_node(
                 Project path: {}\n\
                 File $path: {}",
                 project_path.display(),
-                path.display(),
+                path_node.path().display(),
             );
         }
 
+        (None, None, None, None) if context.default_directory_class_name.is_some() => {
+            Cow::Owned(context.directory_class_name().to_owned())
+        }
+
         (None, None, None, None) => {
             bail!(
                 "Instance \"{}\" is missing some required information.\n\
@@ -221,6 +287,18 @@ pub fn snapshot_project_node(
     }
 
     for (key, unresolved) in &node.properties {
+        let ref_path = unresolved.as_ref_path(&class_name, key).with_context(|| {
+            format!(
+                "Unresolvable property in project at path {}",
+                project_path.display()
+            )
+        })?;
+
+        if let Some(ref_path) = ref_path {
+            metadata.ref_paths.insert(key.clone(), ref_path.to_owned());
+            continue;
+        }
+
         let value = unresolved
             .clone()
             .resolve(&class_name, key)
@@ -286,14 +364,23 @@ pub fn snapshot_project_node(
         parent_class.map(|name| name.to_owned()),
     ));
 
-    Ok(Some(InstanceSnapshot {
+    let mut snapshot = InstanceSnapshot {
         snapshot_id: Ref::none(),
         name,
         class_name,
         properties,
         children,
         metadata,
-    }))
+    };
+    apply_class_defaults(context, &mut snapshot);
+
+    Ok(Some(snapshot))
+}
+
+/// Whether a class that came from a `$path` is one that a directory would turn
+/// into, which can be replaced by a `$className` or an inferred class.
+fn is_directory_class(context: &InstanceContext, class_name: &str) -> bool {
+    class_name == "Folder" || class_name == context.directory_class_name()
 }
 
 fn infer_class_name(name: &str, parent_class: Option<&str>) -> Option<Cow<'static, str>> {
@@ -332,8 +419,11 @@ fn infer_class_name(name: &str, parent_class: Option<&str>) -> Option<Cow<'stati
 mod test {
     use super::*;
 
+    use std::path::PathBuf;
+
     use maplit::hashmap;
     use memofs::{InMemoryFs, VfsSnapshot};
+    use rbx_dom_weak::types::Variant;
 
     #[ignore = "Functionality moved to root snapshot middleware"]
     #[test]
@@ -480,6 +570,273 @@ mod test {
         insta::assert_yaml_snapshot!(instance_snapshot);
     }
 
+    #[test]
+    fn project_with_attributes() {
+        let _ = env_logger::try_init();
+
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.project.json",
+            VfsSnapshot::file(
+                r#"
+                    {
+                        "name": "attributes",
+                        "tree": {
+                            "$className": "Folder",
+                            "$attributes": {
+                                "Health": 100,
+                                "Team": "Red"
+                            }
+                        }
+                    }
+                "#,
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_project(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.project.json"),
+            "NOT_IN_SNAPSHOT",
+        )
+        .expect("snapshot error")
+        .expect("snapshot returned no instances");
+
+        let attributes = match instance_snapshot.properties.get("Attributes") {
+            Some(Variant::Attributes(attributes)) => attributes,
+            other => panic!("expected Attributes property, got {:?}", other),
+        };
+
+        assert_eq!(attributes.len(), 2);
+        assert_eq!(attributes.get("Health"), Some(&Variant::Float64(100.0)));
+        assert_eq!(
+            attributes.get("Team"),
+            Some(&Variant::String("Red".to_owned()))
+        );
+    }
+
+    #[test]
+    fn project_with_ignore_unknown_instances() {
+        let _ = env_logger::try_init();
+
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.project.json",
+            VfsSnapshot::file(
+                r#"
+                    {
+                        "name": "ignore-unknown-instances",
+                        "tree": {
+                            "$className": "Folder",
+                            "Kept": {
+                                "$className": "Folder",
+                                "$ignoreUnknownInstances": true
+                            },
+                            "Authoritative": {
+                                "$className": "Folder",
+                                "$ignoreUnknownInstances": false
+                            },
+                            "Implicit": {
+                                "$className": "Folder"
+                            }
+                        }
+                    }
+                "#,
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_project(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.project.json"),
+            "NOT_IN_SNAPSHOT",
+        )
+        .expect("snapshot error")
+        .expect("snapshot returned no instances");
+
+        let ignores_unknown = |name: &str| {
+            instance_snapshot
+                .children
+                .iter()
+                .find(|child| child.name == name)
+                .unwrap()
+                .metadata
+                .ignore_unknown_instances
+        };
+
+        assert!(ignores_unknown("Kept"));
+        assert!(!ignores_unknown("Authoritative"));
+        assert!(ignores_unknown("Implicit"));
+    }
+
+    #[test]
+    fn project_include_shared_base() {
+        let _ = env_logger::try_init();
+
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir(hashmap! {
+                "shared" => VfsSnapshot::dir(hashmap! {
+                    "base.project.json" => VfsSnapshot::file(r#"
+                        {
+                            "name": "base",
+                            "tree": {
+                                "$className": "DataModel",
+                                "ReplicatedStorage": {
+                                    "Shared": {
+                                        "$path": "src"
+                                    }
+                                },
+                                "Workspace": {
+                                    "$properties": {
+                                        "Gravity": 100
+                                    }
+                                }
+                            }
+                        }
+                    "#),
+                    "src" => VfsSnapshot::dir(hashmap! {
+                        "Util.lua" => VfsSnapshot::file("return {}"),
+                    }),
+                }),
+                "racing.project.json" => VfsSnapshot::file(r#"
+                    {
+                        "name": "racing",
+                        "tree": {
+                            "$include": "shared/base.project.json",
+                            "ServerScriptService": {
+                                "Race": {
+                                    "$className": "Folder"
+                                }
+                            },
+                            "Workspace": {
+                                "$properties": {
+                                    "Gravity": 50
+                                }
+                            }
+                        }
+                    }
+                "#),
+                "obby.project.json" => VfsSnapshot::file(r#"
+                    {
+                        "name": "obby",
+                        "tree": {
+                            "$include": "shared/base.project.json"
+                        }
+                    }
+                "#),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let snapshot = |path: &str| {
+            snapshot_project(
+                &InstanceContext::default(),
+                &vfs,
+                Path::new(path),
+                "NOT_IN_SNAPSHOT",
+            )
+            .expect("snapshot error")
+            .expect("snapshot returned no instances")
+        };
+
+        fn child<'a>(snapshot: &'a InstanceSnapshot, name: &str) -> &'a InstanceSnapshot {
+            snapshot
+                .children
+                .iter()
+                .find(|child| child.name == name)
+                .unwrap_or_else(|| panic!("{} has no child named {}", snapshot.name, name))
+        }
+
+        let racing = snapshot("/foo/racing.project.json");
+        let obby = snapshot("/foo/obby.project.json");
+
+        assert_eq!(racing.class_name, "DataModel");
+        assert_eq!(
+            child(&racing, "Workspace").properties.get("Gravity"),
+            Some(&Variant::Float32(50.0))
+        );
+        assert_eq!(
+            child(child(&racing, "ServerScriptService"), "Race").class_name,
+            "Folder"
+        );
+        assert!(racing
+            .metadata
+            .relevant_paths
+            .contains(&PathBuf::from("/foo/shared/base.project.json")));
+
+        assert_eq!(obby.class_name, "DataModel");
+        assert_eq!(
+            child(&obby, "Workspace").properties.get("Gravity"),
+            Some(&Variant::Float32(100.0))
+        );
+        assert!(!obby
+            .children
+            .iter()
+            .any(|child| child.name == "ServerScriptService"));
+
+        // Paths in the included project are relative to that project.
+        for project in [&racing, &obby] {
+            let shared = child(child(project, "ReplicatedStorage"), "Shared");
+            assert_eq!(child(shared, "Util").class_name, "ModuleScript");
+        }
+    }
+
+    #[test]
+    fn project_include_cycle() {
+        let _ = env_logger::try_init();
+
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir(hashmap! {
+                "a.project.json" => VfsSnapshot::file(r#"
+                    {
+                        "tree": {
+                            "$include": "b.project.json"
+                        }
+                    }
+                "#),
+                "b.project.json" => VfsSnapshot::file(r#"
+                    {
+                        "tree": {
+                            "$className": "Folder",
+                            "Nested": {
+                                "$include": "a.project.json"
+                            }
+                        }
+                    }
+                "#),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let err = snapshot_project(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo/a.project.json"),
+            "NOT_IN_SNAPSHOT",
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Projects cannot include themselves: /foo/a.project.json -> \
+             /foo/b.project.json -> /foo/a.project.json"
+        );
+    }
+
     #[test]
     fn project_with_children() {
         let _ = env_logger::try_init();
@@ -553,6 +910,99 @@ mod test {
         insta::assert_yaml_snapshot!(instance_snapshot);
     }
 
+    #[test]
+    fn project_with_path_to_model_and_different_class() {
+        let _ = env_logger::try_init();
+
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir(hashmap! {
+                "default.project.json" => VfsSnapshot::file(r#"
+                    {
+                        "name": "path-project",
+                        "tree": {
+                            "$className": "Folder",
+                            "Mounted": {
+                                "$className": "Tool",
+                                "$path": "weapon.model.json"
+                            }
+                        }
+                    }
+                "#),
+                "weapon.model.json" => VfsSnapshot::file(r#"{ "className": "Model" }"#),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let err = snapshot_project(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo/default.project.json"),
+            "NOT_IN_SNAPSHOT",
+        )
+        .unwrap_err();
+
+        let message = format!("{:#}", err);
+        assert!(message.contains(
+            r#"in both the project file (as "Tool") and from the filesystem (as "Model")"#
+        ));
+        assert!(message.contains("$path must refer to a Folder.\n\nProject path"));
+    }
+
+    #[test]
+    fn project_with_missing_path() {
+        let _ = env_logger::try_init();
+
+        let project = |optional: bool| {
+            format!(
+                r#"{{
+                    "name": "missing-path",
+                    "tree": {{
+                        "$className": "Folder",
+                        "Missing": {{
+                            "$path": "missing",
+                            "$optional": {}
+                        }}
+                    }}
+                }}"#,
+                optional
+            )
+        };
+
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir(hashmap! {
+                "optional.project.json" => VfsSnapshot::file(project(true)),
+                "required.project.json" => VfsSnapshot::file(project(false)),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_project(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo/optional.project.json"),
+            "NOT_IN_SNAPSHOT",
+        )
+        .expect("snapshot error")
+        .expect("snapshot returned no instances");
+        assert!(instance_snapshot.children.is_empty());
+
+        assert!(snapshot_project(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo/required.project.json"),
+            "NOT_IN_SNAPSHOT",
+        )
+        .is_err());
+    }
+
     #[test]
     fn project_with_path_to_project() {
         let _ = env_logger::try_init();
@@ -724,4 +1174,54 @@ mod test {
 
         insta::assert_yaml_snapshot!(instance_snapshot);
     }
+
+    #[test]
+    fn project_unknown_key() {
+        let _ = env_logger::try_init();
+
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.project.json",
+            VfsSnapshot::file(
+                r#"
+                    {
+                        "name": "unknown-key",
+                        "tree": {
+                            "$className": "Folder",
+                            "$classNme": "Model",
+                            "Child": { "$className": "Folder" }
+                        }
+                    }
+                "#,
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        // Unknown keys are ignored instead of becoming children.
+        let lenient = snapshot_project(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.project.json"),
+            "NOT_IN_SNAPSHOT",
+        )
+        .expect("snapshot error")
+        .expect("snapshot returned no instances");
+
+        assert_eq!(lenient.class_name, "Folder");
+        assert_eq!(lenient.children.len(), 1);
+        assert_eq!(lenient.children[0].name, "Child");
+
+        let mut strict_context = InstanceContext::default();
+        strict_context.set_strict(true);
+
+        let strict = snapshot_project(
+            &strict_context,
+            &vfs,
+            Path::new("/foo.project.json"),
+            "NOT_IN_SNAPSHOT",
+        );
+        assert!(strict.is_err());
+    }
 }
@@ -12,8 +12,8 @@ pub fn snapshot_rbxm(
     path: &Path,
     name: &str,
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
-    let temp_tree = rbx_binary::from_reader(vfs.read(path)?.as_slice())
-        .with_context(|| format!("Malformed rbxm file: {}", path.display()))?;
+    let temp_tree =
+        rbx_binary::from_reader(vfs.read(path)?.as_slice()).context("Malformed rbxm file")?;
 
     let root_instance = temp_tree.root();
     let children = root_instance.children();
@@ -44,6 +44,9 @@ mod test {
     use super::*;
 
     use memofs::{InMemoryFs, VfsSnapshot};
+    use rbx_dom_weak::{types::Variant, InstanceBuilder, WeakDom};
+
+    use crate::snapshot::{apply_patch_set, compute_patch_set, RojoTree};
 
     #[test]
     fn model_from_vfs() {
@@ -54,11 +57,11 @@ mod test {
         )
         .unwrap();
 
-        let mut vfs = Vfs::new(imfs);
+        let vfs = Vfs::new(imfs);
 
         let instance_snapshot = snapshot_rbxm(
             &InstanceContext::default(),
-            &mut vfs,
+            &vfs,
             Path::new("/foo.rbxm"),
             "foo",
         )
@@ -75,4 +78,70 @@ mod test {
         // property that currently deserializes incorrectly.
         // See: https://github.com/Roblox/rbx-dom/issues/49
     }
+
+    /// Encodes a model with a Part and an ObjectValue that points to it.
+    fn model_with_ref(part_name: &str) -> Vec<u8> {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let model = dom.insert(dom.root_ref(), InstanceBuilder::new("Model"));
+        let part = dom.insert(model, InstanceBuilder::new("Part").with_name(part_name));
+        dom.insert(
+            model,
+            InstanceBuilder::new("ObjectValue")
+                .with_name("Pointer")
+                .with_property("Value", part),
+        );
+
+        let mut contents = Vec::new();
+        rbx_binary::to_writer(&mut contents, &dom, &[model]).unwrap();
+        contents
+    }
+
+    /// Finds the instance that `Pointer.Value` refers to in the tree and
+    /// returns its name.
+    fn pointer_target(tree: &RojoTree) -> String {
+        let model = tree.get_instance(tree.get_root_id()).unwrap();
+
+        let pointer = model
+            .children()
+            .iter()
+            .map(|&id| tree.get_instance(id).unwrap())
+            .find(|child| child.name() == "Pointer")
+            .unwrap();
+
+        let target = match pointer.properties().get("Value") {
+            Some(Variant::Ref(target)) => *target,
+            other => panic!("Pointer.Value was not a Ref: {:?}", other),
+        };
+        assert!(model.children().contains(&target));
+
+        tree.get_instance(target).unwrap().name().to_owned()
+    }
+
+    #[test]
+    fn refs_within_model() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo.rbxm", VfsSnapshot::file(model_with_ref("Target")))
+            .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let context = InstanceContext::default();
+        let snapshot = snapshot_rbxm(&context, &vfs, Path::new("/foo.rbxm"), "foo").unwrap();
+
+        let mut tree = RojoTree::new(InstanceSnapshot::new());
+        let root_id = tree.get_root_id();
+        let patch_set = compute_patch_set(snapshot, &tree, root_id);
+        apply_patch_set(&mut tree, patch_set);
+
+        assert_eq!(pointer_target(&tree), "Target");
+
+        // Reading the model again gives every instance a new referent, which
+        // needs to be rewired to the instances already in the tree.
+        vfs.write("/foo.rbxm", model_with_ref("Renamed")).unwrap();
+        let snapshot = snapshot_rbxm(&context, &vfs, Path::new("/foo.rbxm"), "foo").unwrap();
+        let patch_set = compute_patch_set(snapshot, &tree, root_id);
+        apply_patch_set(&mut tree, patch_set);
+
+        assert_eq!(pointer_target(&tree), "Renamed");
+    }
 }
@@ -11,11 +11,18 @@ pub fn snapshot_rbxmx(
     path: &Path,
     name: &str,
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
-    let options = rbx_xml::DecodeOptions::new()
-        .property_behavior(rbx_xml::DecodePropertyBehavior::ReadUnknown);
+    // Unknown properties are usually ones Rojo's reflection database doesn't
+    // know about yet, but they can also be a sign of a corrupt file.
+    let property_behavior = if context.strict {
+        rbx_xml::DecodePropertyBehavior::ErrorOnUnknown
+    } else {
+        rbx_xml::DecodePropertyBehavior::ReadUnknown
+    };
+
+    let options = rbx_xml::DecodeOptions::new().property_behavior(property_behavior);
 
     let temp_tree = rbx_xml::from_reader(vfs.read(path)?.as_slice(), options)
-        .with_context(|| format!("Malformed rbxm file: {}", path.display()))?;
+        .context("Malformed rbxmx file")?;
 
     let root_instance = temp_tree.root();
     let children = root_instance.children();
@@ -66,11 +73,11 @@ mod test {
         )
         .unwrap();
 
-        let mut vfs = Vfs::new(imfs);
+        let vfs = Vfs::new(imfs);
 
         let instance_snapshot = snapshot_rbxmx(
             &InstanceContext::default(),
-            &mut vfs,
+            &vfs,
             Path::new("/foo.rbxmx"),
             "foo",
         )
@@ -82,4 +89,46 @@ mod test {
         assert_eq!(instance_snapshot.properties, Default::default());
         assert_eq!(instance_snapshot.children, Vec::new());
     }
+    #[test]
+    fn unknown_property() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.rbxmx",
+            VfsSnapshot::file(
+                r#"
+                    <roblox version="4">
+                        <Item class="Folder" referent="0">
+                            <Properties>
+                                <string name="Name">foo</string>
+                                <string name="NotARealProperty">oops</string>
+                            </Properties>
+                        </Item>
+                    </roblox>
+                "#,
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let lenient = snapshot_rbxmx(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.rbxmx"),
+            "foo",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            lenient.properties.get("NotARealProperty"),
+            Some(&"oops".into())
+        );
+
+        let mut strict_context = InstanceContext::default();
+        strict_context.set_strict(true);
+
+        let strict = snapshot_rbxmx(&strict_context, &vfs, Path::new("/foo.rbxmx"), "foo");
+        assert!(strict.is_err());
+    }
 }
@@ -0,0 +1,192 @@
+//! Turns Refs that were given by path, like `{ "Ref": "Workspace.Thing" }`,
+//! into real Refs once the whole tree has been snapshotted.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::format_err;
+use rbx_dom_weak::types::{Ref, Variant};
+
+use crate::snapshot::{InstanceSnapshot, RojoTree};
+
+/// Points every Ref given by path in the snapshot at the instance in the same
+/// snapshot with that path. Paths start from the root of the snapshot, which
+/// should be the root of the whole tree.
+///
+/// Instances that are pointed to are given a snapshot ID if they don't have one
+/// already, which is turned into the instance's real ID when the snapshot is
+/// applied to a tree.
+pub fn resolve_ref_paths(root: &mut InstanceSnapshot) -> anyhow::Result<()> {
+    let mut paths = BTreeMap::new();
+    collect_ref_paths(root, &mut paths);
+
+    let mut targets = HashMap::new();
+
+    for (path, referrer) in paths {
+        let target = match find_in_snapshot(root, &path) {
+            Some(target) => target,
+            None => return Err(not_found(&path, &referrer)),
+        };
+
+        if target.snapshot_id.is_none() {
+            target.snapshot_id = Ref::new();
+        }
+
+        targets.insert(path, target.snapshot_id);
+    }
+
+    apply_targets(root, &targets);
+
+    Ok(())
+}
+
+/// Like `resolve_ref_paths`, but finds the instances that are pointed to in
+/// `tree` instead. This is used when only part of the tree is snapshotted
+/// again after a change.
+pub fn resolve_ref_paths_in_tree(
+    snapshot: &mut InstanceSnapshot,
+    tree: &RojoTree,
+) -> anyhow::Result<()> {
+    let mut paths = BTreeMap::new();
+    collect_ref_paths(snapshot, &mut paths);
+
+    let mut targets = HashMap::new();
+
+    for (path, referrer) in paths {
+        match tree.find_by_path(&path) {
+            Some(target) => targets.insert(path, target),
+            None => return Err(not_found(&path, &referrer)),
+        };
+    }
+
+    apply_targets(snapshot, &targets);
+
+    Ok(())
+}
+
+/// Describes the first property that referred to a path, for errors.
+struct Referrer {
+    instance_name: String,
+    property: String,
+}
+
+fn collect_ref_paths(snapshot: &InstanceSnapshot, paths: &mut BTreeMap<String, Referrer>) {
+    for (property, path) in &snapshot.metadata.ref_paths {
+        paths.entry(path.clone()).or_insert_with(|| Referrer {
+            instance_name: snapshot.name.to_string(),
+            property: property.clone(),
+        });
+    }
+
+    for child in &snapshot.children {
+        collect_ref_paths(child, paths);
+    }
+}
+
+fn apply_targets(snapshot: &mut InstanceSnapshot, targets: &HashMap<String, Ref>) {
+    for (property, path) in &snapshot.metadata.ref_paths {
+        if let Some(&target) = targets.get(path) {
+            snapshot
+                .properties
+                .insert(property.clone(), Variant::Ref(target));
+        }
+    }
+
+    for child in &mut snapshot.children {
+        apply_targets(child, targets);
+    }
+}
+
+fn find_in_snapshot<'a>(
+    root: &'a mut InstanceSnapshot,
+    path: &str,
+) -> Option<&'a mut InstanceSnapshot> {
+    let mut current = root;
+
+    for name in path.split('.') {
+        current = current
+            .children
+            .iter_mut()
+            .find(|child| child.name == name)?;
+    }
+
+    Some(current)
+}
+
+fn not_found(path: &str, referrer: &Referrer) -> anyhow::Error {
+    format_err!(
+        "Could not find an instance at path {}, which is referred to by property {} of {}",
+        path,
+        referrer.property,
+        referrer.instance_name
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::snapshot::{apply_patch_set, compute_patch_set, InstanceMetadata};
+
+    fn pointer(path: &str) -> InstanceSnapshot {
+        let mut metadata = InstanceMetadata::new();
+        metadata
+            .ref_paths
+            .insert("Value".to_owned(), path.to_owned());
+
+        InstanceSnapshot::new()
+            .name("Pointer")
+            .class_name("ObjectValue")
+            .metadata(metadata)
+    }
+
+    fn game(pointer_path: &str) -> InstanceSnapshot {
+        InstanceSnapshot::new()
+            .name("Game")
+            .class_name("DataModel")
+            .children(vec![
+                InstanceSnapshot::new()
+                    .name("ReplicatedStorage")
+                    .class_name("ReplicatedStorage")
+                    .children(vec![InstanceSnapshot::new()
+                        .name("Thing")
+                        .class_name("Part")]),
+                InstanceSnapshot::new()
+                    .name("Workspace")
+                    .class_name("Workspace")
+                    .children(vec![pointer(pointer_path)]),
+            ])
+    }
+
+    #[test]
+    fn resolves_after_patch() {
+        let mut snapshot = game("ReplicatedStorage.Thing");
+        resolve_ref_paths(&mut snapshot).unwrap();
+
+        let mut tree = RojoTree::new(InstanceSnapshot::new());
+        let root_id = tree.get_root_id();
+        let patch_set = compute_patch_set(Some(snapshot), &tree, root_id);
+        apply_patch_set(&mut tree, patch_set);
+
+        let thing = tree.find_by_path("ReplicatedStorage.Thing").unwrap();
+        let pointer_id = tree.find_by_path("Workspace.Pointer").unwrap();
+        let instance = tree.get_instance(pointer_id).unwrap();
+        assert_eq!(instance.properties()["Value"], Variant::Ref(thing));
+
+        // Snapshotting part of the tree again finds the same instance.
+        let mut snapshot = pointer("ReplicatedStorage.Thing");
+        resolve_ref_paths_in_tree(&mut snapshot, &tree).unwrap();
+        assert_eq!(snapshot.properties["Value"], Variant::Ref(thing));
+    }
+
+    #[test]
+    fn unresolvable_path() {
+        let mut snapshot = game("ReplicatedStorage.Missing");
+        let err = resolve_ref_paths(&mut snapshot).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Could not find an instance at path ReplicatedStorage.Missing, \
+             which is referred to by property Value of Pointer"
+        );
+    }
+}
@@ -19,8 +19,7 @@ pub fn snapshot_toml(
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
     let contents = vfs.read(path)?;
 
-    let value: toml::Value = toml::from_slice(&contents)
-        .with_context(|| format!("File contains malformed TOML: {}", path.display()))?;
+    let value: toml::Value = toml::from_slice(&contents).context("File contains malformed TOML")?;
 
     let as_lua = toml_to_lua(value).to_string();
 
@@ -108,11 +107,11 @@ mod test {
         )
         .unwrap();
 
-        let mut vfs = Vfs::new(imfs.clone());
+        let vfs = Vfs::new(imfs.clone());
 
         let instance_snapshot = snapshot_toml(
             &InstanceContext::default(),
-            &mut vfs,
+            &vfs,
             Path::new("/foo.toml"),
             "foo",
         )
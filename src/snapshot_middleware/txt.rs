@@ -1,11 +1,12 @@
 use std::{path::Path, str};
 
+use anyhow::bail;
 use maplit::hashmap;
 use memofs::{IoResultExt, Vfs};
 
 use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
 
-use super::meta_file::AdjacentMetadata;
+use super::{meta_file::AdjacentMetadata, util::prune_default_properties};
 
 pub fn snapshot_txt(
     context: &InstanceContext,
@@ -16,48 +17,109 @@ pub fn snapshot_txt(
     let contents = vfs.read_to_string(path)?;
     let contents_str = contents.as_str();
 
-    let properties = hashmap! {
-        "Value".to_owned() => contents_str.into(),
+    let meta_path = path.with_file_name(format!("{}.meta.json", name));
+
+    let mut metadata = match vfs.read(&meta_path).with_not_found()? {
+        Some(meta_contents) => Some(AdjacentMetadata::from_slice(
+            &meta_contents,
+            meta_path.clone(),
+        )?),
+        None => None,
     };
 
-    let meta_path = path.with_file_name(format!("{}.meta.json", name));
+    // A class from the meta file takes priority over one from a sync rule.
+    let class_name = metadata
+        .as_mut()
+        .and_then(|meta| meta.class_name.take())
+        .or_else(|| {
+            context
+                .get_user_sync_rule(path)
+                .and_then(|rule| rule.class_name.clone())
+        });
+
+    let (class_name, properties) = match class_name.as_deref() {
+        None | Some("StringValue") => (
+            "StringValue",
+            hashmap! {
+                "Value".to_owned() => contents_str.into(),
+            },
+        ),
+        Some("ModuleScript") => (
+            "ModuleScript",
+            hashmap! {
+                "Source".to_owned() => format!("return {}", long_string(contents_str)).into(),
+            },
+        ),
+        Some(other) => bail!(
+            "Text files can only become a StringValue or a ModuleScript, \
+             but className is set to {} for {}",
+            other,
+            path.display()
+        ),
+    };
 
     let mut snapshot = InstanceSnapshot::new()
         .name(name)
-        .class_name("StringValue")
+        .class_name(class_name)
         .properties(properties)
         .metadata(
             InstanceMetadata::new()
                 .instigating_source(path)
-                .relevant_paths(vec![path.to_path_buf(), meta_path.clone()])
+                .relevant_paths(vec![path.to_path_buf(), meta_path])
                 .context(context),
         );
 
-    if let Some(meta_contents) = vfs.read(&meta_path).with_not_found()? {
-        let mut metadata = AdjacentMetadata::from_slice(&meta_contents, meta_path)?;
+    prune_default_properties(context, &mut snapshot);
+
+    if let Some(mut metadata) = metadata {
         metadata.apply_all(&mut snapshot)?;
     }
 
     Ok(Some(snapshot))
 }
 
+/// Formats the given text as a Lua long string literal, picking a level of
+/// long brackets that doesn't appear in the text.
+fn long_string(contents: &str) -> String {
+    let mut level = 0;
+    while contents.contains(&format!("]{}]", "=".repeat(level))) {
+        level += 1;
+    }
+
+    let equals = "=".repeat(level);
+
+    // Lua skips a newline that immediately follows the opening bracket, so we
+    // add one of our own to keep a leading newline in the text intact.
+    let newline = if contents.starts_with('\n') || contents.starts_with('\r') {
+        "\n"
+    } else {
+        ""
+    };
+
+    format!("[{0}[{1}{2}]{0}]", equals, newline, contents)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    use std::path::PathBuf;
+
     use memofs::{InMemoryFs, VfsSnapshot};
 
+    use crate::{glob::Glob, snapshot::SyncRule, snapshot_middleware::Middleware};
+
     #[test]
     fn instance_from_vfs() {
         let mut imfs = InMemoryFs::new();
         imfs.load_snapshot("/foo.txt", VfsSnapshot::file("Hello there!"))
             .unwrap();
 
-        let mut vfs = Vfs::new(imfs.clone());
+        let vfs = Vfs::new(imfs.clone());
 
         let instance_snapshot = snapshot_txt(
             &InstanceContext::default(),
-            &mut vfs,
+            &vfs,
             Path::new("/foo.txt"),
             "foo",
         )
@@ -66,4 +128,112 @@ mod test {
 
         insta::assert_yaml_snapshot!(instance_snapshot);
     }
+
+    #[test]
+    fn module_script_from_meta() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/root",
+            VfsSnapshot::dir([
+                (
+                    "foo.txt",
+                    VfsSnapshot::file("local t = a[b[1]]\n-- ]=] too"),
+                ),
+                (
+                    "foo.meta.json",
+                    VfsSnapshot::file(r#"{ "className": "ModuleScript" }"#),
+                ),
+            ]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let instance_snapshot = snapshot_txt(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/root/foo.txt"),
+            "foo",
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::assert_yaml_snapshot!(instance_snapshot);
+    }
+
+    #[test]
+    fn class_from_sync_rule() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/root",
+            VfsSnapshot::dir([
+                ("page.html", VfsSnapshot::file("<p>hi</p>")),
+                ("style.css", VfsSnapshot::file("p { color: red; }")),
+                (
+                    "style.meta.json",
+                    VfsSnapshot::file(r#"{ "className": "StringValue" }"#),
+                ),
+            ]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let mut context = InstanceContext::default();
+        context.add_sync_rules([SyncRule {
+            include: Glob::new("*.{html,css}").unwrap(),
+            exclude: None,
+            middleware: Middleware::Text,
+            suffix: None,
+            class_name: Some("ModuleScript".to_owned()),
+            base_path: PathBuf::from("/root"),
+        }]);
+
+        let page = snapshot_txt(&context, &vfs, Path::new("/root/page.html"), "page")
+            .unwrap()
+            .unwrap();
+        assert_eq!(page.class_name, "ModuleScript");
+        assert_eq!(page.properties["Source"], "return [[<p>hi</p>]]".into());
+
+        // The meta file wins over the sync rule.
+        let style = snapshot_txt(&context, &vfs, Path::new("/root/style.css"), "style")
+            .unwrap()
+            .unwrap();
+        assert_eq!(style.class_name, "StringValue");
+        assert_eq!(style.properties["Value"], "p { color: red; }".into());
+    }
+
+    #[test]
+    fn class_without_string_property() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/root/foo.txt", VfsSnapshot::file("Hello there!"))
+            .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let mut context = InstanceContext::default();
+        context.add_sync_rules([SyncRule {
+            include: Glob::new("*.txt").unwrap(),
+            exclude: None,
+            middleware: Middleware::Text,
+            suffix: None,
+            class_name: Some("Part".to_owned()),
+            base_path: PathBuf::from("/root"),
+        }]);
+
+        let err = snapshot_txt(&context, &vfs, Path::new("/root/foo.txt"), "foo").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Text files can only become a StringValue or a ModuleScript, \
+             but className is set to Part for /root/foo.txt"
+        );
+    }
+
+    #[test]
+    fn long_string_escaping() {
+        assert_eq!(long_string("hello"), "[[hello]]");
+        assert_eq!(long_string("a]]b"), "[=[a]]b]=]");
+        assert_eq!(long_string("a]]b]=]c"), "[==[a]]b]=]c]==]");
+        assert_eq!(long_string("\nhello"), "[[\n\nhello]]");
+    }
 }
@@ -1,6 +1,9 @@
 use std::path::Path;
 
 use anyhow::Context;
+use rbx_dom_weak::types::Variant;
+
+use crate::snapshot::{InstanceContext, InstanceSnapshot};
 
 /// If the given string ends up with the given suffix, returns the portion of
 /// the string before the suffix.
@@ -46,3 +49,117 @@ where
 pub fn emit_legacy_scripts_default() -> Option<bool> {
     Some(true)
 }
+
+/// Removes properties from the snapshot that are equal to the default value
+/// for its class, if the context asks for it. Middleware should call this
+/// before applying meta files so that explicitly set properties are kept.
+pub fn prune_default_properties(context: &InstanceContext, snapshot: &mut InstanceSnapshot) {
+    if !context.prune_default_properties {
+        return;
+    }
+
+    let class_name = &snapshot.class_name;
+    snapshot
+        .properties
+        .retain(|name, value| find_default_property(class_name, name) != Some(value));
+}
+
+/// Gives the snapshot and all of its descendants any properties from the
+/// context's class defaults that they don't already have. Middleware should
+/// call this after applying meta files so that those take precedence.
+pub fn apply_class_defaults(context: &InstanceContext, snapshot: &mut InstanceSnapshot) {
+    if context.class_defaults.is_empty() {
+        return;
+    }
+
+    if let Some(defaults) = context.class_defaults.get(snapshot.class_name.as_ref()) {
+        for (name, value) in defaults {
+            snapshot
+                .properties
+                .entry(name.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+
+    for child in &mut snapshot.children {
+        apply_class_defaults(context, child);
+    }
+}
+
+fn find_default_property(class_name: &str, prop_name: &str) -> Option<&'static Variant> {
+    let database = rbx_reflection_database::get();
+    let mut current_class_name = class_name;
+
+    loop {
+        let class = database.classes.get(current_class_name)?;
+        if let Some(default) = class.default_properties.get(prop_name) {
+            return Some(default);
+        }
+
+        current_class_name = class.superclass.as_deref()?;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn prunes_defaults_only() {
+        let mut context = InstanceContext::default();
+        context.set_prune_default_properties(true);
+
+        let mut snapshot = InstanceSnapshot::new()
+            .class_name("Part")
+            .property("Anchored", false)
+            .property("CanCollide", false);
+
+        prune_default_properties(&context, &mut snapshot);
+
+        assert!(!snapshot.properties.contains_key("Anchored"));
+        assert_eq!(snapshot.properties.get("CanCollide"), Some(&false.into()));
+    }
+
+    #[test]
+    fn does_nothing_when_disabled() {
+        let mut snapshot = InstanceSnapshot::new()
+            .class_name("Part")
+            .property("Anchored", false);
+
+        prune_default_properties(&InstanceContext::default(), &mut snapshot);
+
+        assert_eq!(snapshot.properties.get("Anchored"), Some(&false.into()));
+    }
+
+    #[test]
+    fn class_defaults_fill_missing_properties() {
+        let mut context = InstanceContext::default();
+        context.add_class_defaults(
+            "Part",
+            [
+                ("Anchored".to_owned(), true.into()),
+                ("CanCollide".to_owned(), false.into()),
+            ]
+            .into(),
+        );
+
+        let mut snapshot = InstanceSnapshot::new()
+            .class_name("Part")
+            .property("Anchored", false)
+            .children(vec![
+                InstanceSnapshot::new().class_name("Part"),
+                InstanceSnapshot::new().class_name("Folder"),
+            ]);
+
+        apply_class_defaults(&context, &mut snapshot);
+
+        assert_eq!(snapshot.properties.get("Anchored"), Some(&false.into()));
+        assert_eq!(snapshot.properties.get("CanCollide"), Some(&false.into()));
+
+        let child = &snapshot.children[0];
+        assert_eq!(child.properties.get("Anchored"), Some(&true.into()));
+        assert_eq!(child.properties.get("CanCollide"), Some(&false.into()));
+
+        assert!(snapshot.children[1].properties.is_empty());
+    }
+}
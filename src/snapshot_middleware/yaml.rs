@@ -0,0 +1,155 @@
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use maplit::hashmap;
+use memofs::{IoResultExt, Vfs};
+use serde::Deserialize;
+
+use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
+
+use super::{json::json_to_lua, meta_file::AdjacentMetadata};
+
+pub fn snapshot_yaml(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let contents = vfs.read(path)?;
+
+    let mut documents = serde_yaml::Deserializer::from_slice(&contents);
+
+    // An empty file has no documents at all, which we treat as `nil`.
+    let value = match documents.next() {
+        Some(document) => {
+            serde_json::Value::deserialize(document).context("File contains malformed YAML")?
+        }
+        None => serde_json::Value::Null,
+    };
+
+    if documents.next().is_some() {
+        bail!(
+            "File contains more than one YAML document, which is not supported: {}",
+            path.display()
+        );
+    }
+
+    let as_lua = json_to_lua(value).to_string();
+
+    let properties = hashmap! {
+        "Source".to_owned() => as_lua.into(),
+    };
+
+    let meta_path = path.with_file_name(format!("{}.meta.json", name));
+
+    let mut snapshot = InstanceSnapshot::new()
+        .name(name)
+        .class_name("ModuleScript")
+        .properties(properties)
+        .metadata(
+            InstanceMetadata::new()
+                .instigating_source(path)
+                .relevant_paths(vec![path.to_path_buf(), meta_path.clone()])
+                .context(context),
+        );
+
+    if let Some(meta_contents) = vfs.read(&meta_path).with_not_found()? {
+        let mut metadata = AdjacentMetadata::from_slice(&meta_contents, meta_path)?;
+        metadata.apply_all(&mut snapshot)?;
+    }
+
+    Ok(Some(snapshot))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use memofs::{InMemoryFs, VfsSnapshot};
+
+    use crate::snapshot_middleware::snapshot_from_vfs;
+
+    #[test]
+    fn instance_from_vfs() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.yaml",
+            VfsSnapshot::file(
+                r#"
+array: [1, 2, 3]
+object: &object
+  hello: world
+alias: *object
+"true": true
+"false": false
+"null": null
+int: 1234
+float: 1234.5452
+1invalidident: nice
+"#,
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let instance_snapshot = snapshot_yaml(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.yaml"),
+            "foo",
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::assert_yaml_snapshot!(instance_snapshot);
+    }
+
+    #[test]
+    fn multiple_documents() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo.yml", VfsSnapshot::file("a: 1\n---\nb: 2\n"))
+            .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let error = snapshot_yaml(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.yml"),
+            "foo",
+        )
+        .unwrap_err();
+
+        assert!(error.to_string().contains("more than one YAML document"));
+    }
+
+    #[test]
+    fn needs_sync_rule() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir(hashmap! {
+                "config.yml" => VfsSnapshot::file("a: 1\n---\nb: 2\n"),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        // YAML files are left alone unless a project asks for them, since
+        // things like CI configs often end up inside synced folders.
+        let snapshot = snapshot_from_vfs(&InstanceContext::default(), &vfs, Path::new("/foo"))
+            .unwrap()
+            .unwrap();
+        assert!(snapshot.children.is_empty());
+
+        let mut context = InstanceContext::default();
+        context.add_sync_rules([
+            serde_json::from_str(r#"{ "pattern": "*.yml", "use": "yaml" }"#).unwrap(),
+        ]);
+
+        let error = snapshot_from_vfs(&context, &vfs, Path::new("/foo")).unwrap_err();
+        assert!(format!("{:#}", error).contains("more than one YAML document"));
+    }
+}
@@ -10,6 +10,10 @@ use walkdir::WalkDir;
 pub static ROJO_PATH: &str = env!("CARGO_BIN_EXE_rojo");
 pub static BUILD_TESTS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/rojo-test/build-tests");
 pub static SERVE_TESTS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/rojo-test/serve-tests");
+pub static FMT_PROJECT_TESTS_PATH: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/rojo-test/fmt-project-tests");
+pub static SOURCEMAP_TESTS_PATH: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/rojo-test/sourcemap-tests");
 
 pub fn get_working_dir_path() -> PathBuf {
     let mut manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
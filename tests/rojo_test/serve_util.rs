@@ -87,7 +87,7 @@ impl TestServeSession {
         let port_string = port.to_string();
 
         let rojo_process = Command::new(ROJO_PATH)
-            .args(&[
+            .args([
                 "serve",
                 project_path.to_str().unwrap(),
                 "--port",
@@ -150,7 +150,7 @@ impl TestServeSession {
         Ok(serde_json::from_str(&body).expect("Server returned malformed response"))
     }
 
-    pub fn get_api_read(&self, id: Ref) -> Result<ReadResponse, reqwest::Error> {
+    pub fn get_api_read(&self, id: Ref) -> Result<ReadResponse<'_>, reqwest::Error> {
         let url = format!("http://localhost:{}/api/read/{}", self.port, id);
         let body = reqwest::blocking::get(&url)?.text()?;
 
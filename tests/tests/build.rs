@@ -1,6 +1,14 @@
-use std::{fs, path::Path, process::Command};
+use std::{
+    fs,
+    io::Write,
+    path::Path,
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
 
 use insta::assert_snapshot;
+use rbx_dom_weak::types::Variant;
 use tempfile::tempdir;
 
 use crate::rojo_test::io_util::{get_working_dir_path, BUILD_TESTS_PATH, ROJO_PATH};
@@ -24,12 +32,15 @@ gen_build_tests! {
     init_csv_with_children,
     attributes,
     client_in_folder,
+    class_defaults,
     client_init,
     csv_bug_145,
     csv_bug_147,
     csv_in_folder,
     deep_nesting,
+    default_directory_class_name,
     gitkeep,
+    ignore_glob_artifacts,
     ignore_glob_inner,
     ignore_glob_nested,
     ignore_glob_spec,
@@ -37,36 +48,731 @@ gen_build_tests! {
     infer_starter_player,
     init_meta_class_name,
     init_meta_properties,
+    init_meta_screen_gui,
     init_with_children,
     issue_546,
     json_as_lua,
     json_model_in_folder,
     json_model_legacy_name,
+    luau_init,
     module_in_folder,
+    model_path_in_folder,
     module_init,
     nested_runcontext,
     optional,
+    optional_marker,
+    parallel_snapshots,
     project_composed_default,
     project_composed_file,
+    project_json5,
     project_root_name,
     rbxm_in_folder,
     rbxmx_in_folder,
     rbxmx_ref,
+    ref_paths,
     script_meta_disabled,
     server_in_folder,
     server_init,
+    shared_strings,
+    strip_comments,
     txt,
     txt_in_folder,
     unresolved_values,
     weldconstraint,
     sync_rule_alone,
+    sync_rule_asset,
     sync_rule_complex,
     sync_rule_nested_projects,
+    sync_rule_override,
+    sync_rule_text_class,
     no_name_default_project,
     no_name_project,
     no_name_top_level_project,
 }
 
+#[test]
+fn build_unpacked() {
+    let _ = env_logger::try_init();
+
+    let working_dir = get_working_dir_path();
+    let input_path = Path::new(BUILD_TESTS_PATH).join("unpacked_services");
+    let output_dir = tempdir().expect("couldn't create temporary directory");
+
+    let output = Command::new(ROJO_PATH)
+        .args([
+            "build",
+            input_path.to_str().unwrap(),
+            "--unpacked",
+            output_dir.path().to_str().unwrap(),
+        ])
+        .env("RUST_LOG", "error")
+        .current_dir(working_dir)
+        .output()
+        .expect("Couldn't start Rojo");
+
+    print!("{}", String::from_utf8_lossy(&output.stdout));
+    eprint!("{}", String::from_utf8_lossy(&output.stderr));
+
+    assert!(output.status.success(), "Rojo did not exit successfully");
+
+    let mut file_names: Vec<_> = fs::read_dir(output_dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    file_names.sort();
+
+    assert_eq!(
+        file_names,
+        [
+            "ReplicatedStorage.rbxmx",
+            "ServerScriptService.rbxmx",
+            "Workspace.rbxmx",
+            "index.json",
+        ]
+    );
+
+    let index = fs::read_to_string(output_dir.path().join("index.json")).unwrap();
+    let workspace = fs::read_to_string(output_dir.path().join("Workspace.rbxmx")).unwrap();
+
+    let mut settings = insta::Settings::new();
+    settings.set_snapshot_path(
+        Path::new(BUILD_TESTS_PATH)
+            .parent()
+            .unwrap()
+            .join("build-test-snapshots"),
+    );
+
+    settings.bind(|| {
+        assert_snapshot!("unpacked_index", index);
+        assert_snapshot!("unpacked_workspace", workspace);
+    });
+}
+
+#[test]
+fn build_reports_every_error() {
+    let _ = env_logger::try_init();
+
+    let working_dir = get_working_dir_path();
+    let input_path = Path::new(BUILD_TESTS_PATH).join("multiple_errors");
+    let output_dir = tempdir().expect("couldn't create temporary directory");
+    let output_path = output_dir.path().join("multiple_errors.rbxmx");
+
+    let output = Command::new(ROJO_PATH)
+        .args([
+            "build",
+            input_path.to_str().unwrap(),
+            "-o",
+            output_path.to_str().unwrap(),
+        ])
+        .env("RUST_LOG", "error")
+        .current_dir(working_dir)
+        .output()
+        .expect("Couldn't start Rojo");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    eprint!("{}", stderr);
+
+    assert!(!output.status.success(), "Rojo should have failed");
+    assert!(!output_path.exists(), "Rojo should not have written output");
+
+    assert!(stderr.contains("broken.json"));
+    assert!(stderr.contains("broken.toml"));
+    assert!(stderr.contains("because 2 files had errors"));
+}
+
+#[test]
+fn build_strict_case_collisions() {
+    let _ = env_logger::try_init();
+
+    let project_dir = tempdir().expect("couldn't create temporary directory");
+    let project_path = project_dir.path();
+    let output_path = project_path.join("output.rbxmx");
+
+    fs::create_dir(project_path.join("src")).unwrap();
+    fs::write(
+        project_path.join("default.project.json"),
+        r#"{ "name": "collisions", "tree": { "$path": "src" } }"#,
+    )
+    .unwrap();
+    fs::write(project_path.join("src/Foo.lua"), "return 1").unwrap();
+
+    // Case-insensitive filesystems can't have both files at once.
+    if project_path.join("src/foo.lua").exists() {
+        return;
+    }
+    fs::write(project_path.join("src/foo.lua"), "return 2").unwrap();
+
+    let build = |strict: bool| {
+        let mut command = Command::new(ROJO_PATH);
+        command.args([
+            "build",
+            project_path.to_str().unwrap(),
+            "-o",
+            output_path.to_str().unwrap(),
+        ]);
+
+        if strict {
+            command.arg("--strict");
+        }
+
+        command
+            .env("RUST_LOG", "warn")
+            .output()
+            .expect("Couldn't start Rojo")
+    };
+
+    let output = build(false);
+    assert!(output.status.success(), "Rojo did not exit successfully");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("only differ by case"));
+
+    let output = build(true);
+    assert!(!output.status.success(), "Rojo should have failed");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("only differ by case"));
+}
+
+#[test]
+fn build_exit_codes() {
+    let _ = env_logger::try_init();
+
+    let working_dir = get_working_dir_path();
+    let output_dir = tempdir().expect("couldn't create temporary directory");
+
+    let exit_code = |test_name: &str, output_name: &str| {
+        let input_path = Path::new(BUILD_TESTS_PATH).join(test_name);
+        let output_path = output_dir.path().join(output_name);
+
+        Command::new(ROJO_PATH)
+            .args([
+                "build",
+                input_path.to_str().unwrap(),
+                "-o",
+                output_path.to_str().unwrap(),
+            ])
+            .env("RUST_LOG", "error")
+            .current_dir(&working_dir)
+            .output()
+            .expect("Couldn't start Rojo")
+            .status
+            .code()
+    };
+
+    assert_eq!(exit_code("attributes", "output.txt"), Some(6));
+    assert_eq!(exit_code("multiple_errors", "output.rbxmx"), Some(3));
+    assert_eq!(exit_code("does_not_exist", "output.rbxmx"), Some(3));
+    assert_eq!(exit_code("attributes", "missing/output.rbxmx"), Some(4));
+
+    // Invalid arguments are reported by clap, which exits with 2.
+    let usage_error = Command::new(ROJO_PATH)
+        .args(["build", "--not-a-flag"])
+        .env("RUST_LOG", "error")
+        .output()
+        .expect("Couldn't start Rojo");
+    assert_eq!(usage_error.status.code(), Some(2));
+}
+
+#[test]
+fn build_watch() {
+    let _ = env_logger::try_init();
+
+    let project_dir = tempdir().expect("couldn't create temporary directory");
+    let project_path = project_dir.path();
+    let output_path = project_path.join("output.rbxmx");
+
+    fs::create_dir(project_path.join("src")).unwrap();
+    fs::write(
+        project_path.join("default.project.json"),
+        r#"{ "name": "watch", "tree": { "$path": "src" } }"#,
+    )
+    .unwrap();
+    fs::write(project_path.join("src/value.txt"), "before").unwrap();
+
+    let child = Command::new(ROJO_PATH)
+        .args([
+            "build",
+            project_path.to_str().unwrap(),
+            "-o",
+            output_path.to_str().unwrap(),
+            "--watch",
+        ])
+        .env("RUST_LOG", "error")
+        .stdout(Stdio::null())
+        .spawn()
+        .expect("Couldn't start Rojo");
+    let _child = KillOnDrop(child);
+
+    wait_for_output(&output_path, "before");
+
+    fs::write(project_path.join("src/value.txt"), "after").unwrap();
+    wait_for_output(&output_path, "after");
+
+    // A broken file shouldn't stop later changes from being built.
+    fs::write(project_path.join("src/broken.json"), "{").unwrap();
+    thread::sleep(Duration::from_millis(200));
+    fs::write(project_path.join("src/value.txt"), "fixed").unwrap();
+    wait_for_output(&output_path, "fixed");
+}
+
+/// Kills the process when dropped, so that a failing test doesn't leave it
+/// running.
+struct KillOnDrop(Child);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn wait_for_output(output_path: &Path, expected: &str) {
+    let start = Instant::now();
+
+    while start.elapsed() < Duration::from_secs(10) {
+        if let Ok(contents) = fs::read_to_string(output_path) {
+            if contents.contains(expected) {
+                return;
+            }
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    panic!("Output never contained {:?}", expected);
+}
+
+#[test]
+fn build_kinds() {
+    let _ = env_logger::try_init();
+
+    let project_dir = tempdir().expect("couldn't create temporary directory");
+    let project_path = project_dir.path();
+
+    fs::write(
+        project_path.join("default.project.json"),
+        r#"{
+            "name": "Root",
+            "tree": {
+                "$className": "Folder",
+                "First": { "$className": "Folder" },
+                "Second": { "$className": "Configuration" }
+            }
+        }"#,
+    )
+    .unwrap();
+
+    let build = |kind: &str| {
+        let output_path = project_path.join(format!("{}.rbxmx", kind));
+
+        let output = Command::new(ROJO_PATH)
+            .args([
+                "build",
+                project_path.to_str().unwrap(),
+                "-o",
+                output_path.to_str().unwrap(),
+                "--kind",
+                kind,
+            ])
+            .env("RUST_LOG", "error")
+            .output()
+            .expect("Couldn't start Rojo");
+
+        assert!(output.status.success(), "Rojo did not exit successfully");
+
+        let contents = fs::read(&output_path).expect("Couldn't read output file");
+        let dom = rbx_xml::from_reader_default(contents.as_slice()).unwrap();
+
+        dom.root()
+            .children()
+            .iter()
+            .map(|&id| dom.get_by_ref(id).unwrap().name.clone())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(build("model"), ["Root"]);
+    assert_eq!(build("place"), ["First", "Second"]);
+    assert_eq!(build("plugin"), ["First", "Second"]);
+}
+
+#[test]
+fn build_plugin_kind_rejects_data_model() {
+    let _ = env_logger::try_init();
+
+    let working_dir = get_working_dir_path();
+    let input_path = Path::new(BUILD_TESTS_PATH).join("infer_service_name");
+    let output_dir = tempdir().expect("couldn't create temporary directory");
+    let output_path = output_dir.path().join("plugin.rbxmx");
+
+    let output = Command::new(ROJO_PATH)
+        .args([
+            "build",
+            input_path.to_str().unwrap(),
+            "-o",
+            output_path.to_str().unwrap(),
+            "--kind",
+            "plugin",
+        ])
+        .env("RUST_LOG", "error")
+        .current_dir(working_dir)
+        .output()
+        .expect("Couldn't start Rojo");
+
+    assert!(!output.status.success(), "Rojo should have failed");
+    assert!(!output_path.exists(), "Rojo should not have written output");
+}
+
+#[test]
+fn build_select() {
+    let _ = env_logger::try_init();
+
+    let working_dir = get_working_dir_path();
+    let input_path = Path::new(BUILD_TESTS_PATH).join("ref_paths");
+    let output_dir = tempdir().expect("couldn't create temporary directory");
+    let output_path = output_dir.path().join("selected.rbxmx");
+
+    let build = |select: &str| {
+        Command::new(ROJO_PATH)
+            .args([
+                "build",
+                input_path.to_str().unwrap(),
+                "-o",
+                output_path.to_str().unwrap(),
+                "--select",
+                select,
+            ])
+            .env("RUST_LOG", "error")
+            .current_dir(&working_dir)
+            .output()
+            .expect("Couldn't start Rojo")
+    };
+
+    let output = build("ReplicatedStorage.Shared");
+    assert!(output.status.success(), "Rojo did not exit successfully");
+
+    let contents = fs::read(&output_path).expect("Couldn't read output file");
+    let dom = rbx_xml::from_reader_default(contents.as_slice()).unwrap();
+
+    let selected = dom.get_by_ref(dom.root().children()[0]).unwrap();
+    assert_eq!(dom.root().children().len(), 1);
+    assert_eq!(selected.name, "Shared");
+    assert_eq!(selected.class, "Folder");
+
+    let child = dom.get_by_ref(selected.children()[0]).unwrap();
+    assert_eq!(child.name, "Thing");
+
+    let output = build("ReplicatedStorage.Missing");
+    assert!(!output.status.success(), "Rojo should have failed");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("top-level instances are: ReplicatedStorage, Workspace"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn build_exclude_class() {
+    let _ = env_logger::try_init();
+
+    let project_dir = tempdir().expect("couldn't create temporary directory");
+    let project_path = project_dir.path();
+    let output_path = project_path.join("output.rbxmx");
+
+    fs::create_dir_all(project_path.join("src/Nested/Pointer")).unwrap();
+    fs::write(
+        project_path.join("default.project.json"),
+        r#"{ "name": "exclude_class", "tree": { "$path": "src" } }"#,
+    )
+    .unwrap();
+    fs::write(project_path.join("src/Main.server.luau"), "print(1)").unwrap();
+    fs::write(project_path.join("src/Shared.luau"), "return {}").unwrap();
+    fs::write(
+        project_path.join("src/Nested/Inner.server.luau"),
+        "print(2)",
+    )
+    .unwrap();
+    fs::write(
+        project_path.join("src/Nested/Pointer/init.meta.json"),
+        r#"{ "className": "ObjectValue", "properties": { "Value": { "Ref": "Main" } } }"#,
+    )
+    .unwrap();
+
+    let output = Command::new(ROJO_PATH)
+        .args([
+            "build",
+            project_path.to_str().unwrap(),
+            "-o",
+            output_path.to_str().unwrap(),
+            "--exclude-class",
+            "Script",
+        ])
+        .env("RUST_LOG", "error")
+        .output()
+        .expect("Couldn't start Rojo");
+
+    eprint!("{}", String::from_utf8_lossy(&output.stderr));
+    assert!(output.status.success(), "Rojo did not exit successfully");
+
+    let contents = fs::read(&output_path).expect("Couldn't read output file");
+    let dom = rbx_xml::from_reader_default(contents.as_slice()).unwrap();
+
+    let mut instances = Vec::new();
+    let mut to_visit = vec![dom.root_ref()];
+    while let Some(id) = to_visit.pop() {
+        let instance = dom.get_by_ref(id).unwrap();
+        to_visit.extend_from_slice(instance.children());
+        instances.push(instance);
+    }
+
+    let classes: Vec<_> = instances
+        .iter()
+        .map(|instance| instance.class.as_str())
+        .collect();
+    assert!(!classes.contains(&"Script"), "{:?}", classes);
+    assert!(classes.contains(&"ModuleScript"), "{:?}", classes);
+
+    let pointer = instances
+        .iter()
+        .find(|instance| instance.name == "Pointer")
+        .unwrap();
+    assert!(
+        !matches!(pointer.properties.get("Value"), Some(Variant::Ref(target)) if target.is_some()),
+        "{:?}",
+        pointer.properties.get("Value")
+    );
+}
+
+#[test]
+fn build_project_from_stdin() {
+    let _ = env_logger::try_init();
+
+    let project_dir = tempdir().expect("couldn't create temporary directory");
+    let project_path = project_dir.path();
+    let output_path = project_path.join("output.rbxmx");
+
+    fs::create_dir(project_path.join("src")).unwrap();
+    fs::write(project_path.join("src/hello.txt"), "Hello, world!").unwrap();
+
+    let mut child = Command::new(ROJO_PATH)
+        .args(["build", "-", "-o", output_path.to_str().unwrap()])
+        .env("RUST_LOG", "error")
+        .current_dir(project_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Couldn't start Rojo");
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin
+        .write_all(br#"{ "name": "piped", "tree": { "$path": "src" } }"#)
+        .unwrap();
+    drop(stdin);
+
+    let output = child.wait_with_output().unwrap();
+    eprint!("{}", String::from_utf8_lossy(&output.stderr));
+    assert!(output.status.success(), "Rojo did not exit successfully");
+
+    let contents = fs::read(&output_path).expect("Couldn't read output file");
+    let dom = rbx_xml::from_reader_default(contents.as_slice()).unwrap();
+
+    let root = dom.get_by_ref(dom.root().children()[0]).unwrap();
+    assert_eq!(root.name, "piped");
+
+    let hello = dom.get_by_ref(root.children()[0]).unwrap();
+    assert_eq!(hello.name, "hello");
+    assert_eq!(hello.class, "StringValue");
+}
+
+#[test]
+fn build_hashes() {
+    let _ = env_logger::try_init();
+
+    let working_dir = get_working_dir_path();
+    let input_path = Path::new(BUILD_TESTS_PATH).join("attributes");
+    let output_dir = tempdir().expect("couldn't create temporary directory");
+
+    let build = |file_name: &str, algorithm: &str| {
+        let output_path = output_dir.path().join(file_name);
+
+        let output = Command::new(ROJO_PATH)
+            .args([
+                "build",
+                input_path.to_str().unwrap(),
+                "-o",
+                output_path.to_str().unwrap(),
+                "--hash",
+                algorithm,
+            ])
+            .env("RUST_LOG", "error")
+            .current_dir(&working_dir)
+            .output()
+            .expect("Couldn't start Rojo");
+
+        assert!(output.status.success(), "Rojo did not exit successfully");
+
+        let hash_path = output_dir
+            .path()
+            .join(format!("{}.{}", file_name, algorithm));
+        let hash = fs::read_to_string(hash_path).expect("Couldn't read hash file");
+
+        let (digest, name) = hash.trim_end().split_once("  ").unwrap();
+        assert_eq!(name, file_name);
+
+        digest.to_owned()
+    };
+
+    for algorithm in ["sha256", "blake3"] {
+        let first = build("first.rbxm", algorithm);
+        let second = build("second.rbxm", algorithm);
+
+        assert_eq!(first.len(), 64);
+        assert_eq!(first, second);
+    }
+
+    let output_path = output_dir.path().join("unhashed.rbxm");
+    let output = Command::new(ROJO_PATH)
+        .args([
+            "build",
+            input_path.to_str().unwrap(),
+            "-o",
+            output_path.to_str().unwrap(),
+        ])
+        .env("RUST_LOG", "error")
+        .current_dir(&working_dir)
+        .output()
+        .expect("Couldn't start Rojo");
+
+    assert!(output.status.success(), "Rojo did not exit successfully");
+    assert!(!output_dir.path().join("unhashed.rbxm.sha256").exists());
+}
+
+#[test]
+fn build_compression() {
+    let _ = env_logger::try_init();
+
+    let working_dir = get_working_dir_path();
+    let input_path = Path::new(BUILD_TESTS_PATH).join("deep_nesting");
+    let output_dir = tempdir().expect("couldn't create temporary directory");
+
+    let build = |level: &str| {
+        let output_path = output_dir.path().join(format!("{}.rbxm", level));
+
+        let output = Command::new(ROJO_PATH)
+            .args([
+                "build",
+                input_path.to_str().unwrap(),
+                "-o",
+                output_path.to_str().unwrap(),
+                "--compression",
+                level,
+            ])
+            .env("RUST_LOG", "error")
+            .current_dir(&working_dir)
+            .output()
+            .expect("Couldn't start Rojo");
+
+        assert!(output.status.success(), "Rojo did not exit successfully");
+
+        let contents = fs::read(output_path).expect("Couldn't read output file");
+        rbx_binary::from_reader(contents.as_slice()).expect("Couldn't decode output file");
+
+        contents.len()
+    };
+
+    let fast = build("fast");
+    let best = build("best");
+
+    assert!(
+        best <= fast,
+        "best ({}) was larger than fast ({})",
+        best,
+        fast
+    );
+}
+
+#[test]
+fn build_dedups_shared_strings() {
+    let _ = env_logger::try_init();
+
+    let working_dir = get_working_dir_path();
+    let input_path = Path::new(BUILD_TESTS_PATH).join("shared_strings");
+    let output_dir = tempdir().expect("couldn't create temporary directory");
+    let output_path = output_dir.path().join("shared_strings.rbxm");
+
+    let output = Command::new(ROJO_PATH)
+        .args([
+            "build",
+            input_path.to_str().unwrap(),
+            "-o",
+            output_path.to_str().unwrap(),
+        ])
+        .env("RUST_LOG", "error")
+        .current_dir(working_dir)
+        .output()
+        .expect("Couldn't start Rojo");
+
+    assert!(output.status.success(), "Rojo did not exit successfully");
+
+    let contents = fs::read(&output_path).expect("Couldn't read output file");
+
+    // Both models in the project contain the same SharedString, so it should
+    // only be stored once. The SSTR chunk holds a version and a count,
+    // followed by a 16 byte hash and a length-prefixed buffer for each entry.
+    // rbx_binary also stores an empty string to use as the property's default.
+    let entry_len = |data: &str| 16 + 4 + data.len();
+    let expected_len = 4 + 4 + entry_len("") + entry_len("Hello, shared strings!");
+
+    assert_eq!(
+        binary_chunk_len(&contents, b"SSTR"),
+        Some(expected_len as u32)
+    );
+}
+
+#[test]
+fn build_is_deterministic() {
+    let _ = env_logger::try_init();
+
+    let working_dir = get_working_dir_path();
+    let output_dir = tempdir().expect("couldn't create temporary directory");
+
+    for test_name in ["attributes", "rbxmx_ref", "deep_nesting"] {
+        let input_path = Path::new(BUILD_TESTS_PATH).join(test_name);
+
+        for extension in ["rbxmx", "rbxm"] {
+            let outputs: Vec<_> = (0..2)
+                .map(|run| {
+                    let output_path = output_dir
+                        .path()
+                        .join(format!("{}-{}.{}", test_name, run, extension));
+
+                    let output = Command::new(ROJO_PATH)
+                        .args([
+                            "build",
+                            input_path.to_str().unwrap(),
+                            "-o",
+                            output_path.to_str().unwrap(),
+                        ])
+                        .env("RUST_LOG", "error")
+                        .current_dir(&working_dir)
+                        .output()
+                        .expect("Couldn't start Rojo");
+
+                    assert!(output.status.success(), "Rojo did not exit successfully");
+
+                    fs::read(&output_path).expect("Couldn't read output file")
+                })
+                .collect();
+
+            assert!(
+                outputs[0] == outputs[1],
+                "building {} to .{} twice gave different results",
+                test_name,
+                extension
+            );
+        }
+    }
+}
+
 fn run_build_test(test_name: &str) {
     let working_dir = get_working_dir_path();
 
@@ -107,3 +813,32 @@ fn run_build_test(test_name: &str) {
         assert_snapshot!(test_name, contents);
     });
 }
+
+/// Finds the first chunk with the given name in a binary model file and
+/// returns its uncompressed length.
+fn binary_chunk_len(contents: &[u8], name: &[u8; 4]) -> Option<u32> {
+    let read_u32 =
+        |offset: usize| u32::from_le_bytes(contents[offset..offset + 4].try_into().unwrap());
+
+    // Binary files start with a 32 byte header, followed by chunks that each
+    // have a 16 byte header of their own.
+    let mut offset = 32;
+
+    while offset + 16 <= contents.len() {
+        let compressed_len = read_u32(offset + 4);
+        let len = read_u32(offset + 8);
+
+        if &contents[offset..offset + 4] == name {
+            return Some(len);
+        }
+
+        let stored_len = if compressed_len == 0 {
+            len
+        } else {
+            compressed_len
+        };
+        offset += 16 + stored_len as usize;
+    }
+
+    None
+}
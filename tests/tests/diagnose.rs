@@ -0,0 +1,69 @@
+use std::{fs, path::Path, process::Command};
+
+use insta::assert_yaml_snapshot;
+use serde_json::Value;
+use tempfile::tempdir;
+
+use crate::rojo_test::io_util::{ROJO_PATH, SOURCEMAP_TESTS_PATH};
+
+#[test]
+fn nested_module() {
+    let _ = env_logger::try_init();
+
+    let project_path = Path::new(SOURCEMAP_TESTS_PATH).join("nested_module");
+    let report = run_diagnose(&project_path);
+
+    assert_eq!(report["instanceCount"], 10);
+    assert_eq!(report["unreadableFiles"], Value::Array(Vec::new()));
+    assert_eq!(report["snapshotError"], Value::Null);
+    assert!(!report["watchedPaths"].as_array().unwrap().is_empty());
+
+    let mut settings = insta::Settings::new();
+    settings.set_snapshot_path(
+        Path::new(SOURCEMAP_TESTS_PATH)
+            .parent()
+            .unwrap()
+            .join("diagnose-test-snapshots"),
+    );
+    settings.bind(|| {
+        assert_yaml_snapshot!("nested_module_class_counts", report["classCounts"]);
+    });
+}
+
+#[test]
+fn snapshot_error() {
+    let _ = env_logger::try_init();
+
+    let project_dir = tempdir().expect("Couldn't create temporary directory");
+    fs::write(
+        project_dir.path().join("default.project.json"),
+        r#"{ "tree": { "$path": "missing" } }"#,
+    )
+    .unwrap();
+
+    let report = run_diagnose(project_dir.path());
+
+    assert_eq!(report["instanceCount"], 0);
+    assert!(report["snapshotError"]
+        .as_str()
+        .unwrap()
+        .contains("missing"));
+}
+
+/// Runs `rojo diagnose --json` on the given project, which must succeed even
+/// if the project has problems.
+fn run_diagnose(project_path: &Path) -> Value {
+    let output = Command::new(ROJO_PATH)
+        .args(["diagnose", "--json"])
+        .arg(project_path)
+        .output()
+        .expect("Couldn't start Rojo");
+
+    assert!(
+        output.status.success(),
+        "rojo diagnose failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    serde_json::from_slice(&output.stdout).expect("rojo diagnose did not output JSON")
+}
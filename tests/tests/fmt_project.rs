@@ -0,0 +1,69 @@
+use std::{fs, path::Path, process::Command};
+
+use librojo::Project;
+use tempfile::tempdir;
+
+use crate::rojo_test::io_util::{FMT_PROJECT_TESTS_PATH, ROJO_PATH};
+
+#[test]
+fn messy() {
+    let _ = env_logger::try_init();
+
+    let test_path = Path::new(FMT_PROJECT_TESTS_PATH).join("messy");
+    let expected = fs::read_to_string(test_path.join("expected.project.json")).unwrap();
+
+    let project_dir = tempdir().expect("Couldn't create temporary directory");
+    let project_path = project_dir.path().join("default.project.json");
+    fs::copy(test_path.join("default.project.json"), &project_path).unwrap();
+
+    assert!(!run_fmt_project(&project_path, &["--check"]));
+
+    let stdout = Command::new(ROJO_PATH)
+        .args(["fmt-project", "--stdout"])
+        .arg(&project_path)
+        .output()
+        .expect("Couldn't start Rojo");
+    assert_eq!(String::from_utf8(stdout.stdout).unwrap(), expected);
+
+    let original = load_project(&project_path);
+
+    assert!(run_fmt_project(&project_path, &[]));
+    assert_eq!(fs::read_to_string(&project_path).unwrap(), expected);
+    assert_eq!(load_project(&project_path), original);
+
+    assert!(run_fmt_project(&project_path, &["--check"]));
+}
+
+#[test]
+fn integer_properties() {
+    let _ = env_logger::try_init();
+
+    let test_path = Path::new(FMT_PROJECT_TESTS_PATH).join("messy");
+    let expected = fs::read_to_string(test_path.join("expected.project.json")).unwrap();
+
+    let project_dir = tempdir().expect("Couldn't create temporary directory");
+    let project_path = project_dir.path().join("default.project.json");
+    fs::copy(test_path.join("expected.project.json"), &project_path).unwrap();
+
+    assert!(run_fmt_project(&project_path, &["--check"]));
+
+    assert!(run_fmt_project(&project_path, &[]));
+    assert_eq!(fs::read_to_string(&project_path).unwrap(), expected);
+}
+
+fn load_project(project_path: &Path) -> Project {
+    Project::load_fuzzy(project_path)
+        .expect("Couldn't load project")
+        .expect("Project file is missing")
+}
+
+/// Runs `rojo fmt-project` and returns whether it succeeded.
+fn run_fmt_project(project_path: &Path, args: &[&str]) -> bool {
+    Command::new(ROJO_PATH)
+        .arg("fmt-project")
+        .args(args)
+        .arg(project_path)
+        .status()
+        .expect("Couldn't start Rojo")
+        .success()
+}
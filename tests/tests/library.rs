@@ -0,0 +1,109 @@
+use std::path::Path;
+
+use librojo::{build_tree, patch_tree};
+use memofs::{InMemoryFs, Vfs, VfsSnapshot};
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+
+fn in_memory_project() -> Vfs {
+    let mut imfs = InMemoryFs::new();
+    imfs.load_snapshot(
+        "/game",
+        VfsSnapshot::dir([
+            (
+                "default.project.json",
+                VfsSnapshot::file(
+                    r#"{
+                        "name": "in-memory",
+                        "tree": {
+                            "$className": "DataModel",
+                            "ReplicatedStorage": {
+                                "Shared": { "$path": "src" }
+                            }
+                        }
+                    }"#,
+                ),
+            ),
+            (
+                "src",
+                VfsSnapshot::dir([("hello.lua", VfsSnapshot::file("return 'hello'"))]),
+            ),
+        ]),
+    )
+    .unwrap();
+
+    Vfs::new(imfs)
+}
+
+#[test]
+fn build_tree_from_memory() {
+    let vfs = in_memory_project();
+    let dom = build_tree(&vfs, Path::new("/game")).unwrap();
+
+    let root = dom.root();
+    assert_eq!(root.name, "in-memory");
+    assert_eq!(root.class, "DataModel");
+
+    let storage = dom.get_by_ref(root.children()[0]).unwrap();
+    assert_eq!(storage.class, "ReplicatedStorage");
+
+    let shared = dom.get_by_ref(storage.children()[0]).unwrap();
+    assert_eq!(shared.name, "Shared");
+    assert_eq!(shared.class, "Folder");
+
+    let hello = dom.get_by_ref(shared.children()[0]).unwrap();
+    assert_eq!(hello.name, "hello");
+    assert_eq!(hello.class, "ModuleScript");
+}
+
+#[test]
+fn build_tree_without_project() {
+    let vfs = Vfs::new(InMemoryFs::new());
+
+    assert!(build_tree(&vfs, Path::new("/missing")).is_err());
+}
+
+#[test]
+fn patch_tree_from_memory() {
+    let mut input = WeakDom::new(InstanceBuilder::new("DataModel").with_name("loaded"));
+    let root = input.root_ref();
+    let storage_id = input.insert(root, InstanceBuilder::new("ReplicatedStorage"));
+    let workspace_id = input.insert(root, InstanceBuilder::new("Workspace"));
+    input.insert(
+        workspace_id,
+        InstanceBuilder::new("Part").with_name("Baseplate"),
+    );
+
+    // Shared comes from a directory, so anything in it that the directory
+    // doesn't describe is removed.
+    let shared_id = input.insert(
+        storage_id,
+        InstanceBuilder::new("Folder").with_name("Shared"),
+    );
+    input.insert(
+        shared_id,
+        InstanceBuilder::new("ModuleScript").with_name("stale"),
+    );
+
+    let vfs = in_memory_project();
+    let dom = patch_tree(input, &vfs, Path::new("/game")).unwrap();
+
+    let root = dom.root();
+    assert_eq!(root.name, "in-memory");
+
+    // ReplicatedStorage was already there, so it's updated instead of being
+    // replaced. Workspace isn't part of the project, but the root ignores
+    // unknown instances, so it's kept.
+    assert_eq!(root.children(), [storage_id, workspace_id]);
+
+    let workspace = dom.get_by_ref(workspace_id).unwrap();
+    assert_eq!(workspace.children().len(), 1);
+
+    let storage = dom.get_by_ref(storage_id).unwrap();
+    assert_eq!(storage.children(), [shared_id]);
+
+    let shared = dom.get_by_ref(shared_id).unwrap();
+    assert_eq!(shared.children().len(), 1);
+
+    let hello = dom.get_by_ref(shared.children()[0]).unwrap();
+    assert_eq!(hello.name, "hello");
+}
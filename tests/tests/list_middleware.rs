@@ -0,0 +1,56 @@
+use std::{path::Path, process::Command};
+
+use serde_json::Value;
+
+use crate::rojo_test::io_util::{BUILD_TESTS_PATH, ROJO_PATH};
+
+#[test]
+fn default_rules() {
+    let _ = env_logger::try_init();
+
+    let rules = run_list_middleware(&Path::new(BUILD_TESTS_PATH).join("txt"));
+
+    assert!(rules.iter().all(|rule| rule["source"] == "default"));
+    assert!(rules
+        .iter()
+        .any(|rule| rule["pattern"] == "*.server.luau" && rule["use"] == "serverScript"));
+    assert!(rules
+        .iter()
+        .any(|rule| rule["pattern"] == "*.json" && rule["exclude"] == "*.meta.json"));
+}
+
+#[test]
+fn project_rules_come_first() {
+    let _ = env_logger::try_init();
+
+    let rules = run_list_middleware(&Path::new(BUILD_TESTS_PATH).join("sync_rule_complex"));
+
+    let project_rules: Vec<_> = rules
+        .iter()
+        .take_while(|rule| rule["source"] == "project")
+        .collect();
+    assert_eq!(project_rules.len(), 5);
+    assert_eq!(project_rules[0]["pattern"], "*.module");
+    assert_eq!(project_rules[0]["use"], "moduleScript");
+    assert_eq!(project_rules[4]["suffix"], ".dog.rojo2");
+
+    assert!(rules[5..].iter().all(|rule| rule["source"] == "default"));
+}
+
+/// Runs `rojo list-middleware --json` on the given project and returns the
+/// rules it lists.
+fn run_list_middleware(project_path: &Path) -> Vec<Value> {
+    let output = Command::new(ROJO_PATH)
+        .args(["list-middleware", "--json"])
+        .arg(project_path)
+        .output()
+        .expect("Couldn't start Rojo");
+
+    assert!(
+        output.status.success(),
+        "rojo list-middleware failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    serde_json::from_slice(&output.stdout).expect("rojo list-middleware did not output JSON")
+}
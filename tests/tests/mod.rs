@@ -1,2 +1,7 @@
 mod build;
+mod diagnose;
+mod fmt_project;
+mod library;
+mod list_middleware;
 mod serve;
+mod sourcemap;
@@ -0,0 +1,150 @@
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use insta::assert_yaml_snapshot;
+use serde_json::Value;
+use tempfile::tempdir;
+
+use crate::rojo_test::io_util::{
+    copy_recursive, get_working_dir_path, KillOnDrop, ROJO_PATH, SOURCEMAP_TESTS_PATH,
+};
+
+#[test]
+fn nested_module() {
+    let _ = env_logger::try_init();
+
+    let sourcemap = run_sourcemap("nested_module", &[]);
+
+    with_snapshot_settings(|| {
+        assert_yaml_snapshot!("nested_module", sourcemap);
+    });
+}
+
+#[test]
+fn include_non_scripts() {
+    let _ = env_logger::try_init();
+
+    let scripts_only = run_sourcemap("nested_module", &[]);
+    let everything = run_sourcemap("nested_module", &["--include-non-scripts"]);
+
+    assert_ne!(scripts_only, everything);
+
+    with_snapshot_settings(|| {
+        assert_yaml_snapshot!("nested_module_include_non_scripts", everything);
+    });
+}
+
+#[test]
+fn watch() {
+    let _ = env_logger::try_init();
+
+    let project_dir = tempdir().expect("Couldn't create temporary directory");
+    let project_path = project_dir.path().canonicalize().unwrap();
+    copy_recursive(
+        &Path::new(SOURCEMAP_TESTS_PATH).join("nested_module"),
+        &project_path,
+    )
+    .expect("Couldn't copy project to temporary directory");
+
+    let output_dir = tempdir().expect("Couldn't create temporary directory");
+    let output_path = output_dir.path().join("sourcemap.json");
+
+    let _rojo_process = KillOnDrop(
+        Command::new(ROJO_PATH)
+            .args([
+                "sourcemap",
+                project_path.to_str().unwrap(),
+                "--watch",
+                "-o",
+                output_path.to_str().unwrap(),
+            ])
+            .env("RUST_LOG", "error")
+            .current_dir(get_working_dir_path())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Couldn't start Rojo"),
+    );
+
+    let util_path = project_path.join("src/shared/Util");
+
+    wait_for_sourcemap(&output_path, |sourcemap| sourcemap.contains("\"Strings\""));
+
+    fs::write(util_path.join("Numbers.lua"), "return {}").unwrap();
+    wait_for_sourcemap(&output_path, |sourcemap| sourcemap.contains("\"Numbers\""));
+
+    fs::remove_file(util_path.join("Strings.lua")).unwrap();
+    wait_for_sourcemap(&output_path, |sourcemap| !sourcemap.contains("\"Strings\""));
+}
+
+fn run_sourcemap(test_name: &str, args: &[&str]) -> Value {
+    let input_path = Path::new(SOURCEMAP_TESTS_PATH).join(test_name);
+
+    let output = Command::new(ROJO_PATH)
+        .arg("sourcemap")
+        .arg(input_path.to_str().unwrap())
+        .args(args)
+        .env("RUST_LOG", "error")
+        .current_dir(get_working_dir_path())
+        .output()
+        .expect("Couldn't start Rojo");
+
+    eprint!("{}", String::from_utf8_lossy(&output.stderr));
+
+    assert!(output.status.success(), "Rojo did not exit successfully");
+
+    let mut sourcemap =
+        serde_json::from_slice(&output.stdout).expect("Rojo did not output valid JSON");
+    normalize_file_paths(&mut sourcemap);
+    sourcemap
+}
+
+/// Polls the sourcemap written by a watching Rojo process until it satisfies
+/// the given condition.
+fn wait_for_sourcemap(path: &Path, condition: impl Fn(&str) -> bool) {
+    const MAX_TRIES: u32 = 100;
+
+    for _ in 0..MAX_TRIES {
+        if let Ok(contents) = fs::read_to_string(path) {
+            // The file may be read while it's only been partially written.
+            if serde_json::from_str::<Value>(&contents).is_ok() && condition(&contents) {
+                return;
+            }
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    panic!("Sourcemap at {} was never updated", path.display());
+}
+
+/// Rewrites every path in a sourcemap to use forward slashes so that
+/// snapshots are the same on every platform.
+fn normalize_file_paths(node: &mut Value) {
+    if let Some(Value::Array(paths)) = node.get_mut("filePaths") {
+        for path in paths {
+            if let Value::String(path) = path {
+                *path = path.replace('\\', "/");
+            }
+        }
+    }
+
+    if let Some(Value::Array(children)) = node.get_mut("children") {
+        children.iter_mut().for_each(normalize_file_paths);
+    }
+}
+
+fn with_snapshot_settings(callback: impl FnOnce()) {
+    let mut settings = insta::Settings::new();
+    settings.set_snapshot_path(
+        Path::new(SOURCEMAP_TESTS_PATH)
+            .parent()
+            .unwrap()
+            .join("sourcemap-test-snapshots"),
+    );
+    settings.bind(callback);
+}